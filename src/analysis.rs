@@ -0,0 +1,30 @@
+//! ./analysis.rs
+//!
+//! Static analysis that puts numbers on the ideas the examples illustrate.
+//! Everything works on parsed Rust source, nothing is compiled or executed.
+
+mod level;
+mod source;
+
+pub mod affinity;
+pub mod clusters;
+pub mod control;
+pub mod dashboard;
+pub mod dependencies;
+pub mod external;
+pub mod gate;
+pub mod graph;
+pub mod history;
+pub mod lcom;
+pub mod metrics;
+pub mod modules;
+pub mod report;
+pub mod ripple;
+pub mod shared_state;
+pub mod stamp;
+pub mod summary;
+pub mod workspace;
+
+pub use level::{CohesionLevel, CouplingLevel};
+pub(crate) use source::macro_args;
+pub use source::{Error, Location, Scope, SourceFile};
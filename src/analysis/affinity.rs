@@ -0,0 +1,641 @@
+//! ./analysis/affinity.rs
+//!
+//! Coincidental cohesion: a module whose items have nothing to do with one
+//! another. Each pair of items is scored on three signals, as the Jaccard
+//! similarity of
+//!
+//! - the named types in their signatures or fields
+//! - the functions and methods they call
+//! - the words in their identifiers, `parse_hex_to_u32` giving `parse`, `hex`
+//!   and `u32`
+//!
+//! A pair's relatedness is the mean over the signals either item has at all,
+//! or 1 when the two are linked, directly or through other items of the
+//! module: one calls or names the other, or somewhere in the crate the
+//! result of one is passed to the other. The steps of a pipeline share
+//! little but the function running them. A module's relatedness is the mean
+//! over its pairs. Below the threshold it is coincidental, otherwise taken
+//! as functional.
+//!
+//! The examples' `problem` and `solution` demos are left out, and a module
+//! with nothing else, only submodules, is not scored.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+use super::dependencies::typed_params;
+use super::modules::Crate;
+use super::source::macro_args;
+use super::{CohesionLevel, Error, Location};
+
+/// Relatedness under which a module counts as coincidental.
+pub const DEFAULT_THRESHOLD: f64 = 0.2;
+
+/// Types that say nothing about what an item is for.
+const GENERIC_TYPES: [&str; 25] = [
+    "bool", "char", "str", "String", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16",
+    "i32", "i64", "i128", "isize", "f32", "f64", "Option", "Result", "Vec", "Box", "Rc", "Arc",
+    "Self",
+];
+
+/// Calls that every kind of code makes.
+const GENERIC_CALLS: [&str; 12] = [
+    "new",
+    "clone",
+    "to_string",
+    "into",
+    "from",
+    "unwrap",
+    "expect",
+    "ok",
+    "iter",
+    "collect",
+    "len",
+    "push",
+];
+
+/// What the pairs of items are scored on, in the order of
+/// [`Profile::overlaps`].
+const SIGNALS: [&str; 3] = ["types", "calls", "vocabulary"];
+
+/// The functions every example module runs its variants through.
+const DEMOS: [&str; 2] = ["problem", "solution"];
+
+const STOP_WORDS: [&str; 9] = [
+    "self", "new", "get", "set", "the", "and", "for", "with", "from",
+];
+
+/// How related two items of a module are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pair {
+    pub first: String,
+    pub second: String,
+    pub relatedness: f64,
+}
+
+/// Relatedness of one module's items.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleAffinity {
+    pub module: String,
+    /// Item names; methods as `Type::method`.
+    pub items: Vec<String>,
+    pub pairs: Vec<Pair>,
+    /// Mean relatedness of all pairs, 1 for a module of a single item.
+    pub relatedness: f64,
+    /// The signals no two items share, from `types`, `calls` and
+    /// `vocabulary`, then `links` when no item calls, names or feeds
+    /// another.
+    pub missing: Vec<&'static str>,
+    /// `Function` or `Coincidental`.
+    pub level: CohesionLevel,
+    /// The module's first item.
+    pub location: Location,
+}
+
+pub fn analyze_crate(dir: impl AsRef<Path>, threshold: f64) -> Result<Vec<ModuleAffinity>, Error> {
+    Ok(analyze(&Crate::load(dir)?, threshold))
+}
+
+/// Every module with items outside test modules, least related first.
+pub fn analyze(krate: &Crate, threshold: f64) -> Vec<ModuleAffinity> {
+    let mut flows = Flows::default();
+    for module in krate.modules.iter().filter(|module| !module.test) {
+        for item in &module.items {
+            flows.visit_item(item);
+        }
+    }
+    let mut modules: Vec<ModuleAffinity> = krate
+        .modules
+        .iter()
+        .enumerate()
+        .filter(|(_, module)| !module.test)
+        .filter_map(|(index, module)| {
+            let profiles: Vec<Profile> = module
+                .items
+                .iter()
+                .flat_map(profiles)
+                .filter(|profile| !DEMOS.contains(&profile.name.as_str()))
+                .collect();
+            if profiles.is_empty() {
+                return None;
+            }
+            let location = krate.location(index, module.items.first()?.span());
+            let groups = linked(&profiles, &flows.links);
+            let mut shared = [false; SIGNALS.len()];
+            let mut pairs = Vec::new();
+            for (index, first) in profiles.iter().enumerate() {
+                for (offset, second) in profiles[index + 1..].iter().enumerate() {
+                    for (shared, overlap) in shared.iter_mut().zip(first.overlaps(second)) {
+                        *shared |= overlap.is_some_and(|overlap| overlap > 0.0);
+                    }
+                    pairs.push(Pair {
+                        first: first.name.clone(),
+                        second: second.name.clone(),
+                        relatedness: if groups[index] == groups[index + 1 + offset] {
+                            1.0
+                        } else {
+                            first.relatedness(second)
+                        },
+                    });
+                }
+            }
+            let relatedness = if pairs.is_empty() {
+                1.0
+            } else {
+                pairs.iter().map(|pair| pair.relatedness).sum::<f64>() / pairs.len() as f64
+            };
+            let mut missing: Vec<&'static str> = SIGNALS
+                .into_iter()
+                .zip(shared)
+                .filter(|&(_, shared)| !shared)
+                .map(|(signal, _)| signal)
+                .collect();
+            if groups.iter().enumerate().all(|(at, &group)| at == group) {
+                missing.push("links");
+            }
+            Some(ModuleAffinity {
+                module: module.path.clone(),
+                missing,
+                items: profiles.into_iter().map(|profile| profile.name).collect(),
+                pairs,
+                location,
+                relatedness,
+                level: if relatedness < threshold {
+                    CohesionLevel::Coincidental
+                } else {
+                    CohesionLevel::Function
+                },
+            })
+        })
+        .collect();
+    modules.sort_by(|a, b| {
+        a.relatedness
+            .total_cmp(&b.relatedness)
+            .then_with(|| a.module.cmp(&b.module))
+    });
+    modules
+}
+
+/// The group of linked items each profile falls in, as the index of one
+/// of them.
+fn linked(profiles: &[Profile], flows: &BTreeSet<(String, String)>) -> Vec<usize> {
+    let mut groups: Vec<usize> = (0..profiles.len()).collect();
+    fn root(groups: &mut [usize], mut at: usize) -> usize {
+        while groups[at] != at {
+            at = groups[at];
+        }
+        at
+    }
+    for (first, a) in profiles.iter().enumerate() {
+        for (second, b) in profiles.iter().enumerate().skip(first + 1) {
+            let flow = |from: &Profile, to: &Profile| {
+                flows.contains(&(from.key().to_string(), to.key().to_string()))
+            };
+            if a.names(b) || b.names(a) || flow(a, b) || flow(b, a) {
+                let (a, b) = (root(&mut groups, first), root(&mut groups, second));
+                groups[b] = a;
+            }
+        }
+    }
+    (0..profiles.len())
+        .map(|at| root(&mut groups, at))
+        .collect()
+}
+
+/// What one item mentions.
+#[derive(Default)]
+struct Profile {
+    name: String,
+    types: BTreeSet<String>,
+    calls: BTreeSet<String>,
+    words: BTreeSet<String>,
+    /// Every name in the item's signature and body, generic ones included.
+    paths: BTreeSet<String>,
+}
+
+impl Profile {
+    fn relatedness(&self, other: &Profile) -> f64 {
+        let signals: Vec<f64> = self.overlaps(other).into_iter().flatten().collect();
+        if signals.is_empty() {
+            0.0
+        } else {
+            signals.iter().sum::<f64>() / signals.len() as f64
+        }
+    }
+
+    /// Jaccard similarity on each of the [`SIGNALS`], `None` for one
+    /// neither item has.
+    fn overlaps(&self, other: &Profile) -> [Option<f64>; SIGNALS.len()] {
+        [
+            (&self.types, &other.types),
+            (&self.calls, &other.calls),
+            (&self.words, &other.words),
+        ]
+        .map(|(a, b)| {
+            (!a.is_empty() || !b.is_empty())
+                .then(|| a.intersection(b).count() as f64 / a.union(b).count() as f64)
+        })
+    }
+
+    /// What the item is called by: its name, or a method's own name.
+    fn key(&self) -> &str {
+        self.name.rsplit("::").next().unwrap_or(&self.name)
+    }
+
+    /// Whether this item calls or names `other`.
+    fn names(&self, other: &Profile) -> bool {
+        self.paths.contains(other.key()) || self.name.starts_with(&format!("{}::", other.name))
+    }
+
+    fn add_type(&mut self, ty: &syn::Type, owner: Option<&str>) {
+        let mut names = TypeNames(Vec::new());
+        names.visit_type(ty);
+        for name in names.0 {
+            self.paths.insert(name.clone());
+            match (name.as_str(), owner) {
+                ("Self", Some(owner)) => self.types.insert(owner.to_string()),
+                (name, _) if GENERIC_TYPES.contains(&name) => continue,
+                _ => self.types.insert(name),
+            };
+        }
+    }
+
+    fn add_words(&mut self, ident: &str) {
+        self.words.extend(words(ident));
+    }
+
+    fn add_function(
+        &mut self,
+        sig: &syn::Signature,
+        block: Option<&syn::Block>,
+        owner: Option<&str>,
+    ) {
+        self.add_words(&sig.ident.to_string());
+        if let Some(owner) = owner {
+            if sig.receiver().is_some() {
+                self.types.insert(owner.to_string());
+            }
+            // The type a method belongs to is part of its vocabulary.
+            self.add_words(owner);
+        }
+        for (name, ty) in typed_params(sig) {
+            self.add_words(&name);
+            self.add_type(ty, owner);
+        }
+        if let syn::ReturnType::Type(_, ty) = &sig.output {
+            self.add_type(ty, owner);
+        }
+        if let Some(block) = block {
+            let mut body = Body::default();
+            body.visit_block(block);
+            self.calls.extend(body.calls);
+            self.paths.extend(body.paths);
+            for field in body.fields {
+                self.add_words(&field);
+            }
+        }
+    }
+
+    fn add_fields(&mut self, fields: &syn::Fields, owner: &str) {
+        for field in fields {
+            if let Some(ident) = &field.ident {
+                self.add_words(&ident.to_string());
+            }
+            self.add_type(&field.ty, Some(owner));
+        }
+    }
+}
+
+/// Profiles of an item, one per method for an `impl` block.
+fn profiles(item: &syn::Item) -> Vec<Profile> {
+    let mut profile = Profile::default();
+    match item {
+        syn::Item::Fn(item) => {
+            profile.name = item.sig.ident.to_string();
+            profile.add_function(&item.sig, Some(&item.block), None);
+        }
+        syn::Item::Struct(item) => {
+            let name = item.ident.to_string();
+            profile.types.insert(name.clone());
+            profile.add_words(&name);
+            profile.add_fields(&item.fields, &name);
+            profile.name = name;
+        }
+        syn::Item::Enum(item) => {
+            let name = item.ident.to_string();
+            profile.types.insert(name.clone());
+            profile.add_words(&name);
+            for variant in &item.variants {
+                profile.add_words(&variant.ident.to_string());
+                profile.add_fields(&variant.fields, &name);
+            }
+            profile.name = name;
+        }
+        syn::Item::Trait(item) => {
+            let name = item.ident.to_string();
+            profile.types.insert(name.clone());
+            for trait_item in &item.items {
+                if let syn::TraitItem::Fn(method) = trait_item {
+                    profile.add_function(&method.sig, method.default.as_ref(), Some(&name));
+                }
+            }
+            profile.name = name;
+        }
+        syn::Item::Impl(item) => {
+            let syn::Type::Path(owner) = &*item.self_ty else {
+                return Vec::new();
+            };
+            let Some(owner) = owner.path.segments.last() else {
+                return Vec::new();
+            };
+            let owner = owner.ident.to_string();
+            return item
+                .items
+                .iter()
+                .filter_map(|impl_item| match impl_item {
+                    syn::ImplItem::Fn(method) => {
+                        let mut profile = Profile {
+                            name: format!("{}::{}", owner, method.sig.ident),
+                            ..Profile::default()
+                        };
+                        profile.add_function(&method.sig, Some(&method.block), Some(&owner));
+                        Some(profile)
+                    }
+                    _ => None,
+                })
+                .collect();
+        }
+        _ => return Vec::new(),
+    }
+    vec![profile]
+}
+
+/// Lowercase words of an identifier, split at `_` and case changes.
+fn words(ident: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut previous_lower = false;
+    for c in ident.chars() {
+        if c == '_' || (c.is_uppercase() && previous_lower) {
+            words.push(std::mem::take(&mut word));
+        }
+        if c != '_' {
+            word.extend(c.to_lowercase());
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+    }
+    words.push(word);
+    words
+        .into_iter()
+        .filter(|word| word.len() > 2 && !STOP_WORDS.contains(&word.as_str()))
+        .collect()
+}
+
+/// Last segment of every path in a type.
+struct TypeNames(Vec<String>);
+
+impl<'ast> Visit<'ast> for TypeNames {
+    fn visit_type_path(&mut self, ty: &'ast syn::TypePath) {
+        if let Some(last) = ty.path.segments.last() {
+            self.0.push(last.ident.to_string());
+        }
+        visit::visit_type_path(self, ty);
+    }
+}
+
+/// Calls, field accesses and named paths in a function body.
+#[derive(Default)]
+struct Body {
+    calls: BTreeSet<String>,
+    fields: BTreeSet<String>,
+    paths: BTreeSet<String>,
+}
+
+impl Body {
+    fn call(&mut self, name: String) {
+        if !GENERIC_CALLS.contains(&name.as_str()) {
+            self.calls.insert(name);
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Body {
+    fn visit_expr_call(&mut self, expr: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(func) = &*expr.func
+            && let Some(last) = func.path.segments.last()
+        {
+            self.call(last.ident.to_string());
+        }
+        visit::visit_expr_call(self, expr);
+    }
+
+    fn visit_expr_method_call(&mut self, expr: &'ast syn::ExprMethodCall) {
+        self.call(expr.method.to_string());
+        visit::visit_expr_method_call(self, expr);
+    }
+
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        for segment in &path.segments {
+            self.paths.insert(segment.ident.to_string());
+        }
+        visit::visit_path(self, path);
+    }
+
+    fn visit_member(&mut self, member: &'ast syn::Member) {
+        if let syn::Member::Named(ident) = member {
+            self.fields.insert(ident.to_string());
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        for arg in macro_args(mac) {
+            self.visit_expr(&arg);
+        }
+    }
+}
+
+/// Calls whose result reaches another call through a local, anywhere in
+/// the crate: `let raw = fetch(); summarize(&raw)` gives `fetch` to
+/// `summarize`.
+#[derive(Default)]
+struct Flows {
+    /// The calls each local of the current function was bound from.
+    locals: HashMap<String, BTreeSet<String>>,
+    links: BTreeSet<(String, String)>,
+}
+
+impl Flows {
+    fn function(&mut self, block: &syn::Block) {
+        let outer = std::mem::take(&mut self.locals);
+        self.visit_block(block);
+        self.locals = outer;
+    }
+
+    fn call<'a>(&mut self, callee: String, args: impl IntoIterator<Item = &'a syn::Expr>) {
+        let mut names = LocalNames(Vec::new());
+        for arg in args {
+            names.visit_expr(arg);
+        }
+        for name in names.0 {
+            for source in self.locals.get(&name).into_iter().flatten() {
+                self.links.insert((source.clone(), callee.clone()));
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Flows {
+    fn visit_item_fn(&mut self, item: &'ast syn::ItemFn) {
+        self.function(&item.block);
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast syn::ImplItemFn) {
+        self.function(&item.block);
+    }
+
+    fn visit_trait_item_fn(&mut self, item: &'ast syn::TraitItemFn) {
+        if let Some(block) = &item.default {
+            self.function(block);
+        }
+    }
+
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        visit::visit_local(self, local);
+        let Some(init) = &local.init else {
+            return;
+        };
+        let mut body = Body::default();
+        body.visit_expr(&init.expr);
+        let mut bound = Bound(Vec::new());
+        bound.visit_pat(&local.pat);
+        for name in bound.0 {
+            self.locals.insert(name, body.calls.clone());
+        }
+    }
+
+    fn visit_expr_call(&mut self, expr: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(func) = &*expr.func
+            && let Some(last) = func.path.segments.last()
+        {
+            self.call(last.ident.to_string(), &expr.args);
+        }
+        visit::visit_expr_call(self, expr);
+    }
+
+    fn visit_expr_method_call(&mut self, expr: &'ast syn::ExprMethodCall) {
+        let args = std::iter::once(&*expr.receiver).chain(&expr.args);
+        self.call(expr.method.to_string(), args);
+        visit::visit_expr_method_call(self, expr);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        for arg in macro_args(mac) {
+            self.visit_expr(&arg);
+        }
+    }
+}
+
+/// Names bound by a pattern.
+struct Bound(Vec<String>);
+
+impl<'ast> Visit<'ast> for Bound {
+    fn visit_pat_ident(&mut self, pat: &'ast syn::PatIdent) {
+        self.0.push(pat.ident.to_string());
+        visit::visit_pat_ident(self, pat);
+    }
+}
+
+/// Single-name paths in an expression, the locals it reads.
+struct LocalNames(Vec<String>);
+
+impl<'ast> Visit<'ast> for LocalNames {
+    fn visit_expr_path(&mut self, expr: &'ast syn::ExprPath) {
+        if let Some(ident) = expr.path.get_ident() {
+            self.0.push(ident.to_string());
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        for arg in macro_args(mac) {
+            self.visit_expr(&arg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::SourceFile;
+
+    fn module<'a>(modules: &'a [ModuleAffinity], path: &str) -> &'a ModuleAffinity {
+        modules
+            .iter()
+            .find(|module| module.module.ends_with(path))
+            .unwrap_or_else(|| panic!("no module {}", path))
+    }
+
+    #[test]
+    fn utils_is_coincidental_and_its_solutions_functional() {
+        let modules = analyze_crate(env!("CARGO_MANIFEST_DIR"), DEFAULT_THRESHOLD).unwrap();
+
+        let utils = module(&modules, "coincidental_cohesion::utils");
+        assert_eq!(utils.level, CohesionLevel::Coincidental);
+        assert_eq!(utils.relatedness, 0.0);
+        assert_eq!(utils.pairs.len(), 3);
+        assert_eq!(utils.missing, ["types", "calls", "vocabulary", "links"]);
+
+        let math = module(&modules, "coincidental_cohesion::math_utils");
+        assert_eq!(math.level, CohesionLevel::Function);
+
+        let circle = module(&modules, "function_cohesion::circle_geometry");
+        assert_eq!(circle.level, CohesionLevel::Function);
+        assert!(circle.relatedness > 0.5, "{}", circle.relatedness);
+        assert!(
+            modules.iter().position(|m| m.module == utils.module)
+                < modules.iter().position(|m| m.module == circle.module)
+        );
+    }
+
+    #[test]
+    fn steps_run_by_one_function_are_related() {
+        let modules = analyze_crate(env!("CARGO_MANIFEST_DIR"), DEFAULT_THRESHOLD).unwrap();
+        for name in [
+            "sequence_cohesion::text_processor",
+            "temporal_cohesion::system_v2",
+            // Run by `solution`, each result passed on to the next step.
+            "procedural_cohesion::report_parts",
+            "external_coupling::parser",
+        ] {
+            let found = module(&modules, name);
+            assert_eq!(found.level, CohesionLevel::Function, "{}", name);
+        }
+        assert!(
+            !modules
+                .iter()
+                .any(|m| m.module.ends_with("::sequence_cohesion"))
+        );
+    }
+
+    #[test]
+    fn shared_vocabulary_relates_free_functions() {
+        let file = SourceFile::parse(
+            "lib.rs",
+            "fn parse_header(line: &str) -> Option<u32> { line.parse().ok() }
+             fn parse_footer(line: &str) -> Option<u32> { line.trim().parse().ok() }",
+        )
+        .unwrap();
+        let modules = analyze(&Crate::from_file("demo", file).unwrap(), DEFAULT_THRESHOLD);
+        assert_eq!(modules[0].level, CohesionLevel::Function);
+        assert_eq!(modules[0].items, vec!["parse_header", "parse_footer"]);
+    }
+
+    #[test]
+    fn identifiers_split_into_words() {
+        assert_eq!(words("parse_hex_to_u32"), vec!["parse", "hex", "u32"]);
+        assert_eq!(words("CustomerProfile"), vec!["customer", "profile"]);
+        assert_eq!(words("new"), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,472 @@
+//! ./analysis/clusters.rs
+//!
+//! Temporal and procedural cohesion inside one function: a body made of
+//! steps that share no data.
+//!
+//! The top-level statements are grouped by data flow, joining a statement
+//! with the one that defined each local it uses, and statements using the
+//! same parameter with each other. A statement using no locals at all, like a
+//! `println!`, stays with the statement before it unless a blank line sets it
+//! apart. A group of such statements at the very start or end is left to the
+//! function itself.
+//!
+//! Two or more groups remaining make the function
+//!
+//! - procedural, when its final expression combines their results, so
+//!   the steps are only linked by the order they run in
+//! - temporal otherwise, the steps merely running at the same time
+//!
+//! Each group is proposed as a function of its own, named after the comment
+//! above it where there is one, otherwise after the locals it binds. A name
+//! another group or the function already has gets a number.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+use super::dependencies::typed_params;
+use super::lcom::Components;
+use super::modules::{Crate, Function};
+use super::source::macro_args;
+use super::{CohesionLevel, Error, Location};
+
+/// A group of statements sharing data, proposed as a function of its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cluster {
+    /// Proposed name of the extracted function.
+    pub name: String,
+    /// Indices into the body's statements.
+    pub statements: Vec<usize>,
+    pub first_line: usize,
+    pub last_line: usize,
+    /// Parameters of the original function the group needs.
+    pub params: Vec<String>,
+    /// Locals the rest of the function still needs from the group.
+    pub returns: Vec<String>,
+}
+
+impl Cluster {
+    /// The proposed function's signature, types left out,
+    /// e.g. `fn build_summary(data) -> summary`.
+    pub fn signature(&self) -> String {
+        let returns = match self.returns.as_slice() {
+            [] => String::new(),
+            [one] => format!(" -> {}", one),
+            many => format!(" -> ({})", many.join(", ")),
+        };
+        format!("fn {}({}){}", self.name, self.params.join(", "), returns)
+    }
+}
+
+/// A function doing several unrelated things in a row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterFinding {
+    pub module: String,
+    pub function: String,
+    /// `Temporal` or `Procedural`.
+    pub level: CohesionLevel,
+    pub clusters: Vec<Cluster>,
+    /// Statements left in the function, which calls the extracted ones.
+    pub coordinator: Vec<usize>,
+    pub location: Location,
+}
+
+pub fn detect_crate(dir: impl AsRef<Path>) -> Result<Vec<ClusterFinding>, Error> {
+    Ok(detect(&Crate::load(dir)?))
+}
+
+/// Functions outside test modules whose bodies fall apart into
+/// unrelated steps.
+pub fn detect(krate: &Crate) -> Vec<ClusterFinding> {
+    krate
+        .functions()
+        .filter(|function| !krate.modules[function.module].test)
+        .filter_map(|function| analyze(krate, &function))
+        .collect()
+}
+
+/// Locals a statement binds and names it uses.
+struct Flow {
+    defines: BTreeSet<String>,
+    uses: BTreeSet<String>,
+}
+
+fn analyze(krate: &Crate, function: &Function) -> Option<ClusterFinding> {
+    let stmts = &function.block.stmts;
+    let tail = match stmts.last() {
+        Some(syn::Stmt::Expr(_, None)) => Some(stmts.len() - 1),
+        _ => None,
+    };
+    let steps: Vec<usize> = (0..stmts.len())
+        .filter(|&index| Some(index) != tail && !matches!(stmts[index], syn::Stmt::Item(_)))
+        .collect();
+    if steps.len() < 2 {
+        return None;
+    }
+    let params: Vec<String> = function
+        .sig
+        .receiver()
+        .map(|_| "self".to_string())
+        .into_iter()
+        .chain(typed_params(function.sig).map(|(name, _)| name))
+        .collect();
+    let mut flows: BTreeMap<usize, Flow> = steps
+        .iter()
+        .map(|&index| (index, flow(&stmts[index])))
+        .collect();
+    // Other names are functions, constants and the like.
+    let locals: BTreeSet<String> = flows
+        .values()
+        .flat_map(|flow| flow.defines.iter().cloned())
+        .chain(params.iter().cloned())
+        .collect();
+    for flow in flows.values_mut() {
+        flow.uses.retain(|name| locals.contains(name));
+    }
+    let text = krate.file_of(function.module).text();
+
+    // Nodes are statements by index, then one per parameter.
+    let mut components = Components::new(stmts.len() + params.len());
+    let mut defined_at: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut previous: Option<usize> = None;
+    for &index in &steps {
+        let flow = &flows[&index];
+        for name in &flow.uses {
+            if let Some(&at) = defined_at.get(name.as_str()) {
+                components.join(index, at);
+            } else if let Some(param) = params.iter().position(|param| param == name) {
+                components.join(index, stmts.len() + param);
+            }
+        }
+        if flow.defines.is_empty()
+            && flow.uses.is_empty()
+            && let Some(previous) = previous
+            && !blank_line_between(text, &stmts[previous], &stmts[index])
+        {
+            components.join(index, previous);
+        }
+        for name in &flow.defines {
+            defined_at.insert(name, index);
+        }
+        previous = Some(index);
+    }
+
+    let mut groups: Vec<Vec<usize>> = components
+        .groups()
+        .into_iter()
+        .map(|group| {
+            group
+                .into_iter()
+                .filter(|node| steps.contains(node))
+                .collect()
+        })
+        .filter(|group: &Vec<usize>| !group.is_empty())
+        .collect();
+    groups.sort();
+    let silent = |group: &Vec<usize>| {
+        group
+            .iter()
+            .all(|index| flows[index].defines.is_empty() && flows[index].uses.is_empty())
+    };
+    let mut coordinator = Vec::new();
+    if groups.first().is_some_and(silent) {
+        coordinator.extend(groups.remove(0));
+    }
+    if groups.last().is_some_and(silent) {
+        coordinator.extend(groups.pop().unwrap_or_default());
+    }
+    // A step that runs between another's statements and leaves nothing
+    // behind belongs to it, like a callback between setup and teardown.
+    while let Some((outer, inner)) = enclosed(&groups, &flows) {
+        let inner = groups.remove(inner);
+        let outer = if outer > inner[0] { outer - 1 } else { outer };
+        groups[outer].extend(inner);
+        groups[outer].sort();
+    }
+    if groups.len() < 2 {
+        return None;
+    }
+    coordinator.extend(tail);
+    coordinator.sort();
+
+    let tail_uses = tail.map(|tail| flow(&stmts[tail]).uses).unwrap_or_default();
+    let mut taken = BTreeSet::from([function.sig.ident.to_string()]);
+    let clusters: Vec<Cluster> = groups
+        .into_iter()
+        .enumerate()
+        .map(|(number, statements)| {
+            let defines: BTreeSet<&String> = statements
+                .iter()
+                .flat_map(|index| &flows[index].defines)
+                .collect();
+            let uses: BTreeSet<&String> = statements
+                .iter()
+                .flat_map(|index| &flows[index].uses)
+                .collect();
+            let returns: Vec<String> = statements
+                .iter()
+                .flat_map(|index| &flows[index].defines)
+                .filter(|name| tail_uses.contains(*name))
+                .cloned()
+                .collect();
+            let first = &stmts[statements[0]];
+            let last = &stmts[statements[statements.len() - 1]];
+            let named: Vec<&str> = if returns.is_empty() {
+                defines.iter().map(|name| name.as_str()).collect()
+            } else {
+                returns.iter().map(String::as_str).collect()
+            };
+            let candidates = [
+                comment_above(text, first),
+                (!named.is_empty()).then(|| format!("build_{}", named.join("_and_"))),
+            ];
+            let base = candidates
+                .iter()
+                .flatten()
+                .find(|name| !taken.contains(*name))
+                .or(candidates.iter().flatten().next())
+                .cloned()
+                .unwrap_or_else(|| format!("{}_part_{}", function.sig.ident, number + 1));
+            let name = (1..)
+                .map(|count| match count {
+                    1 => base.clone(),
+                    count => format!("{}_{}", base, count),
+                })
+                .find(|name| !taken.contains(name))
+                .expect("some count is free");
+            taken.insert(name.clone());
+            Cluster {
+                name,
+                first_line: first.span().start().line,
+                last_line: last.span().end().line,
+                params: params
+                    .iter()
+                    .filter(|param| uses.contains(param))
+                    .cloned()
+                    .collect(),
+                returns,
+                statements,
+            }
+        })
+        .collect();
+
+    let combined = clusters
+        .iter()
+        .filter(|cluster| !cluster.returns.is_empty())
+        .count();
+    Some(ClusterFinding {
+        module: krate.modules[function.module].path.clone(),
+        function: krate.function_path(function),
+        level: if combined >= 2 {
+            CohesionLevel::Procedural
+        } else {
+            CohesionLevel::Temporal
+        },
+        clusters,
+        coordinator,
+        location: krate.location(function.module, function.sig.ident.span()),
+    })
+}
+
+/// `(outer, inner)` indices of a group defining nothing that sits
+/// between the first and last statements of another.
+fn enclosed(groups: &[Vec<usize>], flows: &BTreeMap<usize, Flow>) -> Option<(usize, usize)> {
+    let span = |group: &Vec<usize>| (group[0], group[group.len() - 1]);
+    groups.iter().enumerate().find_map(|(inner, group)| {
+        let (first, last) = span(group);
+        if group.iter().any(|index| !flows[index].defines.is_empty()) {
+            return None;
+        }
+        groups
+            .iter()
+            .position(|outer| {
+                let (start, end) = span(outer);
+                start < first && last < end
+            })
+            .map(|outer| (outer, inner))
+    })
+}
+
+fn flow(stmt: &syn::Stmt) -> Flow {
+    let mut defines = BTreeSet::new();
+    if let syn::Stmt::Local(local) = stmt {
+        bindings(&local.pat, &mut defines);
+    }
+    let mut names = Names::default();
+    names.visit_stmt(stmt);
+    Flow {
+        uses: names.0.difference(&defines).cloned().collect(),
+        defines,
+    }
+}
+
+fn bindings(pat: &syn::Pat, names: &mut BTreeSet<String>) {
+    struct Bindings<'n>(&'n mut BTreeSet<String>);
+
+    impl<'ast> Visit<'ast> for Bindings<'_> {
+        fn visit_pat_ident(&mut self, pat: &'ast syn::PatIdent) {
+            self.0.insert(pat.ident.to_string());
+            visit::visit_pat_ident(self, pat);
+        }
+    }
+
+    Bindings(names).visit_pat(pat);
+}
+
+/// Single-segment paths, which is how locals and parameters appear.
+#[derive(Default)]
+struct Names(BTreeSet<String>);
+
+impl<'ast> Visit<'ast> for Names {
+    fn visit_expr_path(&mut self, expr: &'ast syn::ExprPath) {
+        if let Some(ident) = expr.path.get_ident() {
+            self.0.insert(ident.to_string());
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        for arg in macro_args(mac) {
+            self.visit_expr(&arg);
+        }
+    }
+}
+
+fn blank_line_between(text: &str, before: &syn::Stmt, after: &syn::Stmt) -> bool {
+    let (from, to) = (before.span().end().line, after.span().start().line);
+    text.lines()
+        .skip(from)
+        .take(to.saturating_sub(from + 1))
+        .any(|line| line.trim().is_empty())
+}
+
+/// The first line of the comment right above a statement, as a function
+/// name: `// 2. Create a log file.` gives `create_log_file`.
+fn comment_above(text: &str, stmt: &syn::Stmt) -> Option<String> {
+    let line = stmt.span().start().line;
+    let lines: Vec<&str> = text.lines().take(line.saturating_sub(1)).collect();
+    let comment = lines
+        .iter()
+        .rev()
+        .map(|line| line.trim())
+        .take_while(|line| line.starts_with("//"))
+        .last()?;
+    let sentence = comment
+        .trim_start_matches('/')
+        .trim()
+        .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c == ')')
+        .split('.')
+        .next()?;
+    let words: Vec<String> = sentence
+        .split(|c: char| !c.is_alphanumeric())
+        .map(str::to_lowercase)
+        .filter(|word| !word.is_empty() && !["a", "an", "the"].contains(&word.as_str()))
+        .take(5)
+        .collect();
+    (!words.is_empty()).then(|| words.join("_"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::SourceFile;
+    use std::sync::OnceLock;
+
+    fn this_crate() -> &'static [ClusterFinding] {
+        static FINDINGS: OnceLock<Vec<ClusterFinding>> = OnceLock::new();
+        FINDINGS.get_or_init(|| detect_crate(env!("CARGO_MANIFEST_DIR")).unwrap())
+    }
+
+    fn finding(function: &str) -> Option<&'static ClusterFinding> {
+        this_crate()
+            .iter()
+            .find(|finding| finding.function.ends_with(function))
+    }
+
+    #[test]
+    fn startup_is_temporal() {
+        let finding = finding("temporal_cohesion::system::startup").unwrap();
+        assert_eq!(finding.level, CohesionLevel::Temporal);
+        let names: Vec<&str> = finding
+            .clusters
+            .iter()
+            .map(|cluster| cluster.name.as_str())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "initialize_in_memory_cache",
+                "create_log_file",
+                "set_global_configuration_variable"
+            ]
+        );
+        assert_eq!(finding.clusters[0].statements, vec![1, 2, 3]);
+        assert_eq!(finding.clusters[1].signature(), "fn create_log_file()");
+        assert_eq!(finding.coordinator, vec![0, 8]);
+    }
+
+    #[test]
+    fn generate_full_report_is_procedural() {
+        let finding = finding("report_generation::generate_full_report").unwrap();
+        assert_eq!(finding.level, CohesionLevel::Procedural);
+        let signatures: Vec<String> = finding.clusters.iter().map(Cluster::signature).collect();
+        assert_eq!(
+            signatures,
+            vec![
+                "fn build_raw_data_and_summary() -> (raw_data, summary)",
+                "fn build_cover() -> cover"
+            ]
+        );
+        assert_eq!(finding.coordinator, vec![3]);
+    }
+
+    #[test]
+    fn coordinators_are_left_alone() {
+        assert!(finding("temporal_cohesion::system_v2::startup").is_none());
+        assert!(finding("procedural_cohesion::solution").is_none());
+    }
+
+    #[test]
+    fn clashing_names_are_numbered() {
+        let file = SourceFile::parse(
+            "lib.rs",
+            "fn setup() {
+                 // Setup
+                 let cache = 1;
+                 println!(\"{}\", cache);
+
+                 let log = 2;
+                 println!(\"{}\", log);
+
+                 // Setup
+                 let log = 3;
+                 println!(\"{}\", log);
+             }",
+        )
+        .unwrap();
+        let findings = detect(&Crate::from_file("demo", file).unwrap());
+        let names: Vec<&str> = findings[0]
+            .clusters
+            .iter()
+            .map(|cluster| cluster.name.as_str())
+            .collect();
+        assert_eq!(names, ["build_cache", "build_log", "setup_2"]);
+    }
+
+    #[test]
+    fn a_shared_parameter_holds_steps_together() {
+        let file = SourceFile::parse(
+            "lib.rs",
+            "fn report(data: &[u32]) {
+                 let total: u32 = data.iter().sum();
+                 println!(\"{}\", total);
+
+                 let max = data.iter().max();
+                 println!(\"{:?}\", max);
+             }",
+        )
+        .unwrap();
+        assert!(detect(&Crate::from_file("demo", file).unwrap()).is_empty());
+    }
+}
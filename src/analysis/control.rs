@@ -0,0 +1,259 @@
+//! ./analysis/control.rs
+//!
+//! Control coupling: a caller passes an enum or `bool` flag and the callee
+//! `match`es on it to pick entirely different behaviour, so the caller
+//! decides the callee's control flow.
+//!
+//! Only a top-level `match` or `if` on the parameter counts, and only when
+//! at least two of its branches do something different.
+
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use quote::ToTokens;
+use syn::spanned::Spanned;
+
+use super::dependencies::{control_params, peel, typed_params};
+use super::modules::Crate;
+use super::{Error, Location};
+
+/// A flag parameter that selects the behaviour of its function.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlFinding {
+    /// Full path of the function, e.g. `crate::formatter::format_data`.
+    pub function: String,
+    pub parameter: String,
+    /// The flag's type as written, e.g. `Format` or `bool`.
+    pub flag: String,
+    /// Branch labels in source order, e.g. `PlainText`, `Xml`, `Html`.
+    pub variants: Vec<String>,
+    pub location: Location,
+    pub suggestion: String,
+}
+
+pub fn detect_crate(dir: impl AsRef<Path>) -> Result<Vec<ControlFinding>, Error> {
+    Ok(detect(&Crate::load(dir)?))
+}
+
+/// Flag-driven functions outside test modules.
+pub fn detect(krate: &Crate) -> Vec<ControlFinding> {
+    let mut findings = Vec::new();
+    for function in krate.functions() {
+        if krate.modules[function.module].test {
+            continue;
+        }
+        for (parameter, branch) in
+            control_params(krate, function.module, function.sig, function.block)
+        {
+            let branches = branches(branch);
+            let bodies: BTreeSet<&String> = branches.iter().map(|(_, body)| body).collect();
+            if bodies.len() < 2 {
+                continue;
+            }
+            let flag = typed_params(function.sig)
+                .find(|(name, _)| *name == parameter)
+                .map(|(_, ty)| peel(ty).to_token_stream().to_string())
+                .unwrap_or_default();
+            let variants: Vec<String> = branches.into_iter().map(|(label, _)| label).collect();
+            let name = function.sig.ident.to_string();
+            findings.push(ControlFinding {
+                function: krate.function_path(&function),
+                suggestion: suggest(&name, &flag, &variants),
+                flag,
+                variants,
+                location: krate.location(function.module, branch.span()),
+                parameter,
+            });
+        }
+    }
+    findings
+}
+
+/// `(label, body)` of each branch of a `match` or `if`.
+fn branches(expr: &syn::Expr) -> Vec<(String, String)> {
+    let tokens = |body: &dyn ToTokens| body.to_token_stream().to_string();
+    match expr {
+        syn::Expr::Match(expr) => expr
+            .arms
+            .iter()
+            .map(|arm| (pattern_label(&arm.pat), tokens(&arm.body)))
+            .collect(),
+        syn::Expr::If(expr) => {
+            let (then, otherwise) = match &*expr.cond {
+                syn::Expr::Let(cond) => (pattern_label(&cond.pat), "_".to_string()),
+                _ => ("true".to_string(), "false".to_string()),
+            };
+            let else_body = expr
+                .else_branch
+                .as_ref()
+                .map(|(_, body)| tokens(body))
+                .unwrap_or_default();
+            vec![(then, tokens(&expr.then_branch)), (otherwise, else_body)]
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn pattern_label(pat: &syn::Pat) -> String {
+    let last = |path: &syn::Path| {
+        path.segments
+            .last()
+            .map(|segment| segment.ident.to_string())
+            .unwrap_or_default()
+    };
+    match pat {
+        syn::Pat::Path(pat) => last(&pat.path),
+        syn::Pat::TupleStruct(pat) => last(&pat.path),
+        syn::Pat::Struct(pat) => last(&pat.path),
+        syn::Pat::Ident(pat) => pat.ident.to_string(),
+        syn::Pat::Wild(_) => "_".to_string(),
+        syn::Pat::Or(pat) => pat
+            .cases
+            .iter()
+            .map(pattern_label)
+            .collect::<Vec<_>>()
+            .join(" | "),
+        pat => pat.to_token_stream().to_string(),
+    }
+}
+
+fn suggest(function: &str, flag: &str, variants: &[String]) -> String {
+    let name = trait_name(function);
+    let implementations: Vec<String> = variants
+        .iter()
+        .filter(|variant| variant.chars().next().is_some_and(char::is_alphabetic))
+        .map(|variant| implementation_name(variant, &name))
+        .collect();
+    format!(
+        "replace `{}` with a trait `{}` implemented by {}, and let callers pass the implementation, like `traits::Formatter` or `loggers::Logger`",
+        flag,
+        name,
+        implementations.join(", ")
+    )
+}
+
+/// Verbs of several syllables stressed on the last one, whose final
+/// consonant doubles like a one-syllable verb's. Spelling can't tell them
+/// from `open` or `render`.
+const STRESSED_LAST: [&str; 12] = [
+    "admit", "begin", "commit", "compel", "control", "emit", "forget", "format", "omit", "permit",
+    "refer", "submit",
+];
+
+/// The role a function's leading verb describes: `format_data` gives
+/// `Formatter`, `log_message` gives `Logger`.
+pub(crate) fn trait_name(function: &str) -> String {
+    let verb = function.split('_').next().unwrap_or(function);
+    let chars: Vec<char> = verb.chars().collect();
+    let vowel = |c: char| "aeiou".contains(c);
+    let syllables = chars
+        .iter()
+        .enumerate()
+        .filter(|&(at, &c)| vowel(c) && (at == 0 || !vowel(chars[at - 1])))
+        .count();
+    let noun = match chars.as_slice() {
+        [.., 'e'] => format!("{}r", verb),
+        // A short vowel before the final consonant of a stressed last
+        // syllable doubles it: log, run, submit; open and render don't.
+        [.., a, b, c]
+            if (syllables == 1 || STRESSED_LAST.contains(&verb))
+                && !vowel(*a)
+                && vowel(*b)
+                && !vowel(*c)
+                && !"wxy".contains(*c) =>
+        {
+            format!("{}{}er", verb, c)
+        }
+        _ => format!("{}er", verb),
+    };
+    let mut chars = noun.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// The struct implementing `trait_name` for one variant, e.g. `HtmlFormatter`.
+pub(crate) fn implementation_name(variant: &str, trait_name: &str) -> String {
+    let prefix = match variant {
+        "true" => "Enabled",
+        "false" => "Disabled",
+        variant => variant,
+    };
+    format!("{}{}", prefix, trait_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::SourceFile;
+    use std::sync::OnceLock;
+
+    fn this_crate() -> &'static [ControlFinding] {
+        static FINDINGS: OnceLock<Vec<ControlFinding>> = OnceLock::new();
+        FINDINGS.get_or_init(|| detect_crate(env!("CARGO_MANIFEST_DIR")).unwrap())
+    }
+
+    fn finding(function: &str) -> &'static ControlFinding {
+        this_crate()
+            .iter()
+            .find(|finding| finding.function.ends_with(function))
+            .unwrap_or_else(|| panic!("no finding for {}", function))
+    }
+
+    #[test]
+    fn format_data_is_driven_by_its_format() {
+        let finding = finding("formatter::format_data");
+        assert_eq!(finding.parameter, "format");
+        assert_eq!(finding.flag, "Format");
+        assert_eq!(finding.variants, vec!["PlainText", "Xml", "Html"]);
+        assert_eq!(finding.location.line, 18);
+        assert!(finding.suggestion.contains("trait `Formatter`"));
+        assert!(
+            finding
+                .suggestion
+                .contains("PlainTextFormatter, XmlFormatter, HtmlFormatter")
+        );
+    }
+
+    #[test]
+    fn log_message_is_driven_by_its_destination() {
+        let finding = finding("logging::log_message");
+        assert_eq!(finding.flag, "LogDestination");
+        assert_eq!(finding.variants, vec!["Console", "File", "Database"]);
+        assert!(finding.suggestion.contains("trait `Logger`"));
+    }
+
+    #[test]
+    fn only_flags_that_change_behaviour_count() {
+        let file = SourceFile::parse(
+            "lib.rs",
+            "enum Mode { A, B }
+             fn same(mode: Mode) -> u8 { match mode { Mode::A => 1, Mode::B => 1 } }
+             fn verbose(text: &str, loud: bool) -> String {
+                 if loud { text.to_uppercase() } else { text.to_string() }
+             }",
+        )
+        .unwrap();
+        let findings = detect(&Crate::from_file("demo", file).unwrap());
+
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].function, "crate::verbose");
+        assert_eq!(findings[0].variants, vec!["true", "false"]);
+    }
+
+    #[test]
+    fn trait_names_come_from_the_leading_verb() {
+        assert_eq!(trait_name("format_data"), "Formatter");
+        assert_eq!(trait_name("log_message"), "Logger");
+        assert_eq!(trait_name("send_mail"), "Sender");
+        assert_eq!(trait_name("encode"), "Encoder");
+        assert_eq!(trait_name("open_file"), "Opener");
+        assert_eq!(trait_name("render_page"), "Renderer");
+        assert_eq!(trait_name("print_receipt"), "Printer");
+        assert_eq!(trait_name("run_job"), "Runner");
+        assert_eq!(trait_name("submit_form"), "Submitter");
+        assert_eq!(trait_name("commit"), "Committer");
+        assert_eq!(trait_name("control_flow"), "Controller");
+    }
+}
@@ -0,0 +1,564 @@
+//! ./analysis/dashboard.rs
+//!
+//! Everything the analyzer finds in one HTML file to hand around: the
+//! module metrics, the findings sorted onto the coupling and cohesion
+//! ladders with the source they point at, and the dependency graph drawn
+//! as SVG. Styles and the script that sorts tables are inline, so the file
+//! opens anywhere without a network.
+//!
+//! The graph is laid out in columns, each module to the left of what it
+//! depends on; modules without dependencies either way are left out.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::Path;
+
+use super::external::Adapters;
+use super::graph::{self, Graph};
+use super::modules::Crate;
+use super::report::{Finding, Level, Report, Severity};
+use super::summary::{self, CrateSummary};
+use super::{CohesionLevel, CouplingLevel, Error};
+
+/// Lines of source shown above a finding.
+const CONTEXT: usize = 2;
+/// Most lines shown of the finding itself.
+const MAX_LINES: usize = 12;
+
+const STYLE: &str = "
+body { font: 14px/1.4 system-ui, sans-serif; margin: 2em auto; max-width: 76em; color: #222; }
+h1, h2, h3 { font-weight: 600; }
+h3 { margin-bottom: 0.3em; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 1em; }
+th, td { border-bottom: 1px solid #ddd; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+td.number { text-align: right; font-variant-numeric: tabular-nums; }
+table.sortable th { cursor: pointer; user-select: none; }
+table.sortable th[data-order=asc]::after { content: ' \\25B2'; }
+table.sortable th[data-order=desc]::after { content: ' \\25BC'; }
+.note { color: #1565c0; } .warning { color: #ef6c00; } .error { color: #c62828; }
+.none { color: #888; font-style: italic; }
+summary { cursor: pointer; }
+pre { background: #f6f6f6; padding: 0.5em; overflow-x: auto; font-size: 12px; }
+pre mark { background: #fff3c4; display: inline-block; width: 100%; }
+.graph { overflow: auto; border: 1px solid #ddd; }
+.legend span { display: inline-block; margin-right: 1em; }
+.legend i { display: inline-block; width: 1.5em; height: 0.3em; vertical-align: middle; margin-right: 0.3em; }
+";
+
+/// Sorts a table by the clicked column, by each cell's `data-key` when it
+/// has one, numerically when both keys are numbers.
+const SCRIPT: &str = "
+for (const th of document.querySelectorAll('table.sortable th')) {
+  th.addEventListener('click', () => {
+    const table = th.closest('table');
+    const ascending = th.dataset.order !== 'asc';
+    for (const other of table.querySelectorAll('th')) delete other.dataset.order;
+    th.dataset.order = ascending ? 'asc' : 'desc';
+    const key = row => {
+      const cell = row.cells[th.cellIndex];
+      return cell.dataset.key ?? cell.textContent;
+    };
+    const rows = Array.from(table.tBodies[0].rows);
+    rows.sort((a, b) => {
+      const [x, y] = [key(a), key(b)];
+      const order = isNaN(x) || isNaN(y) ? x.localeCompare(y) : x - y;
+      return ascending ? order : -order;
+    });
+    table.tBodies[0].append(...rows);
+  });
+}
+";
+
+pub fn render_crate(dir: impl AsRef<Path>, adapters: &Adapters) -> Result<String, Error> {
+    let dir = dir.as_ref();
+    Ok(render_from(&Crate::load(dir)?, adapters, dir))
+}
+
+/// The dashboard of a crate, with file paths as the crate has them.
+pub fn render(krate: &Crate, adapters: &Adapters) -> String {
+    render_from(krate, adapters, Path::new(""))
+}
+
+/// The dashboard with file paths relative to `root`.
+fn render_from(krate: &Crate, adapters: &Adapters, root: &Path) -> String {
+    let summary = summary::summarize(krate, adapters);
+    let report = Report::build(krate, adapters);
+    let mut out = String::new();
+    let _ = writeln!(out, "<!DOCTYPE html>");
+    let _ = writeln!(out, "<html lang=\"en\">");
+    let _ = writeln!(out, "<head>");
+    let _ = writeln!(out, "<meta charset=\"utf-8\">");
+    let _ = writeln!(
+        out,
+        "<title>Coupling and cohesion: {}</title>",
+        escape(&krate.name)
+    );
+    let _ = writeln!(out, "<style>{}</style>", STYLE);
+    let _ = writeln!(out, "</head>");
+    let _ = writeln!(out, "<body>");
+    let _ = writeln!(
+        out,
+        "<h1>Coupling and cohesion: {}</h1>",
+        escape(&krate.name)
+    );
+    let _ = writeln!(
+        out,
+        "<p>{} modules, {} functions, {} findings.</p>",
+        summary.modules.len(),
+        summary.functions,
+        report.findings.len()
+    );
+    metrics_table(&mut out, &summary);
+    findings(&mut out, krate, &report, root);
+    let _ = writeln!(out, "<h2>Dependencies</h2>");
+    let _ = writeln!(
+        out,
+        "<p>Each module sits left of what it depends on. Hover an arrow for its level.</p>"
+    );
+    legend(&mut out);
+    let _ = writeln!(out, "<div class=\"graph\">");
+    out.push_str(&svg(&Graph::build(krate)));
+    let _ = writeln!(out, "</div>");
+    let _ = writeln!(out, "<script>{}</script>", SCRIPT);
+    let _ = writeln!(out, "</body>");
+    let _ = writeln!(out, "</html>");
+    out
+}
+
+fn metrics_table(out: &mut String, summary: &CrateSummary) {
+    let _ = writeln!(out, "<h2>Modules</h2>");
+    let _ = writeln!(out, "<table class=\"sortable\">");
+    let _ = writeln!(
+        out,
+        "<thead><tr><th>module</th><th>afferent</th><th>efferent</th><th>instability</th><th>abstractness</th><th>distance</th><th>LCOM4</th><th>findings</th><th>worst</th></tr></thead>"
+    );
+    let _ = writeln!(out, "<tbody>");
+    for row in &summary.modules {
+        let metrics = &row.metrics;
+        let lcom4 = row.lcom4.map_or("-".to_string(), |lcom4| lcom4.to_string());
+        let (worst, worst_key) = match row.worst {
+            Some(level) => (level.rule_id(), rank_key(level)),
+            None => ("-".to_string(), 0),
+        };
+        let _ = writeln!(
+            out,
+            "<tr><td>{}</td><td class=\"number\">{}</td><td class=\"number\">{}</td><td class=\"number\">{:.2}</td><td class=\"number\">{:.2}</td><td class=\"number\">{:.2}</td><td class=\"number\" data-key=\"{}\">{}</td><td class=\"number\">{}</td><td data-key=\"{}\">{}</td></tr>",
+            escape(&metrics.module),
+            metrics.afferent,
+            metrics.efferent,
+            metrics.instability,
+            metrics.abstractness,
+            metrics.distance,
+            row.lcom4.unwrap_or(0),
+            lcom4,
+            row.findings,
+            worst_key,
+            worst
+        );
+    }
+    let _ = writeln!(out, "</tbody>");
+    let _ = writeln!(out, "</table>");
+}
+
+/// One table per level, down each ladder from the loosest coupling and the
+/// strongest cohesion.
+fn findings(out: &mut String, krate: &Crate, report: &Report, root: &Path) {
+    let ladders = [
+        ("Coupling", CouplingLevel::ALL.map(Level::Coupling).to_vec()),
+        ("Cohesion", CohesionLevel::ALL.map(Level::Cohesion).to_vec()),
+    ];
+    for (ladder, levels) in ladders {
+        let _ = writeln!(out, "<h2>{}</h2>", ladder);
+        for level in levels {
+            let found: Vec<&Finding> = report
+                .findings
+                .iter()
+                .filter(|finding| finding.level == level)
+                .collect();
+            let _ = writeln!(
+                out,
+                "<h3 id=\"{}\">{} <span class=\"{}\">{}</span> ({})</h3>",
+                level.rule_id().replace('/', "-"),
+                level.rank(),
+                level.severity().as_str(),
+                level.rule_id(),
+                found.len()
+            );
+            if found.is_empty() {
+                let _ = writeln!(out, "<p class=\"none\">None found.</p>");
+                continue;
+            }
+            let _ = writeln!(out, "<table class=\"sortable\">");
+            let _ = writeln!(
+                out,
+                "<thead><tr><th>item</th><th>location</th><th>severity</th><th>finding</th></tr></thead>"
+            );
+            let _ = writeln!(out, "<tbody>");
+            for finding in found {
+                let location = &finding.location;
+                let file = location.file.strip_prefix(root).unwrap_or(&location.file);
+                let file = file.display().to_string();
+                let _ = writeln!(
+                    out,
+                    "<tr><td>{}</td><td data-key=\"{}:{:06}\">{}:{}</td><td class=\"{}\" data-key=\"{}\">{}</td><td><details><summary>{}</summary>{}</details></td></tr>",
+                    escape(&finding.item),
+                    escape(&file),
+                    location.line,
+                    escape(&file),
+                    location.line,
+                    finding.severity.as_str(),
+                    severity_key(finding.severity),
+                    finding.severity.as_str(),
+                    escape(&finding.message),
+                    snippet(krate, finding)
+                );
+            }
+            let _ = writeln!(out, "</tbody>");
+            let _ = writeln!(out, "</table>");
+        }
+    }
+}
+
+/// The lines a finding covers, marked, with a little context above.
+fn snippet(krate: &Crate, finding: &Finding) -> String {
+    let location = &finding.location;
+    let Some(file) = krate.files.iter().find(|file| file.path() == location.file) else {
+        return String::new();
+    };
+    let first = location.line.saturating_sub(CONTEXT).max(1);
+    let last = location.end_line.min(location.line + MAX_LINES - 1);
+    let mut out = String::from("<pre>");
+    for (index, line) in file.text().lines().enumerate() {
+        let number = index + 1;
+        if number < first || number > last {
+            continue;
+        }
+        let text = format!("{:>4}  {}", number, escape(line));
+        if number >= location.line {
+            let _ = writeln!(out, "<mark>{}</mark>", text);
+        } else {
+            let _ = writeln!(out, "{}", text);
+        }
+    }
+    out.push_str("</pre>");
+    out
+}
+
+fn legend(out: &mut String) {
+    let _ = write!(out, "<p class=\"legend\">");
+    for level in CouplingLevel::ALL {
+        let _ = write!(
+            out,
+            "<span><i style=\"background: {}\"></i>{}</span>",
+            graph::color(level),
+            level
+        );
+    }
+    let _ = writeln!(out, "</p>");
+}
+
+const NODE_HEIGHT: usize = 34;
+const ROW: usize = 46;
+const GAP: usize = 90;
+const MARGIN: usize = 20;
+/// Rough width of a character of the label font, for sizing boxes.
+const CHAR_WIDTH: usize = 7;
+
+/// The modules with dependencies as boxes in columns, each to the left of
+/// the modules and crates it depends on, with one colored arrow per edge.
+fn svg(graph: &Graph) -> String {
+    let linked: BTreeSet<&str> = graph
+        .edges
+        .iter()
+        .flat_map(|edge| [edge.from.as_str(), edge.to.as_str()])
+        .collect();
+    let nodes: Vec<&graph::Node> = graph
+        .nodes
+        .iter()
+        .filter(|node| linked.contains(node.id.as_str()))
+        .collect();
+    let columns = columns(&nodes, graph);
+    let label = |id: &str| id.strip_prefix("crate::").unwrap_or(id).to_string();
+
+    // Column widths fit their longest label.
+    let count = columns.values().max().map_or(0, |column| column + 1);
+    let mut widths = vec![0; count];
+    let mut rows = vec![0; count];
+    let mut placed: BTreeMap<&str, (usize, usize, usize)> = BTreeMap::new();
+    for node in &nodes {
+        let column = columns[node.id.as_str()];
+        let text = label(&node.id)
+            .len()
+            .max(node.cohesion.map_or(0, |cohesion| cohesion.as_str().len()));
+        widths[column] = widths[column].max(text * CHAR_WIDTH + 20);
+    }
+    let mut lefts = vec![MARGIN; count];
+    for column in 1..count {
+        lefts[column] = lefts[column - 1] + widths[column - 1] + GAP;
+    }
+    for node in &nodes {
+        let column = columns[node.id.as_str()];
+        let top = MARGIN + rows[column] * ROW;
+        rows[column] += 1;
+        placed.insert(&node.id, (lefts[column], top, widths[column]));
+    }
+    let width = lefts.last().map_or(MARGIN, |left| left + widths[count - 1]) + MARGIN;
+    let height = MARGIN + rows.iter().max().copied().unwrap_or(0) * ROW + MARGIN;
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<svg width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"sans-serif\" font-size=\"12\" role=\"img\">",
+        w = width,
+        h = height
+    );
+    let _ = writeln!(out, "<defs>");
+    for level in CouplingLevel::ALL {
+        let _ = writeln!(
+            out,
+            "<marker id=\"arrow-{}\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" markerWidth=\"7\" markerHeight=\"7\" orient=\"auto\"><path d=\"M0,0 L10,5 L0,10 z\" fill=\"{}\"/></marker>",
+            level,
+            graph::color(level)
+        );
+    }
+    let _ = writeln!(out, "</defs>");
+    for edge in &graph.edges {
+        let (Some(&(x1, y1, w1)), Some(&(x2, y2, _))) =
+            (placed.get(edge.from.as_str()), placed.get(edge.to.as_str()))
+        else {
+            continue;
+        };
+        let (x1, y1) = (x1 + w1, y1 + NODE_HEIGHT / 2);
+        let y2 = y2 + NODE_HEIGHT / 2;
+        let bend = GAP / 2;
+        let _ = writeln!(
+            out,
+            "<path d=\"M{},{} C{},{} {},{} {},{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\" marker-end=\"url(#arrow-{})\"><title>{} → {}: {}</title></path>",
+            x1,
+            y1,
+            x1 + bend,
+            y1,
+            x2.saturating_sub(bend),
+            y2,
+            x2,
+            y2,
+            graph::color(edge.level),
+            edge.level,
+            escape(&label(&edge.from)),
+            escape(&label(&edge.to)),
+            edge.level
+        );
+    }
+    for node in &nodes {
+        let (x, y, width) = placed[node.id.as_str()];
+        let dashed = if node.cohesion.is_none() {
+            " stroke-dasharray=\"4 3\""
+        } else {
+            ""
+        };
+        let _ = writeln!(
+            out,
+            "<g><title>{}</title><rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"4\" fill=\"#fff\" stroke=\"#555\"{}/>",
+            escape(&node.id),
+            x,
+            y,
+            width,
+            NODE_HEIGHT,
+            dashed
+        );
+        match node.cohesion {
+            Some(cohesion) => {
+                let _ = writeln!(
+                    out,
+                    "<text x=\"{}\" y=\"{}\">{}</text><text x=\"{}\" y=\"{}\" fill=\"#777\" font-size=\"10\">{}</text></g>",
+                    x + 10,
+                    y + 14,
+                    escape(&label(&node.id)),
+                    x + 10,
+                    y + 27,
+                    cohesion
+                );
+            }
+            None => {
+                let _ = writeln!(
+                    out,
+                    "<text x=\"{}\" y=\"{}\">{}</text></g>",
+                    x + 10,
+                    y + 21,
+                    escape(&label(&node.id))
+                );
+            }
+        }
+    }
+    let _ = writeln!(out, "</svg>");
+    out
+}
+
+/// The column of each node: one past the deepest module depending on it,
+/// ignoring the edges that close a cycle.
+fn columns<'n>(nodes: &[&'n graph::Node], graph: &Graph) -> BTreeMap<&'n str, usize> {
+    let mut targets: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for edge in &graph.edges {
+        targets.entry(&edge.from).or_default().push(&edge.to);
+    }
+
+    // Depth-first from every node in order; an edge back onto the stack
+    // closes a cycle.
+    let mut order: Vec<&str> = Vec::new();
+    let mut back: BTreeSet<(&str, &str)> = BTreeSet::new();
+    let mut state: BTreeMap<&str, bool> = BTreeMap::new();
+    fn visit<'a>(
+        node: &'a str,
+        targets: &BTreeMap<&str, Vec<&'a str>>,
+        state: &mut BTreeMap<&'a str, bool>,
+        back: &mut BTreeSet<(&'a str, &'a str)>,
+        order: &mut Vec<&'a str>,
+    ) {
+        state.insert(node, true);
+        for &target in targets.get(node).into_iter().flatten() {
+            match state.get(target) {
+                Some(true) => {
+                    back.insert((node, target));
+                }
+                Some(false) => {}
+                None => visit(target, targets, state, back, order),
+            }
+        }
+        state.insert(node, false);
+        order.push(node);
+    }
+    for node in nodes {
+        if !state.contains_key(node.id.as_str()) {
+            visit(&node.id, &targets, &mut state, &mut back, &mut order);
+        }
+    }
+
+    // Reverse postorder puts every node after those depending on it.
+    let mut columns: BTreeMap<&str, usize> = BTreeMap::new();
+    for &node in order.iter().rev() {
+        let column = *columns.entry(node).or_insert(0);
+        for &target in targets.get(node).into_iter().flatten() {
+            if !back.contains(&(node, target)) {
+                let entry = columns.entry(target).or_insert(0);
+                *entry = (*entry).max(column + 1);
+            }
+        }
+    }
+    nodes
+        .iter()
+        .map(|node| (node.id.as_str(), columns[node.id.as_str()]))
+        .collect()
+}
+
+fn rank_key(level: Level) -> u8 {
+    match level {
+        Level::Coupling(level) => level.rank(),
+        Level::Cohesion(level) => 10 + level.rank(),
+    }
+}
+
+fn severity_key(severity: Severity) -> u8 {
+    match severity {
+        Severity::Note => 1,
+        Severity::Warning => 2,
+        Severity::Error => 3,
+    }
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::SourceFile;
+
+    #[test]
+    fn this_crate_renders_in_one_file() {
+        let html = render_crate(env!("CARGO_MANIFEST_DIR"), &Adapters::default()).unwrap();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        for external in ["http", "src=", "href=", "<link", "@import"] {
+            assert!(!html.contains(external), "{}", external);
+        }
+        assert!(html.contains(
+            "<tr><td>crate::coupling::control_coupling::formatter</td><td class=\"number\">1</td>"
+        ));
+
+        // The control coupling table lists format_data with its source.
+        let control = &html[html.find("<h3 id=\"coupling-control\">").unwrap()..];
+        let control = &control[..control.find("</table>").unwrap()];
+        assert!(control.contains(
+            "<td>crate::coupling::control_coupling::formatter::format_data</td><td data-key=\"src/coupling/control_coupling.rs:000018\">src/coupling/control_coupling.rs:18</td>"
+        ));
+        assert!(control.contains(
+            "  17      pub fn format_data(data: String, format: Format) -&gt; String {\n<mark>  18          match format {</mark>"
+        ));
+
+        let svg = &html[html.find("<svg").unwrap()..html.find("</svg>").unwrap()];
+        assert!(svg.contains(
+            "<title>coupling::control_coupling::report_generator → coupling::control_coupling::formatter: control</title>"
+        ));
+        assert!(!svg.contains("<title>crate::coupling::content_coupling::test</title>"));
+    }
+
+    #[test]
+    fn ladders_run_in_order_and_source_is_escaped() {
+        let file = SourceFile::parse(
+            "lib.rs",
+            "mod telemetry { pub struct Record { pub id: u64, pub name: String } }
+             mod sender {
+                 use super::telemetry::Record;
+                 pub fn send(record: &Record) -> bool { record.id < 3 && true }
+             }",
+        )
+        .unwrap();
+        let krate = Crate::from_file("demo", file).unwrap();
+        let html = render(&krate, &Adapters::default());
+
+        let headings: Vec<&str> = html
+            .lines()
+            .filter_map(|line| line.strip_prefix("<h3 id=\""))
+            .map(|line| &line[..line.find('"').unwrap()])
+            .collect();
+        assert_eq!(
+            headings,
+            vec![
+                "coupling-data",
+                "coupling-stamp",
+                "coupling-control",
+                "coupling-external",
+                "coupling-common",
+                "coupling-content",
+                "cohesion-function",
+                "cohesion-sequence",
+                "cohesion-communicational",
+                "cohesion-procedural",
+                "cohesion-temporal",
+                "cohesion-logical",
+                "cohesion-coincidental",
+            ]
+        );
+        assert!(html.contains("<h3 id=\"coupling-data\">1 <span class=\"note\">coupling/data</span> (0)</h3>\n<p class=\"none\">None found.</p>"));
+        assert!(html.contains("record.id &lt; 3 &amp;&amp; true"));
+
+        // The sender depends on telemetry, so it sits in the first column.
+        assert!(html.contains("<rect x=\"20\" y=\"20\" width=\"76\" height=\"34\" rx=\"4\" fill=\"#fff\" stroke=\"#555\"/>\n<text x=\"30\" y=\"34\">sender</text>"));
+        assert!(html.contains("<text x=\"196\" y=\"34\">telemetry</text>"));
+        assert!(
+            html.contains(
+                "marker-end=\"url(#arrow-stamp)\"><title>sender → telemetry: stamp</title>"
+            )
+        );
+    }
+}
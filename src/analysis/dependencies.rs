@@ -0,0 +1,551 @@
+//! ./analysis/dependencies.rs
+//!
+//! The module dependency graph of a crate, each edge labelled with the
+//! tightest coupling level found between the two modules:
+//!
+//! - data: plain references and calls passing primitives
+//! - stamp: a struct parameter of which only some fields are read
+//! - control: an enum or bool argument the callee `match`es on
+//! - external: types tied to a third-party crate, such as serde derives
+//! - common: a shared `static`
+//! - content: references inside `unsafe` code using raw pointers or `transmute`
+//!
+//! Test modules are left out; they depend on everything by design.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use proc_macro2::Span;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+use super::modules::{Crate, ItemKind, Target, flatten_use, is_standard_crate};
+use super::source::macro_args;
+use super::{CouplingLevel, Error, Location};
+
+/// A dependency from one module to another module or crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edge {
+    /// Module path, e.g. `crate::coupling::control_coupling::report_generator`.
+    pub from: String,
+    /// Module path, or the crate name for another crate.
+    pub to: String,
+    pub level: CouplingLevel,
+    /// Source of the first reference found at that level.
+    pub evidence: String,
+    pub location: Location,
+}
+
+pub fn classify_crate(dir: impl AsRef<Path>) -> Result<Vec<Edge>, Error> {
+    Ok(classify(&Crate::load(dir)?))
+}
+
+pub fn classify(krate: &Crate) -> Vec<Edge> {
+    let mut strongest: BTreeMap<(String, String), Edge> = BTreeMap::new();
+    for (index, module) in krate.modules.iter().enumerate() {
+        if module.test {
+            continue;
+        }
+        let mut collector = Collector {
+            krate,
+            module: index,
+            raw: false,
+            found: Vec::new(),
+        };
+        for item in &module.items {
+            if !matches!(item, syn::Item::Mod(_) | syn::Item::Use(_)) {
+                collector.visit_item(item);
+            }
+        }
+        for (to, level, span) in collector.found {
+            let key = (module.path.clone(), to.clone());
+            if strongest.get(&key).is_some_and(|edge| edge.level >= level) {
+                continue;
+            }
+            strongest.insert(
+                key,
+                Edge {
+                    from: module.path.clone(),
+                    to,
+                    level,
+                    evidence: krate.snippet(index, span),
+                    location: krate.location(index, span),
+                },
+            );
+        }
+    }
+    strongest.into_values().collect()
+}
+
+struct Collector<'c> {
+    krate: &'c Crate,
+    module: usize,
+    /// Inside `unsafe` code that works on raw pointers.
+    raw: bool,
+    found: Vec<(String, CouplingLevel, Span)>,
+}
+
+impl Collector<'_> {
+    fn reference(&mut self, target: &Target, level: CouplingLevel, span: Span) {
+        let to = match target {
+            Target::External(krate) if is_standard_crate(krate) => return,
+            Target::External(krate) => krate.clone(),
+            Target::Module(module) | Target::Item { module, .. } => {
+                let module = &self.krate.modules[*module];
+                if *module.path == self.krate.modules[self.module].path || module.test {
+                    return;
+                }
+                module.path.clone()
+            }
+        };
+        let level = if self.raw {
+            CouplingLevel::Content
+        } else {
+            level
+        };
+        self.found.push((to, level, span));
+    }
+
+    /// Level implied by naming `target` at all.
+    fn level_of(&self, target: &Target) -> CouplingLevel {
+        match target {
+            Target::External(_) => CouplingLevel::External,
+            _ if self.krate.kind(target) == Some(ItemKind::Static) => CouplingLevel::Common,
+            _ if tied_to_external_crate(self.krate, target) => CouplingLevel::External,
+            _ => CouplingLevel::Data,
+        }
+    }
+
+    /// Stamp coupling on this function's own struct parameters.
+    fn check_params(&mut self, sig: &syn::Signature, block: &syn::Block) {
+        for (ident, ty) in typed_params(sig) {
+            if let Some(target) = struct_param(self.krate, self.module, ty)
+                && partially_used(self.krate, &target, block, &ident)
+            {
+                self.reference(&target, CouplingLevel::Stamp, ty.span());
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Collector<'_> {
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        if let Some(target) = self.krate.resolve(self.module, path) {
+            let level = self.level_of(&target);
+            self.reference(&target, level, path.span());
+        }
+        visit::visit_path(self, path);
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(func) = &*call.func
+            && let Some(target) = self.krate.resolve(self.module, &func.path)
+            && let Some(syn::Item::Fn(callee)) = self.krate.item(&target)
+            && let Target::Item { module, .. } = &target
+        {
+            let level = call_level(self.krate, *module, callee);
+            self.reference(&target, level, call.span());
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_item_fn(&mut self, item: &'ast syn::ItemFn) {
+        self.check_params(&item.sig, &item.block);
+        let raw = self.raw;
+        self.raw |= item.sig.unsafety.is_some() && uses_raw_pointers(&item.block);
+        visit::visit_item_fn(self, item);
+        self.raw = raw;
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast syn::ImplItemFn) {
+        self.check_params(&item.sig, &item.block);
+        let raw = self.raw;
+        self.raw |= item.sig.unsafety.is_some() && uses_raw_pointers(&item.block);
+        visit::visit_impl_item_fn(self, item);
+        self.raw = raw;
+    }
+
+    fn visit_expr_unsafe(&mut self, expr: &'ast syn::ExprUnsafe) {
+        let raw = self.raw;
+        self.raw |= uses_raw_pointers(&expr.block);
+        visit::visit_expr_unsafe(self, expr);
+        self.raw = raw;
+    }
+
+    /// `use` inside a function body; module-level imports only count once used.
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        let mut imports = BTreeMap::new();
+        flatten_use(&item.tree, &mut Vec::new(), &mut imports, &mut Vec::new());
+        for segments in imports.into_values() {
+            let path = syn::Path {
+                leading_colon: None,
+                segments: segments
+                    .iter()
+                    .map(|segment| syn::PathSegment::from(syn::Ident::new(segment, item.span())))
+                    .collect(),
+            };
+            if let Some(target) = self.krate.resolve(self.module, &path) {
+                let level = self.level_of(&target);
+                self.reference(&target, level, item.tree.span());
+            }
+        }
+    }
+
+    /// `pub(crate)` and friends name modules without depending on them.
+    fn visit_visibility(&mut self, _: &'ast syn::Visibility) {}
+
+    fn visit_attribute(&mut self, attr: &'ast syn::Attribute) {
+        for path in derives(attr) {
+            self.visit_path(&path);
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        for arg in macro_args(mac) {
+            self.visit_expr(&arg);
+        }
+    }
+}
+
+/// The level a call into `callee`, defined in `module`, couples its caller at.
+fn call_level(krate: &Crate, module: usize, callee: &syn::ItemFn) -> CouplingLevel {
+    if !control_params(krate, module, &callee.sig, &callee.block).is_empty() {
+        return CouplingLevel::Control;
+    }
+    let stamped = typed_params(&callee.sig).any(|(ident, ty)| {
+        struct_param(krate, module, ty)
+            .is_some_and(|target| partially_used(krate, &target, &callee.block, &ident))
+    });
+    if stamped {
+        CouplingLevel::Stamp
+    } else {
+        CouplingLevel::Data
+    }
+}
+
+/// `(name, type)` of every parameter bound to a plain identifier.
+pub(crate) fn typed_params(sig: &syn::Signature) -> impl Iterator<Item = (String, &syn::Type)> {
+    sig.inputs.iter().filter_map(|input| match input {
+        syn::FnArg::Typed(param) => match &*param.pat {
+            syn::Pat::Ident(pat) => Some((pat.ident.to_string(), &*param.ty)),
+            _ => None,
+        },
+        syn::FnArg::Receiver(_) => None,
+    })
+}
+
+/// The type behind any number of references and parentheses.
+pub(crate) fn peel(ty: &syn::Type) -> &syn::Type {
+    match ty {
+        syn::Type::Reference(ty) => peel(&ty.elem),
+        syn::Type::Paren(ty) => peel(&ty.elem),
+        syn::Type::Group(ty) => peel(&ty.elem),
+        ty => ty,
+    }
+}
+
+/// The struct a parameter of type `ty` is, taken by value or by reference.
+pub(crate) fn struct_param(krate: &Crate, module: usize, ty: &syn::Type) -> Option<Target> {
+    let syn::Type::Path(ty) = peel(ty) else {
+        return None;
+    };
+    let target = krate.resolve(module, &ty.path)?;
+    (krate.kind(&target) == Some(ItemKind::Struct)).then_some(target)
+}
+
+/// Named fields of a struct.
+pub(crate) fn struct_fields(krate: &Crate, target: &Target) -> Vec<String> {
+    match krate.item(target) {
+        Some(syn::Item::Struct(item)) => item
+            .fields
+            .iter()
+            .filter_map(|field| field.ident.as_ref().map(ToString::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn partially_used(krate: &Crate, target: &Target, block: &syn::Block, param: &str) -> bool {
+    let fields = struct_fields(krate, target);
+    field_accesses(block, param).is_some_and(|used| used.len() < fields.len())
+}
+
+/// Fields read from `param` in `block`, or `None` if `param` is also used
+/// as a whole (passed on, matched, called on), which hides what is needed.
+pub(crate) fn field_accesses(block: &syn::Block, param: &str) -> Option<BTreeSet<String>> {
+    struct Accesses<'p> {
+        param: &'p str,
+        fields: BTreeSet<String>,
+        whole: bool,
+    }
+
+    impl<'ast> Visit<'ast> for Accesses<'_> {
+        fn visit_expr_field(&mut self, expr: &'ast syn::ExprField) {
+            match (&*expr.base, &expr.member) {
+                (syn::Expr::Path(base), syn::Member::Named(field))
+                    if base.path.is_ident(self.param) =>
+                {
+                    self.fields.insert(field.to_string());
+                }
+                _ => visit::visit_expr_field(self, expr),
+            }
+        }
+
+        fn visit_expr_path(&mut self, expr: &'ast syn::ExprPath) {
+            if expr.path.is_ident(self.param) {
+                self.whole = true;
+            }
+        }
+
+        fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+            for arg in macro_args(mac) {
+                self.visit_expr(&arg);
+            }
+        }
+    }
+
+    let mut accesses = Accesses {
+        param,
+        fields: BTreeSet::new(),
+        whole: false,
+    };
+    accesses.visit_block(block);
+    (!accesses.whole).then_some(accesses.fields)
+}
+
+/// Parameters of enum or `bool` type that a top-level `match` or `if` in
+/// the body branches on, with that `match` or `if`.
+pub(crate) fn control_params<'b>(
+    krate: &Crate,
+    module: usize,
+    sig: &syn::Signature,
+    block: &'b syn::Block,
+) -> Vec<(String, &'b syn::Expr)> {
+    let flags: Vec<String> = typed_params(sig)
+        .filter(|(_, ty)| is_flag_type(krate, module, ty))
+        .map(|(ident, _)| ident)
+        .collect();
+    block
+        .stmts
+        .iter()
+        .filter_map(|stmt| match stmt {
+            syn::Stmt::Expr(expr, _) => Some(expr),
+            _ => None,
+        })
+        .filter_map(|expr| {
+            let scrutinee = match expr {
+                syn::Expr::Match(expr) => &*expr.expr,
+                syn::Expr::If(expr) => match &*expr.cond {
+                    syn::Expr::Let(cond) => &*cond.expr,
+                    cond => cond,
+                },
+                _ => return None,
+            };
+            match scrutinee {
+                syn::Expr::Path(path) => flags
+                    .iter()
+                    .find(|flag| path.path.is_ident(flag.as_str()))
+                    .map(|flag| (flag.clone(), expr)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn is_flag_type(krate: &Crate, module: usize, ty: &syn::Type) -> bool {
+    let syn::Type::Path(ty) = peel(ty) else {
+        return false;
+    };
+    ty.path.is_ident("bool")
+        || krate
+            .resolve(module, &ty.path)
+            .is_some_and(|target| krate.kind(&target) == Some(ItemKind::Enum))
+}
+
+/// Paths listed in a `#[derive(..)]`.
+pub(crate) fn derives(attr: &syn::Attribute) -> Vec<syn::Path> {
+    if !attr.path().is_ident("derive") {
+        return Vec::new();
+    }
+    attr.parse_args_with(Punctuated::<syn::Path, syn::Token![,]>::parse_terminated)
+        .map(|paths| paths.into_iter().collect())
+        .unwrap_or_default()
+}
+
+/// Whether a struct or enum derives from, or holds fields of, a third-party crate.
+pub(crate) fn tied_to_external_crate(krate: &Crate, target: &Target) -> bool {
+    let Target::Item { module, .. } = target else {
+        return false;
+    };
+    let (attrs, fields): (&[syn::Attribute], Vec<&syn::Field>) = match krate.item(target) {
+        Some(syn::Item::Struct(item)) => (&item.attrs, item.fields.iter().collect()),
+        Some(syn::Item::Enum(item)) => (
+            &item.attrs,
+            item.variants.iter().flat_map(|v| &v.fields).collect(),
+        ),
+        _ => return false,
+    };
+    let third_party = |path: &syn::Path| matches!(krate.resolve(*module, path), Some(Target::External(name)) if !is_standard_crate(&name));
+    attrs
+        .iter()
+        .flat_map(derives)
+        .any(|path| third_party(&path))
+        || fields.iter().any(|field| match peel(&field.ty) {
+            syn::Type::Path(ty) => third_party(&ty.path),
+            _ => false,
+        })
+}
+
+/// Whether a block does pointer arithmetic, raw pointer casts or transmutes.
+pub(crate) fn uses_raw_pointers(block: &syn::Block) -> bool {
+    #[derive(Default)]
+    struct Raw(bool);
+
+    impl<'ast> Visit<'ast> for Raw {
+        fn visit_expr_method_call(&mut self, expr: &'ast syn::ExprMethodCall) {
+            self.0 |= POINTER_ARITHMETIC.contains(&expr.method.to_string().as_str());
+            visit::visit_expr_method_call(self, expr);
+        }
+
+        fn visit_expr_path(&mut self, expr: &'ast syn::ExprPath) {
+            self.0 |= expr
+                .path
+                .segments
+                .last()
+                .is_some_and(|segment| segment.ident == "transmute");
+        }
+
+        fn visit_type_ptr(&mut self, ty: &'ast syn::TypePtr) {
+            self.0 = true;
+            visit::visit_type_ptr(self, ty);
+        }
+    }
+
+    let mut raw = Raw::default();
+    raw.visit_block(block);
+    raw.0
+}
+
+/// Pointer methods that move to a neighbouring address.
+pub(crate) const POINTER_ARITHMETIC: [&str; 8] = [
+    "offset",
+    "byte_offset",
+    "add",
+    "byte_add",
+    "sub",
+    "byte_sub",
+    "wrapping_offset",
+    "wrapping_byte_offset",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::SourceFile;
+    use std::sync::OnceLock;
+
+    fn this_crate() -> &'static [Edge] {
+        static EDGES: OnceLock<Vec<Edge>> = OnceLock::new();
+        EDGES.get_or_init(|| classify_crate(env!("CARGO_MANIFEST_DIR")).unwrap())
+    }
+
+    fn edge_between<'e>(edges: &'e [Edge], from: &str, to: &str) -> Option<&'e Edge> {
+        edges
+            .iter()
+            .find(|edge| edge.from.ends_with(from) && edge.to.ends_with(to))
+    }
+
+    fn edge<'e>(edges: &'e [Edge], from: &str, to: &str) -> &'e Edge {
+        edge_between(edges, from, to).unwrap_or_else(|| panic!("no edge {} -> {}", from, to))
+    }
+
+    fn classify_source(source: &str) -> Vec<Edge> {
+        let file = SourceFile::parse("lib.rs", source).unwrap();
+        classify(&Crate::from_file("demo", file).unwrap())
+    }
+
+    #[test]
+    fn formatter_flag_is_control_coupling() {
+        let edge = edge(this_crate(), "::report_generator", "::formatter");
+        assert_eq!(edge.level, CouplingLevel::Control);
+        assert_eq!(edge.evidence, "formatter::format_data(data, format_type)");
+        assert!(
+            edge.location
+                .file
+                .ends_with("src/coupling/control_coupling.rs")
+        );
+        let text = std::fs::read_to_string(
+            Path::new(env!("CARGO_MANIFEST_DIR")).join(&edge.location.file),
+        )
+        .unwrap();
+        let line = text.lines().nth(edge.location.line - 1).unwrap();
+        assert_eq!(
+            line.chars()
+                .skip(edge.location.column - 1)
+                .collect::<String>(),
+            edge.evidence
+        );
+    }
+
+    #[test]
+    fn telemetry_record_is_stamp_coupling() {
+        let edge = edge(this_crate(), "::notification", "::telemetry");
+        assert_eq!(edge.level, CouplingLevel::Stamp);
+        assert_eq!(edge.evidence, "&Record");
+    }
+
+    #[test]
+    fn serde_record_is_external_coupling() {
+        let records = edge(this_crate(), "::data_processor", "::data_loader");
+        assert_eq!(records.level, CouplingLevel::External);
+        assert_eq!(records.evidence, "Record");
+
+        let csv = edge(this_crate(), "::data_loader", "csv");
+        assert_eq!(csv.level, CouplingLevel::External);
+    }
+
+    #[test]
+    fn primitive_price_is_data_coupling() {
+        let edge = edge(this_crate(), "coupling::data_coupling", "::display");
+        assert_eq!(edge.level, CouplingLevel::Data);
+
+        let v2 = edge_between(this_crate(), "::report_generator_v2", "::traits");
+        assert_eq!(v2.map(|edge| edge.level), Some(CouplingLevel::Data));
+    }
+
+    #[test]
+    fn shared_statics_are_common_coupling() {
+        let edges = classify_source(
+            "mod config { pub static mut VERBOSE: bool = false; }
+             mod logger { pub fn log() -> bool { unsafe { super::config::VERBOSE } } }",
+        );
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].level, CouplingLevel::Common);
+        assert_eq!(edges[0].evidence, "super::config::VERBOSE");
+    }
+
+    #[test]
+    fn raw_pointer_access_is_content_coupling() {
+        let edges = classify_source(
+            "mod storage { pub struct Storage { view_count: usize } }
+             mod report {
+                 use super::storage::Storage;
+                 pub fn peek(storage: &Storage) -> usize {
+                     unsafe { *(storage as *const Storage as *const usize) }
+                 }
+             }",
+        );
+        assert_eq!(edges.len(), 1);
+        assert_eq!(edges[0].level, CouplingLevel::Content);
+    }
+
+    #[test]
+    fn test_modules_are_left_out() {
+        assert!(
+            this_crate()
+                .iter()
+                .all(|edge| !edge.from.ends_with("::tests") && !edge.from.ends_with("::test"))
+        );
+    }
+}
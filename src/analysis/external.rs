@@ -0,0 +1,399 @@
+//! ./analysis/external.rs
+//!
+//! External coupling: items whose shape is dictated by a third-party crate,
+//! such as a struct deriving `serde::Deserialize` or a function returning
+//! `csv::Error`, and the modules that name them.
+//!
+//! Such items belong in adapter modules that translate the outside format
+//! into the crate's own types. Any other module naming one is reported.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use proc_macro2::Span;
+use quote::ToTokens;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+use super::dependencies::{derives, peel};
+use super::modules::{Crate, Target, flatten_use, is_standard_crate, item_name};
+use super::source::macro_args;
+use super::{Error, Location};
+
+/// An item tied to one or more third-party crates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TiedItem {
+    /// Full path, e.g. `crate::data_loader::Record`.
+    pub path: String,
+    /// Why, e.g. `derives serde::Deserialize` or `returns csv::Error`.
+    pub reasons: Vec<String>,
+    pub location: Location,
+}
+
+/// A module outside the adapters naming a tied item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub module: String,
+    /// Full path of the tied item.
+    pub item: String,
+    /// The first reference in the module, or its definition.
+    pub evidence: String,
+    pub location: Location,
+}
+
+/// Modules allowed to deal with third-party formats directly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Adapters {
+    modules: Vec<String>,
+}
+
+impl Adapters {
+    /// Module paths, with or without the leading `crate::`; each covers
+    /// its submodules too.
+    pub fn new<S: AsRef<str>>(modules: impl IntoIterator<Item = S>) -> Self {
+        let modules = modules
+            .into_iter()
+            .map(|module| match module.as_ref() {
+                module if module == "crate" || module.starts_with("crate::") => module.to_string(),
+                module => format!("crate::{}", module),
+            })
+            .collect();
+        Adapters { modules }
+    }
+
+    pub fn allows(&self, module: &str) -> bool {
+        self.modules.iter().any(|adapter| {
+            module == adapter
+                || module
+                    .strip_prefix(adapter.as_str())
+                    .is_some_and(|rest| rest.starts_with("::"))
+        })
+    }
+}
+
+pub fn check_crate(dir: impl AsRef<Path>, adapters: &Adapters) -> Result<Vec<Violation>, Error> {
+    Ok(check(&Crate::load(dir)?, adapters))
+}
+
+/// Structs, enums and functions outside test modules tied to another crate.
+pub fn tied_items(krate: &Crate) -> Vec<TiedItem> {
+    let mut tied = Vec::new();
+    for (index, module) in krate.modules.iter().enumerate() {
+        if module.test {
+            continue;
+        }
+        for item in &module.items {
+            let reasons = ties(krate, index, item);
+            if reasons.is_empty() {
+                continue;
+            }
+            let Some((name, _)) = item_name(item) else {
+                continue;
+            };
+            tied.push(TiedItem {
+                path: format!("{}::{}", module.path, name),
+                reasons,
+                location: krate.location(index, item_ident(item).span()),
+            });
+        }
+    }
+    tied
+}
+
+/// Every non-adapter module defining or naming a tied item, once per item.
+pub fn check(krate: &Crate, adapters: &Adapters) -> Vec<Violation> {
+    let tied: BTreeMap<String, TiedItem> = tied_items(krate)
+        .into_iter()
+        .map(|item| (item.path.clone(), item))
+        .collect();
+    let mut violations = Vec::new();
+    for (index, module) in krate.modules.iter().enumerate() {
+        if module.test || adapters.allows(&module.path) {
+            continue;
+        }
+        let mut names = Names {
+            krate,
+            module: index,
+            tied: &tied,
+            found: Vec::new(),
+        };
+        for item in &module.items {
+            if let Some((name, _)) = item_name(item)
+                && tied.contains_key(&format!("{}::{}", module.path, name))
+            {
+                names.found.push((
+                    format!("{}::{}", module.path, name),
+                    item_ident(item).span(),
+                ));
+            }
+            names.visit_item(item);
+        }
+        let mut seen = BTreeMap::new();
+        for (item, span) in names.found {
+            seen.entry(item.clone()).or_insert_with(|| Violation {
+                module: module.path.clone(),
+                item,
+                evidence: krate.snippet(index, span),
+                location: krate.location(index, span),
+            });
+        }
+        violations.extend(seen.into_values());
+    }
+    violations
+}
+
+/// Reasons an item is shaped by a third-party crate.
+fn ties(krate: &Crate, module: usize, item: &syn::Item) -> Vec<String> {
+    let third_party = |path: &syn::Path| external_name(krate, module, path).is_some();
+    let written = |path: &syn::Path| external_name(krate, module, path).unwrap_or_default();
+    let mut reasons = Vec::new();
+    let fields = |fields: &syn::Fields, reasons: &mut Vec<String>| {
+        for field in fields {
+            if let syn::Type::Path(ty) = peel(&field.ty)
+                && third_party(&ty.path)
+            {
+                let name = field
+                    .ident
+                    .as_ref()
+                    .map(ToString::to_string)
+                    .unwrap_or_else(|| "_".to_string());
+                reasons.push(format!("field `{}: {}`", name, written(&ty.path)));
+            }
+        }
+    };
+    let attrs = match item {
+        syn::Item::Struct(item) => {
+            fields(&item.fields, &mut reasons);
+            &item.attrs
+        }
+        syn::Item::Enum(item) => {
+            for variant in &item.variants {
+                fields(&variant.fields, &mut reasons);
+            }
+            &item.attrs
+        }
+        syn::Item::Fn(item) => {
+            for input in &item.sig.inputs {
+                if let syn::FnArg::Typed(input) = input {
+                    for path in external_paths(&input.ty, &third_party) {
+                        reasons.push(format!("takes {}", written(&path)));
+                    }
+                }
+            }
+            if let syn::ReturnType::Type(_, ty) = &item.sig.output {
+                for path in external_paths(ty, &third_party) {
+                    reasons.push(format!("returns {}", written(&path)));
+                }
+            }
+            return reasons;
+        }
+        _ => return reasons,
+    };
+    let derived = attrs
+        .iter()
+        .flat_map(derives)
+        .filter(|path| third_party(path))
+        .map(|path| format!("derives {}", written(&path)));
+    derived.chain(reasons).collect()
+}
+
+/// A third-party path as written, prefixed with its crate when imported,
+/// e.g. `Deserialize` after `use serde::Deserialize` gives `serde::Deserialize`.
+fn external_name(krate: &Crate, module: usize, path: &syn::Path) -> Option<String> {
+    let Some(Target::External(name)) = krate.resolve(module, path) else {
+        return None;
+    };
+    if is_standard_crate(&name) {
+        return None;
+    }
+    let written = path.to_token_stream().to_string().replace(' ', "");
+    if written.starts_with(&format!("{}::", name)) {
+        Some(written)
+    } else {
+        Some(format!("{}::{}", name, written))
+    }
+}
+
+/// Third-party type paths anywhere inside a type, generics included.
+fn external_paths(ty: &syn::Type, third_party: &dyn Fn(&syn::Path) -> bool) -> Vec<syn::Path> {
+    struct Paths<'f> {
+        third_party: &'f dyn Fn(&syn::Path) -> bool,
+        found: Vec<syn::Path>,
+    }
+
+    impl<'ast> Visit<'ast> for Paths<'_> {
+        fn visit_type_path(&mut self, ty: &'ast syn::TypePath) {
+            if (self.third_party)(&ty.path) {
+                let mut path = ty.path.clone();
+                for segment in &mut path.segments {
+                    segment.arguments = syn::PathArguments::None;
+                }
+                self.found.push(path);
+            }
+            visit::visit_type_path(self, ty);
+        }
+    }
+
+    let mut paths = Paths {
+        third_party,
+        found: Vec::new(),
+    };
+    paths.visit_type(ty);
+    paths.found
+}
+
+fn item_ident(item: &syn::Item) -> &syn::Ident {
+    match item {
+        syn::Item::Fn(item) => &item.sig.ident,
+        syn::Item::Struct(item) => &item.ident,
+        syn::Item::Enum(item) => &item.ident,
+        syn::Item::Trait(item) => &item.ident,
+        syn::Item::Static(item) => &item.ident,
+        syn::Item::Const(item) => &item.ident,
+        syn::Item::Type(item) => &item.ident,
+        syn::Item::Union(item) => &item.ident,
+        _ => unreachable!("item_ident on an unnamed item"),
+    }
+}
+
+/// References from one module to tied items.
+struct Names<'c, 't> {
+    krate: &'c Crate,
+    module: usize,
+    tied: &'t BTreeMap<String, TiedItem>,
+    found: Vec<(String, Span)>,
+}
+
+impl Names<'_, '_> {
+    fn reference(&mut self, target: Option<Target>, span: Span) {
+        if let Some(target @ Target::Item { .. }) = target {
+            let path = self.krate.target_path(&target);
+            if self.tied.contains_key(&path) {
+                self.found.push((path, span));
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Names<'_, '_> {
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        let target = self.krate.resolve(self.module, path);
+        self.reference(target, path.span());
+        visit::visit_path(self, path);
+    }
+
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        let mut imports = BTreeMap::new();
+        flatten_use(&item.tree, &mut Vec::new(), &mut imports, &mut Vec::new());
+        for segments in imports.into_values() {
+            let path = syn::Path {
+                leading_colon: None,
+                segments: segments
+                    .iter()
+                    .map(|segment| syn::PathSegment::from(syn::Ident::new(segment, item.span())))
+                    .collect(),
+            };
+            let target = self.krate.resolve(self.module, &path);
+            self.reference(target, item.tree.span());
+        }
+    }
+
+    /// Nested modules are checked on their own.
+    fn visit_item_mod(&mut self, _: &'ast syn::ItemMod) {}
+
+    fn visit_visibility(&mut self, _: &'ast syn::Visibility) {}
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        for arg in macro_args(mac) {
+            self.visit_expr(&arg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODULE: &str = "crate::coupling::external_coupling";
+
+    fn this_crate() -> Crate {
+        Crate::load(env!("CARGO_MANIFEST_DIR")).unwrap()
+    }
+
+    #[test]
+    fn csv_and_serde_ties_are_found() {
+        let tied = tied_items(&this_crate());
+        let reasons = |name: &str| {
+            tied.iter()
+                .find(|item| item.path == format!("{}::{}", MODULE, name))
+                .map(|item| item.reasons.clone())
+                .unwrap_or_default()
+        };
+        assert_eq!(
+            reasons("data_loader::Record"),
+            vec!["derives serde::Deserialize"]
+        );
+        assert_eq!(
+            reasons("data_loader::load_data"),
+            vec!["returns csv::Error"]
+        );
+        assert_eq!(
+            reasons("parser::CsvRecord"),
+            vec!["derives serde::Deserialize"]
+        );
+        assert!(reasons("app_models::AppRecord").is_empty());
+    }
+
+    #[test]
+    fn processor_reaches_past_the_loader() {
+        let adapters = Adapters::new([
+            "coupling::external_coupling::data_loader",
+            "coupling::external_coupling::parser",
+        ]);
+        let violations = check(&this_crate(), &adapters);
+        let in_module = |name: &str| -> Vec<&str> {
+            violations
+                .iter()
+                .filter(|violation| violation.module == format!("{}::{}", MODULE, name))
+                .map(|violation| violation.item.as_str())
+                .collect()
+        };
+
+        let processor = in_module("data_processor");
+        assert_eq!(processor, vec![format!("{}::data_loader::Record", MODULE)]);
+        assert!(in_module("data_processor_v2").is_empty());
+        assert!(in_module("app_models").is_empty());
+    }
+
+    #[test]
+    fn definitions_outside_adapters_count() {
+        let file = crate::analysis::SourceFile::parse(
+            "lib.rs",
+            "mod model {
+                 #[derive(serde::Serialize)]
+                 pub struct Order { pub id: u32 }
+             }
+             mod raw { pub struct Row { pub value: serde_json::Value } }",
+        )
+        .unwrap();
+        let krate = Crate::from_file("demo", file).unwrap();
+
+        let tied = tied_items(&krate);
+        assert_eq!(tied[1].reasons, vec!["field `value: serde_json::Value`"]);
+
+        let violations = check(&krate, &Adapters::new(["raw"]));
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].item, "crate::model::Order");
+        assert_eq!(violations[0].evidence, "Order");
+    }
+
+    #[test]
+    fn adapters_cover_their_submodules() {
+        let adapters = Adapters::new(["io", "crate::net::http"]);
+        assert!(adapters.allows("crate::io"));
+        assert!(adapters.allows("crate::io::csv"));
+        assert!(!adapters.allows("crate::iox"));
+        assert!(adapters.allows("crate::net::http"));
+        assert!(!adapters.allows("crate::net"));
+    }
+}
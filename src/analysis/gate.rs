@@ -0,0 +1,485 @@
+//! ./analysis/gate.rs
+//!
+//! A pass/fail check of a crate against the limits in
+//! `cohesion-coupling.toml`, for failing a build that regressed:
+//!
+//! ```toml
+//! max_coupling = "common"        # no content coupling
+//! max_cohesion = "logical"       # no coincidental cohesion
+//! max_stamp_unused_ratio = 0.5
+//! max_lcom4 = 1
+//! adapters = ["storage::csv"]    # may use third-party formats directly
+//!
+//! [modules."legacy::reports"]    # this module and everything below it
+//! max_lcom4 = 3
+//! ```
+//!
+//! A `// coupling:allow(control)` comment lets a finding through, on its own
+//! line, on the next line, or anywhere in the item starting on the next line.
+//! It takes level names such as `control` or `temporal`, rule IDs such as
+//! `coupling/stamp`, and `lcom4`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+use super::external::Adapters;
+use super::modules::Crate;
+use super::report::{Level, Report};
+use super::{CohesionLevel, CouplingLevel, Error, Location, lcom, stamp};
+
+/// Name of the configuration file, looked up in the crate directory.
+pub const CONFIG_FILE: &str = "cohesion-coupling.toml";
+
+/// Limits for a crate or module; unset ones are not checked.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Limits {
+    pub max_coupling: Option<CouplingLevel>,
+    pub max_cohesion: Option<CohesionLevel>,
+    pub max_stamp_unused_ratio: Option<f64>,
+    pub max_lcom4: Option<usize>,
+}
+
+impl Limits {
+    /// These limits with the ones `other` sets replacing them.
+    fn overridden_by(&self, other: &Limits) -> Limits {
+        Limits {
+            max_coupling: other.max_coupling.or(self.max_coupling),
+            max_cohesion: other.max_cohesion.or(self.max_cohesion),
+            max_stamp_unused_ratio: other.max_stamp_unused_ratio.or(self.max_stamp_unused_ratio),
+            max_lcom4: other.max_lcom4.or(self.max_lcom4),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(from = "ConfigFile")]
+pub struct Config {
+    /// Limits for every module.
+    pub limits: Limits,
+    /// Modules allowed to name items tied to other crates.
+    pub adapters: Vec<String>,
+    /// Limits for a module and its submodules, by path.
+    pub modules: BTreeMap<String, Limits>,
+}
+
+/// The file layout, with the global limits at the top level; serde can't
+/// reject misspelt keys through `#[serde(flatten)]`.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    max_coupling: Option<CouplingLevel>,
+    max_cohesion: Option<CohesionLevel>,
+    max_stamp_unused_ratio: Option<f64>,
+    max_lcom4: Option<usize>,
+    #[serde(default)]
+    adapters: Vec<String>,
+    #[serde(default)]
+    modules: BTreeMap<String, Limits>,
+}
+
+impl From<ConfigFile> for Config {
+    fn from(file: ConfigFile) -> Self {
+        Config {
+            limits: Limits {
+                max_coupling: file.max_coupling,
+                max_cohesion: file.max_cohesion,
+                max_stamp_unused_ratio: file.max_stamp_unused_ratio,
+                max_lcom4: file.max_lcom4,
+            },
+            adapters: file.adapters,
+            modules: file.modules,
+        }
+    }
+}
+
+impl Config {
+    pub fn parse(path: impl AsRef<Path>, text: &str) -> Result<Self, Error> {
+        toml::from_str(text).map_err(|source| Error::Config {
+            path: path.as_ref().to_path_buf(),
+            source,
+        })
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(path, &text)
+    }
+
+    /// The crate's `cohesion-coupling.toml`, or no limits without one.
+    pub fn find(dir: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = dir.as_ref().join(CONFIG_FILE);
+        if path.exists() {
+            Self::read(path)
+        } else {
+            Ok(Config::default())
+        }
+    }
+
+    /// The limits for `module`, the most specific section winning.
+    pub fn limits_for(&self, module: &str) -> Limits {
+        let mut sections: Vec<(String, &Limits)> = self
+            .modules
+            .iter()
+            .map(|(path, limits)| (qualified(path), limits))
+            .filter(|(path, _)| within(module, path))
+            .collect();
+        sections.sort_by_key(|(path, _)| path.len());
+        sections
+            .into_iter()
+            .fold(self.limits.clone(), |limits, (_, section)| {
+                limits.overridden_by(section)
+            })
+    }
+}
+
+/// A finding beyond its module's limits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// Rule ID, e.g. `coupling/control` or `cohesion/lcom4`.
+    pub rule: String,
+    pub module: String,
+    pub item: String,
+    pub message: String,
+    pub location: Location,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Outcome {
+    pub violations: Vec<Violation>,
+    /// Violations let through by a `coupling:allow` comment.
+    pub suppressed: Vec<Violation>,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Checks the crate in `dir`, with file paths relative to `dir`.
+pub fn check_crate(dir: impl AsRef<Path>, config: &Config) -> Result<Outcome, Error> {
+    let dir = dir.as_ref();
+    let mut outcome = check(&Crate::load(dir)?, config);
+    for violation in outcome
+        .violations
+        .iter_mut()
+        .chain(outcome.suppressed.iter_mut())
+    {
+        if let Ok(relative) = violation.location.file.strip_prefix(dir) {
+            violation.location.file = relative.to_path_buf();
+        }
+    }
+    Ok(outcome)
+}
+
+pub fn check(krate: &Crate, config: &Config) -> Outcome {
+    let mut found = Vec::new();
+    let mut violation = |rule: String, item: String, message: String, location: Location| {
+        found.push(Violation {
+            module: module_of(krate, &item),
+            rule,
+            item,
+            message,
+            location,
+        });
+    };
+
+    for finding in stamp::detect(krate) {
+        let limits = config.limits_for(&module_of(krate, &finding.function));
+        let rule = Level::Coupling(CouplingLevel::Stamp).rule_id();
+        let ratio = finding.unused_ratio();
+        let message = match (limits.max_coupling, limits.max_stamp_unused_ratio) {
+            (Some(max), _) if CouplingLevel::Stamp > max => {
+                format!(
+                    "stamp coupling through `{}`, over max_coupling = {}",
+                    finding.parameter, max
+                )
+            }
+            (_, Some(max)) if ratio > max => format!(
+                "`{}` leaves {:.2} of its fields unused, over max_stamp_unused_ratio = {}",
+                finding.parameter, ratio, max
+            ),
+            _ => continue,
+        };
+        violation(rule, finding.function, message, finding.location);
+    }
+
+    let report = Report::build(krate, &Adapters::new(&config.adapters));
+    for finding in report.findings {
+        let limits = config.limits_for(&module_of(krate, &finding.item));
+        let limit = match finding.level {
+            Level::Coupling(CouplingLevel::Stamp) => continue,
+            Level::Coupling(level) => limits
+                .max_coupling
+                .filter(|max| level > *max)
+                .map(|max| format!("max_coupling = {}", max)),
+            Level::Cohesion(level) => limits
+                .max_cohesion
+                .filter(|max| level > *max)
+                .map(|max| format!("max_cohesion = {}", max)),
+        };
+        if let Some(limit) = limit {
+            let message = format!("{}, over {}", finding.message, limit);
+            violation(finding.rule, finding.item, message, finding.location);
+        }
+    }
+
+    for (module, cohesion) in lcom::analyze_crate(krate) {
+        if let Some(max) = config.limits_for(&module).max_lcom4
+            && cohesion.lcom4 > max
+        {
+            let name = cohesion.name.rsplit("::").next().unwrap_or(&cohesion.name);
+            let message = format!(
+                "`{}` has LCOM4 {}, over max_lcom4 = {}",
+                name, cohesion.lcom4, max
+            );
+            violation(
+                "cohesion/lcom4".to_string(),
+                cohesion.name,
+                message,
+                cohesion.location,
+            );
+        }
+    }
+
+    let suppressions = suppressions(krate);
+    let (suppressed, violations) = found.into_iter().partition(|violation| {
+        suppressions
+            .iter()
+            .any(|suppression| suppression.covers(violation))
+    });
+    Outcome {
+        violations,
+        suppressed,
+    }
+}
+
+/// A `coupling:allow(..)` comment and the lines it covers.
+struct Suppression {
+    file: PathBuf,
+    lines: RangeInclusive<usize>,
+    names: Vec<String>,
+}
+
+impl Suppression {
+    fn covers(&self, violation: &Violation) -> bool {
+        let level = violation.rule.rsplit('/').next().unwrap_or(&violation.rule);
+        self.file == violation.location.file
+            && self.lines.contains(&violation.location.line)
+            && self
+                .names
+                .iter()
+                .any(|name| *name == violation.rule || name == level)
+    }
+}
+
+fn suppressions(krate: &Crate) -> Vec<Suppression> {
+    const MARKER: &str = "coupling:allow(";
+
+    let mut suppressions = Vec::new();
+    for file in &krate.files {
+        let lines: Vec<&str> = file.text().lines().collect();
+        let mut items = ItemLines::default();
+        items.visit_file(file.syntax());
+        for (index, line) in lines.iter().enumerate() {
+            let Some(comment) = line.find("//").map(|at| &line[at..]) else {
+                continue;
+            };
+            let Some(names) = comment
+                .find(MARKER)
+                .map(|at| &comment[at + MARKER.len()..])
+                .and_then(|rest| rest.split(')').next())
+            else {
+                continue;
+            };
+            let line = index + 1;
+            let next = lines[index + 1..]
+                .iter()
+                .position(|line| {
+                    let line = line.trim();
+                    !line.is_empty() && !line.starts_with("//")
+                })
+                .map_or(line, |offset| line + 1 + offset);
+            let end = items
+                .0
+                .iter()
+                .filter(|(start, _)| *start == next)
+                .map(|(_, end)| *end)
+                .max()
+                .unwrap_or(next);
+            suppressions.push(Suppression {
+                file: file.path().to_path_buf(),
+                lines: line..=end,
+                names: names
+                    .split(',')
+                    .map(|name| name.trim().to_string())
+                    .collect(),
+            });
+        }
+    }
+    suppressions
+}
+
+/// `(first, last)` lines of every item, nested ones included.
+#[derive(Default)]
+struct ItemLines(Vec<(usize, usize)>);
+
+impl<'ast> Visit<'ast> for ItemLines {
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        let span = item.span();
+        self.0.push((span.start().line, span.end().line));
+        visit::visit_item(self, item);
+    }
+
+    fn visit_impl_item_fn(&mut self, item: &'ast syn::ImplItemFn) {
+        let span = item.span();
+        self.0.push((span.start().line, span.end().line));
+        visit::visit_impl_item_fn(self, item);
+    }
+}
+
+/// The innermost module containing the item at `path`.
+fn module_of(krate: &Crate, path: &str) -> String {
+    krate
+        .module_containing(path)
+        .map_or("crate", |module| module.path.as_str())
+        .to_string()
+}
+
+/// Whether `path` is `module` or inside it.
+fn within(path: &str, module: &str) -> bool {
+    path.strip_prefix(module)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+}
+
+fn qualified(module: &str) -> String {
+    if module == "crate" || module.starts_with("crate::") {
+        module.to_string()
+    } else {
+        format!("crate::{}", module)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::SourceFile;
+
+    fn check_source(source: &str, config: &str) -> Outcome {
+        let file = SourceFile::parse("lib.rs", source).unwrap();
+        let krate = Crate::from_file("demo", file).unwrap();
+        check(&krate, &Config::parse(CONFIG_FILE, config).unwrap())
+    }
+
+    const TELEMETRY: &str = "
+        mod telemetry { pub struct Record { pub id: u64, pub name: String, pub email: String } }
+        mod notification {
+            use super::telemetry::Record;
+            pub fn send(record: &Record) -> u64 { record.id }
+        }";
+
+    #[test]
+    fn stamp_ratio_over_the_limit_fails() {
+        let outcome = check_source(TELEMETRY, "max_stamp_unused_ratio = 0.5");
+        assert!(!outcome.passed());
+        assert_eq!(outcome.violations[0].rule, "coupling/stamp");
+        assert_eq!(outcome.violations[0].module, "crate::notification");
+        assert!(outcome.violations[0].message.contains("0.67"));
+
+        let relaxed =
+            "max_stamp_unused_ratio = 0.5\n[modules.notification]\nmax_stamp_unused_ratio = 0.8";
+        assert!(check_source(TELEMETRY, relaxed).passed());
+    }
+
+    #[test]
+    fn lcom4_over_the_limit_fails() {
+        let outcome = check_source(
+            "pub struct Split { a: u8, b: u8 }
+             impl Split {
+                 fn a(&self) -> u8 { self.a }
+                 fn b(&self) -> u8 { self.b }
+             }",
+            "max_lcom4 = 1",
+        );
+        assert_eq!(outcome.violations.len(), 1);
+        assert_eq!(outcome.violations[0].rule, "cohesion/lcom4");
+        assert_eq!(outcome.violations[0].item, "crate::Split");
+    }
+
+    #[test]
+    fn allow_comments_cover_the_next_item() {
+        let source = "
+            pub enum Mode { Loud, Quiet }
+            // coupling:allow(control)
+            pub fn say(text: &str, mode: Mode) -> String {
+                match mode { Mode::Loud => text.to_uppercase(), Mode::Quiet => text.to_lowercase() }
+            }";
+        let outcome = check_source(source, "max_coupling = \"stamp\"");
+        assert!(outcome.passed());
+        assert_eq!(outcome.suppressed.len(), 1);
+        assert_eq!(outcome.suppressed[0].rule, "coupling/control");
+
+        let outcome = check_source(
+            &source.replace("coupling:allow", "note"),
+            "max_coupling = \"stamp\"",
+        );
+        assert_eq!(outcome.violations.len(), 1);
+    }
+
+    #[test]
+    fn format_data_is_exempted_in_this_crate() {
+        let krate = Crate::load(env!("CARGO_MANIFEST_DIR")).unwrap();
+        let config = Config::parse(
+            CONFIG_FILE,
+            "[modules.\"coupling::control_coupling::formatter\"]\nmax_coupling = \"data\"",
+        )
+        .unwrap();
+        let outcome = check(&krate, &config);
+        assert!(outcome.passed(), "{:?}", outcome.violations);
+        assert!(
+            outcome
+                .suppressed
+                .iter()
+                .any(|violation| violation.item.ends_with("formatter::format_data"))
+        );
+    }
+
+    #[test]
+    fn only_the_example_is_externally_coupled_in_this_crate() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let config = Config::find(dir).unwrap();
+        let report = Report::load(dir, &Adapters::new(&config.adapters)).unwrap();
+        let external: Vec<&str> = report
+            .findings
+            .iter()
+            .filter(|finding| finding.rule == "coupling/external")
+            .map(|finding| finding.item.as_str())
+            .collect();
+        assert!(!external.is_empty());
+        for item in external {
+            assert!(
+                item.starts_with("crate::coupling::external_coupling"),
+                "{}",
+                item
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_limits_are_rejected() {
+        let error = Config::parse(CONFIG_FILE, "max_colpuing = \"data\"").unwrap_err();
+        assert!(error.to_string().starts_with(CONFIG_FILE));
+        assert!(Config::parse(CONFIG_FILE, "max_coupling = \"tight\"").is_err());
+    }
+}
@@ -0,0 +1,300 @@
+//! ./analysis/graph.rs
+//!
+//! The module dependency graph as Graphviz DOT or Mermaid text, for slides.
+//! Edges are colored and labelled by coupling level, nodes carry the
+//! weakest cohesion found in the module.
+//!
+//! Nodes are sorted by path and edges by their ends, so the same source
+//! always renders the same text.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::Path;
+
+use super::dependencies::{Edge, classify};
+use super::modules::Crate;
+use super::{CohesionLevel, CouplingLevel, Error, affinity, clusters};
+
+/// A module, or another crate depended on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    /// Module path, or the crate name for another crate.
+    pub id: String,
+    /// `None` for other crates.
+    pub cohesion: Option<CohesionLevel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    /// Module path stripped from labels.
+    prefix: String,
+}
+
+impl Graph {
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, Error> {
+        Ok(Self::build(&Crate::load(dir)?))
+    }
+
+    /// Every module outside test modules and every edge between them.
+    pub fn build(krate: &Crate) -> Self {
+        let cohesion = module_cohesion(krate);
+        let edges = classify(krate);
+        let mut nodes: BTreeMap<&str, Option<CohesionLevel>> = krate
+            .modules
+            .iter()
+            .filter(|module| !module.test)
+            .map(|module| (module.path.as_str(), cohesion.get(&module.path).copied()))
+            .collect();
+        for edge in &edges {
+            nodes.entry(&edge.to).or_insert(None);
+        }
+        Graph {
+            nodes: nodes
+                .into_iter()
+                .map(|(id, cohesion)| Node {
+                    id: id.to_string(),
+                    cohesion,
+                })
+                .collect(),
+            edges,
+            prefix: "crate".to_string(),
+        }
+    }
+
+    /// The part of the graph below module `prefix`, plus the other crates
+    /// it depends on, labelled relative to `prefix`.
+    pub fn within(&self, prefix: &str) -> Self {
+        let inside = |id: &str| {
+            id.strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with("::"))
+        };
+        let external = |id: &str| id != "crate" && !id.starts_with("crate::");
+        let edges: Vec<Edge> = self
+            .edges
+            .iter()
+            .filter(|edge| inside(&edge.from) && (inside(&edge.to) || external(&edge.to)))
+            .cloned()
+            .collect();
+        let reached: BTreeSet<&str> = edges.iter().map(|edge| edge.to.as_str()).collect();
+        Graph {
+            nodes: self
+                .nodes
+                .iter()
+                .filter(|node| inside(&node.id) || reached.contains(node.id.as_str()))
+                .cloned()
+                .collect(),
+            edges,
+            prefix: prefix.to_string(),
+        }
+    }
+
+    fn label(&self, id: &str) -> String {
+        id.strip_prefix(&self.prefix)
+            .and_then(|rest| rest.strip_prefix("::"))
+            .unwrap_or(id)
+            .to_string()
+    }
+
+    pub fn to_dot(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "digraph dependencies {{");
+        let _ = writeln!(out, "    rankdir=LR;");
+        let _ = writeln!(out, "    node [shape=box, fontname=\"Helvetica\"];");
+        let _ = writeln!(out, "    edge [fontname=\"Helvetica\"];");
+        for node in &self.nodes {
+            let label = self.label(&node.id);
+            let _ = match node.cohesion {
+                Some(cohesion) => writeln!(
+                    out,
+                    "    \"{}\" [label=\"{}\\n{}\"];",
+                    node.id, label, cohesion
+                ),
+                None => writeln!(
+                    out,
+                    "    \"{}\" [label=\"{}\", style=dashed];",
+                    node.id, label
+                ),
+            };
+        }
+        for edge in &self.edges {
+            let color = color(edge.level);
+            let _ = writeln!(
+                out,
+                "    \"{}\" -> \"{}\" [label=\"{}\", color=\"{}\", fontcolor=\"{}\"];",
+                edge.from, edge.to, edge.level, color, color
+            );
+        }
+        let _ = writeln!(out, "}}");
+        out
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        let ids: BTreeMap<&str, String> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.id.as_str(), format!("n{}", index)))
+            .collect();
+        let mut out = String::new();
+        let _ = writeln!(out, "flowchart LR");
+        for node in &self.nodes {
+            let id = &ids[node.id.as_str()];
+            let label = self.label(&node.id);
+            let _ = match node.cohesion {
+                Some(cohesion) => writeln!(out, "    {}[\"{}<br/>{}\"]", id, label, cohesion),
+                None => writeln!(out, "    {}([\"{}\"])", id, label),
+            };
+        }
+        for edge in &self.edges {
+            let _ = writeln!(
+                out,
+                "    {} -->|{}| {}",
+                ids[edge.from.as_str()],
+                edge.level,
+                ids[edge.to.as_str()]
+            );
+        }
+        for (index, edge) in self.edges.iter().enumerate() {
+            let color = color(edge.level);
+            let _ = writeln!(
+                out,
+                "    linkStyle {} stroke:{},color:{}",
+                index, color, color
+            );
+        }
+        out
+    }
+}
+
+/// The weakest cohesion found in each module outside test modules: the
+/// affinity of its items, and the clusters of its functions.
+pub fn module_cohesion(krate: &Crate) -> BTreeMap<String, CohesionLevel> {
+    let mut cohesion: BTreeMap<String, CohesionLevel> = krate
+        .modules
+        .iter()
+        .filter(|module| !module.test)
+        .map(|module| (module.path.clone(), CohesionLevel::Function))
+        .collect();
+    let found = affinity::analyze(krate, affinity::DEFAULT_THRESHOLD)
+        .into_iter()
+        .map(|module| (module.module, module.level))
+        .chain(
+            clusters::detect(krate)
+                .into_iter()
+                .map(|finding| (finding.module, finding.level)),
+        );
+    for (module, level) in found {
+        let entry = cohesion.entry(module).or_insert(level);
+        *entry = (*entry).max(level);
+    }
+    cohesion
+}
+
+/// Edge color for a coupling level, from green to purple as it tightens.
+pub fn color(level: CouplingLevel) -> &'static str {
+    match level {
+        CouplingLevel::Data => "#2e7d32",
+        CouplingLevel::Stamp => "#9e9d24",
+        CouplingLevel::Control => "#f9a825",
+        CouplingLevel::External => "#ef6c00",
+        CouplingLevel::Common => "#c62828",
+        CouplingLevel::Content => "#6a1b9a",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::SourceFile;
+
+    #[test]
+    fn examples_render_with_their_levels() {
+        let graph = Graph::load(env!("CARGO_MANIFEST_DIR")).unwrap();
+
+        let control = graph.within("crate::coupling::control_coupling");
+        let dot = control.to_dot();
+        assert!(dot.contains(
+            "\"crate::coupling::control_coupling::report_generator\" -> \"crate::coupling::control_coupling::formatter\" [label=\"control\", color=\"#f9a825\", fontcolor=\"#f9a825\"];"
+        ));
+        assert!(dot.contains("[label=\"traits\\nfunction\"]"));
+
+        let external = graph.within("crate::coupling::external_coupling");
+        let mermaid = external.to_mermaid();
+        assert!(mermaid.contains("([\"csv\"])"));
+        assert!(mermaid.contains("[\"data_processor<br/>function\"]"));
+
+        let temporal = graph.within("crate::cohesion::temporal_cohesion");
+        assert!(temporal.to_dot().contains("[label=\"system\\ntemporal\"]"));
+
+        assert_eq!(
+            Graph::load(env!("CARGO_MANIFEST_DIR"))
+                .unwrap()
+                .to_mermaid(),
+            graph.to_mermaid()
+        );
+    }
+
+    #[test]
+    fn crates_named_like_crate_stay_external() {
+        let file = SourceFile::parse(
+            "lib.rs",
+            "mod registry {
+                 mod client {
+                     pub fn fetch(index: &crates_io::Index) -> crates_io::Registry { index.registry() }
+                 }
+             }",
+        )
+        .unwrap();
+        let graph =
+            Graph::build(&Crate::from_file("demo", file).unwrap()).within("crate::registry");
+        assert_eq!(
+            graph.to_mermaid(),
+            "flowchart LR
+    n0[\"client<br/>function\"]
+    n1([\"crates_io\"])
+    n0 -->|external| n1
+    linkStyle 0 stroke:#ef6c00,color:#ef6c00
+"
+        );
+    }
+
+    #[test]
+    fn small_crate_renders_exactly() {
+        let file = SourceFile::parse(
+            "lib.rs",
+            "mod telemetry { pub struct Record { pub id: u64, pub name: String } }
+             mod notification {
+                 use super::telemetry::Record;
+                 pub fn send(record: &Record) -> u64 { record.id }
+             }",
+        )
+        .unwrap();
+        let graph = Graph::build(&Crate::from_file("demo", file).unwrap());
+
+        assert_eq!(
+            graph.to_mermaid(),
+            "flowchart LR
+    n0[\"crate<br/>function\"]
+    n1[\"notification<br/>function\"]
+    n2[\"telemetry<br/>function\"]
+    n1 -->|stamp| n2
+    linkStyle 0 stroke:#9e9d24,color:#9e9d24
+"
+        );
+        assert_eq!(
+            graph.to_dot(),
+            "digraph dependencies {
+    rankdir=LR;
+    node [shape=box, fontname=\"Helvetica\"];
+    edge [fontname=\"Helvetica\"];
+    \"crate\" [label=\"crate\\nfunction\"];
+    \"crate::notification\" [label=\"notification\\nfunction\"];
+    \"crate::telemetry\" [label=\"telemetry\\nfunction\"];
+    \"crate::notification\" -> \"crate::telemetry\" [label=\"stamp\", color=\"#9e9d24\", fontcolor=\"#9e9d24\"];
+}
+"
+        );
+    }
+}
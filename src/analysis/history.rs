@@ -0,0 +1,579 @@
+//! ./analysis/history.rs
+//!
+//! How the metrics of a crate moved over its git history, as a time series
+//! with one row per sampled commit and module: instability, the worst LCOM4
+//! of the module's structs, and its dependencies at each coupling level.
+//!
+//! Commits are read with the `git` command line, first parents only, and
+//! only those touching the crate. Each sampled commit's `.rs` files are
+//! written to a scratch directory and loaded like a checkout.
+//!
+//! Results are cached by content: LCOM per file by blob hash, and whole
+//! samples by the hash of the crate's tree, which stays the same across
+//! commits that changed something else. The cache lives in the repository's
+//! git directory, so a rerun only analyzes commits it hasn't seen.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+
+use super::dependencies::classify;
+use super::modules::Crate;
+use super::{CouplingLevel, Error, lcom, metrics};
+
+/// Bumped whenever cached results would come out differently.
+pub const CACHE_VERSION: u32 = 1;
+
+const CACHE_FILE: &str = "coupling-history.json";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    /// Revision whose history is walked.
+    pub rev: String,
+    /// Commits to sample evenly, first and last included; 0 for all.
+    pub samples: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            rev: "HEAD".to_string(),
+            samples: 0,
+        }
+    }
+}
+
+/// One module at one commit.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModulePoint {
+    pub module: String,
+    pub instability: f64,
+    /// Highest LCOM4 among the module's structs, `None` without structs.
+    pub lcom4: Option<usize>,
+    /// Modules and crates depended on, by strongest coupling level; every
+    /// level is present.
+    pub coupling: BTreeMap<CouplingLevel, usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+    pub commit: String,
+    /// Committer date, ISO 8601.
+    pub date: String,
+    pub modules: Vec<ModulePoint>,
+}
+
+/// A sampled commit left out, such as one before the crate existed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Skipped {
+    pub commit: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Trend {
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    /// Oldest first.
+    pub samples: Vec<Sample>,
+    pub skipped: Vec<Skipped>,
+}
+
+/// LCOM4 of a struct, by name from its file's root.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StructLcom {
+    pub name: String,
+    pub lcom4: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Cache {
+    pub version: u32,
+    /// Structs of a file, by blob hash.
+    pub files: BTreeMap<String, Vec<StructLcom>>,
+    /// Modules of a sample, by the hash of the crate's tree.
+    pub trees: BTreeMap<String, Vec<ModulePoint>>,
+}
+
+impl Cache {
+    /// Where the cache of the repository around `dir` is kept.
+    pub fn path(dir: impl AsRef<Path>) -> Result<PathBuf, Error> {
+        let dir = dir.as_ref();
+        let git_dir = Git { dir }.run(&["rev-parse", "--absolute-git-dir"])?;
+        Ok(Path::new(git_dir.trim()).join(CACHE_FILE))
+    }
+
+    /// The cache at `path`, empty when missing, unreadable or stale.
+    pub fn load(path: impl AsRef<Path>) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str::<Cache>(&json).ok())
+            .filter(|cache| cache.version == CACHE_VERSION)
+            .unwrap_or_else(|| Cache {
+                version: CACHE_VERSION,
+                ..Cache::default()
+            })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let json = serde_json::to_string(self).expect("cache serializes");
+        fs::write(path, json).map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+}
+
+/// Walks the history of the crate in `dir`, which may be any directory of
+/// a repository holding a `src/lib.rs` or `src/main.rs`.
+pub fn walk(dir: impl AsRef<Path>, options: &Options, cache: &mut Cache) -> Result<Trend, Error> {
+    let dir = dir.as_ref();
+    let git = Git { dir };
+    let prefix = git.run(&["rev-parse", "--show-prefix"])?.trim().to_string();
+    let crate_name = dir
+        .canonicalize()
+        .ok()
+        .and_then(|dir| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "crate".to_string());
+
+    let log = git.run(&[
+        "log",
+        "--first-parent",
+        "--reverse",
+        "--format=%H %cI",
+        &options.rev,
+        "--",
+        ".",
+    ])?;
+    let commits: Vec<(&str, &str)> = log
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .collect();
+
+    let mut walker = Walker {
+        git,
+        prefix,
+        crate_name: crate_name.clone(),
+        texts: HashMap::new(),
+        cache,
+    };
+    let mut trend = Trend {
+        crate_name,
+        samples: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for (commit, date) in sample(&commits, options.samples) {
+        match walker.sample(commit)? {
+            Ok(modules) => trend.samples.push(Sample {
+                commit: commit.to_string(),
+                date: date.to_string(),
+                modules,
+            }),
+            Err(reason) => trend.skipped.push(Skipped {
+                commit: commit.to_string(),
+                reason,
+            }),
+        }
+    }
+    Ok(trend)
+}
+
+/// `count` items spread evenly over `items`, first and last included.
+fn sample<T: Copy>(items: &[T], count: usize) -> Vec<T> {
+    if count == 0 || count >= items.len() {
+        return items.to_vec();
+    }
+    if count == 1 {
+        return items.last().copied().into_iter().collect();
+    }
+    (0..count)
+        .map(|index| items[index * (items.len() - 1) / (count - 1)])
+        .collect()
+}
+
+struct Walker<'a> {
+    git: Git<'a>,
+    /// The crate's directory within the repository, `""` or ending in `/`.
+    prefix: String,
+    crate_name: String,
+    /// File contents by blob hash, for this run.
+    texts: HashMap<String, String>,
+    cache: &'a mut Cache,
+}
+
+impl Walker<'_> {
+    /// The modules at `commit`, or why there are none.
+    fn sample(&mut self, commit: &str) -> Result<Result<Vec<ModulePoint>, String>, Error> {
+        let Ok(tree) = self
+            .git
+            .run(&["rev-parse", &format!("{}:{}", commit, self.prefix)])
+        else {
+            return Ok(Err("the crate directory does not exist".to_string()));
+        };
+        let tree = tree.trim().to_string();
+        if let Some(modules) = self.cache.trees.get(&tree) {
+            return Ok(Ok(modules.clone()));
+        }
+
+        // Blob hash of every `.rs` file, by path within the crate.
+        let listing = self
+            .git
+            .run(&["ls-tree", "-r", "-z", "--full-tree", &tree])?;
+        let blobs: BTreeMap<String, String> = listing
+            .split('\0')
+            .filter_map(|entry| {
+                let (meta, path) = entry.split_once('\t')?;
+                let mut meta = meta.split(' ');
+                let (_, kind, hash) = (meta.next()?, meta.next()?, meta.next()?);
+                (kind == "blob" && path.ends_with(".rs"))
+                    .then(|| (path.to_string(), hash.to_string()))
+            })
+            .collect();
+        let missing: Vec<String> = blobs
+            .values()
+            .filter(|hash| !self.texts.contains_key(*hash))
+            .cloned()
+            .collect();
+        for (hash, text) in missing.iter().zip(self.git.blobs(&missing)?) {
+            self.texts.insert(hash.clone(), text);
+        }
+
+        let scratch =
+            std::env::temp_dir().join(format!("coupling-history-{}-{}", std::process::id(), tree));
+        for (path, hash) in &blobs {
+            let path = scratch.join(path);
+            let written = fs::create_dir_all(path.parent().unwrap_or(&scratch))
+                .and_then(|_| fs::write(&path, &self.texts[hash]));
+            if let Err(source) = written {
+                let _ = fs::remove_dir_all(&scratch);
+                return Err(Error::Io { path, source });
+            }
+        }
+        let loaded = ["src/lib.rs", "src/main.rs"]
+            .into_iter()
+            .map(|root| scratch.join(root))
+            .find(|root| root.is_file())
+            .map(|root| Crate::from_root(&self.crate_name, root));
+        let modules = match loaded {
+            Some(Ok(krate)) => Ok(self.measure(&krate, &scratch, &blobs)),
+            Some(Err(error)) => Err(error.to_string()),
+            None => Err("no src/lib.rs or src/main.rs".to_string()),
+        };
+        let _ = fs::remove_dir_all(&scratch);
+
+        let modules = match modules {
+            Ok(modules) => modules,
+            Err(reason) => {
+                let scratch = scratch.to_string_lossy();
+                return Ok(Err(reason.replace(&*scratch, "").replace("//", "/")));
+            }
+        };
+        self.cache.trees.insert(tree, modules.clone());
+        Ok(Ok(modules))
+    }
+
+    fn measure(
+        &mut self,
+        krate: &Crate,
+        scratch: &Path,
+        blobs: &BTreeMap<String, String>,
+    ) -> Vec<ModulePoint> {
+        let mut lcom4: BTreeMap<String, usize> = BTreeMap::new();
+        for (index, file) in krate.files.iter().enumerate() {
+            let Some(root) = krate.file_root(index) else {
+                continue;
+            };
+            let Some(hash) = file
+                .path()
+                .strip_prefix(scratch)
+                .ok()
+                .and_then(|path| blobs.get(&*path.to_string_lossy()))
+            else {
+                continue;
+            };
+            let structs = self.cache.files.entry(hash.clone()).or_insert_with(|| {
+                lcom::analyze(file)
+                    .structs
+                    .into_iter()
+                    .map(|cohesion| StructLcom {
+                        name: cohesion.name,
+                        lcom4: cohesion.lcom4,
+                    })
+                    .collect()
+            });
+            for item in structs.iter() {
+                let path = format!("{}::{}", root.path, item.name);
+                if let Some(module) = krate.module_containing(&path) {
+                    let worst = lcom4.entry(module.path.clone()).or_default();
+                    *worst = (*worst).max(item.lcom4);
+                }
+            }
+        }
+
+        let mut coupling: BTreeMap<String, BTreeMap<CouplingLevel, usize>> = BTreeMap::new();
+        for edge in classify(krate) {
+            *coupling
+                .entry(edge.from)
+                .or_default()
+                .entry(edge.level)
+                .or_default() += 1;
+        }
+
+        metrics::measure(krate)
+            .into_iter()
+            .map(|row| {
+                let counts = coupling.remove(&row.module).unwrap_or_default();
+                ModulePoint {
+                    lcom4: lcom4.get(&row.module).copied(),
+                    coupling: CouplingLevel::ALL
+                        .into_iter()
+                        .map(|level| (level, counts.get(&level).copied().unwrap_or(0)))
+                        .collect(),
+                    module: row.module,
+                    instability: row.instability,
+                }
+            })
+            .collect()
+    }
+}
+
+impl Trend {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("trend serializes")
+    }
+
+    /// One row per sample and module, the coupling levels as columns.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("commit,date,module,instability,lcom4");
+        for level in CouplingLevel::ALL {
+            let _ = write!(out, ",{}", level);
+        }
+        out.push('\n');
+        for sample in &self.samples {
+            for point in &sample.modules {
+                let lcom4 = point.lcom4.map_or(String::new(), |lcom4| lcom4.to_string());
+                let _ = write!(
+                    out,
+                    "{},{},{},{:.2},{}",
+                    sample.commit, sample.date, point.module, point.instability, lcom4
+                );
+                for count in point.coupling.values() {
+                    let _ = write!(out, ",{}", count);
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+/// The `git` command line, run in a directory of the repository.
+struct Git<'a> {
+    dir: &'a Path,
+}
+
+impl Git<'_> {
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new("git");
+        command.arg("-C").arg(self.dir).args(args);
+        command
+    }
+
+    fn failed(&self, args: &[&str], stderr: &[u8]) -> Error {
+        Error::Git {
+            command: format!("git {}", args.join(" ")),
+            message: String::from_utf8_lossy(stderr).trim().to_string(),
+        }
+    }
+
+    fn spawn_error(&self, source: std::io::Error) -> Error {
+        Error::Io {
+            path: PathBuf::from("git"),
+            source,
+        }
+    }
+
+    fn run(&self, args: &[&str]) -> Result<String, Error> {
+        let output = self
+            .command(args)
+            .output()
+            .map_err(|source| self.spawn_error(source))?;
+        if !output.status.success() {
+            return Err(self.failed(args, &output.stderr));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Contents of the blobs, in order, through one `git cat-file --batch`.
+    fn blobs(&self, hashes: &[String]) -> Result<Vec<String>, Error> {
+        if hashes.is_empty() {
+            return Ok(Vec::new());
+        }
+        let args = ["cat-file", "--batch"];
+        let mut child = self
+            .command(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|source| self.spawn_error(source))?;
+        // Written from another thread so a full stdout pipe can't block
+        // git while it waits for more input.
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let input: String = hashes.iter().map(|hash| format!("{}\n", hash)).collect();
+        let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+        let mut output = Vec::new();
+        let read = child
+            .stdout
+            .take()
+            .expect("stdout is piped")
+            .read_to_end(&mut output);
+        let written = writer.join().expect("writer does not panic");
+        let status = child.wait().map_err(|source| self.spawn_error(source))?;
+        if let Err(source) = read.and(written) {
+            return Err(self.spawn_error(source));
+        }
+        if !status.success() {
+            let mut stderr = Vec::new();
+            if let Some(mut pipe) = child.stderr.take() {
+                let _ = pipe.read_to_end(&mut stderr);
+            }
+            return Err(self.failed(&args, &stderr));
+        }
+
+        // `<hash> blob <size>\n<content>\n` per blob.
+        let mut texts = Vec::new();
+        let mut rest = &output[..];
+        for _ in hashes {
+            let header_end = rest.iter().position(|&byte| byte == b'\n');
+            let size = header_end.and_then(|end| {
+                let header = String::from_utf8_lossy(&rest[..end]);
+                header.rsplit(' ').next()?.parse::<usize>().ok()
+            });
+            let (Some(end), Some(size)) = (header_end, size) else {
+                return Err(self.failed(&args, b"unexpected output"));
+            };
+            let content = &rest[end + 1..end + 1 + size];
+            texts.push(String::from_utf8_lossy(content).into_owned());
+            rest = &rest[(end + 2 + size).min(rest.len())..];
+        }
+        Ok(texts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?}", args);
+    }
+
+    #[test]
+    fn samples_are_spread_over_the_history() {
+        assert_eq!(sample(&[1, 2, 3, 4, 5], 3), [1, 3, 5]);
+        assert_eq!(sample(&[1, 2, 3], 0), [1, 2, 3]);
+        assert_eq!(sample(&[1, 2, 3], 1), [3]);
+    }
+
+    #[test]
+    fn stamp_coupling_fixed_in_a_later_commit() {
+        let repo = std::env::temp_dir().join(format!("history-{}", std::process::id()));
+        let dir = repo.join("app");
+        let write = |path: &str, text: &str| {
+            let path = repo.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        };
+        git(
+            repo.parent().unwrap(),
+            &["init", "-q", repo.to_str().unwrap()],
+        );
+        write("README.md", "before the crate");
+        git(&repo, &["add", "-A"]);
+        git(&repo, &["commit", "-q", "-m", "readme"]);
+        write("app/src/lib.rs", "mod telemetry; mod notification;");
+        write(
+            "app/src/telemetry.rs",
+            "pub struct Record { pub id: u64, pub name: String }
+             impl Record {
+                 fn id(&self) -> u64 { self.id }
+                 fn name(&self) -> &str { &self.name }
+             }",
+        );
+        write(
+            "app/src/notification.rs",
+            "use crate::telemetry::Record;
+             pub fn send(record: &Record) -> u64 { record.id }",
+        );
+        git(&repo, &["add", "-A"]);
+        git(&repo, &["commit", "-q", "-m", "stamp"]);
+        write(
+            "app/src/notification.rs",
+            "pub fn send(id: u64) -> u64 { id }",
+        );
+        git(&repo, &["add", "-A"]);
+        git(&repo, &["commit", "-q", "-m", "data"]);
+
+        let mut cache = Cache::load(repo.join("missing.json"));
+        let trend = walk(&dir, &Options::default(), &mut cache).unwrap();
+        assert_eq!(trend.crate_name, "app");
+        assert_eq!(trend.samples.len(), 2);
+        let notification = |sample: &Sample| {
+            sample
+                .modules
+                .iter()
+                .find(|point| point.module == "crate::notification")
+                .unwrap()
+                .clone()
+        };
+        let before = notification(&trend.samples[0]);
+        let after = notification(&trend.samples[1]);
+        assert_eq!(before.coupling[&CouplingLevel::Stamp], 1);
+        assert_eq!(before.instability, 1.0);
+        assert_eq!(after.coupling[&CouplingLevel::Stamp], 0);
+        assert_eq!(after.instability, 0.0);
+        assert_eq!(trend.samples[1].modules[1].lcom4, Some(2));
+
+        let csv = trend.to_csv();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("commit,date,module,instability,lcom4,data,stamp,control,external,common,content")
+        );
+        assert!(csv.contains(",crate::telemetry,0.00,2,0,0,0,0,0,0\n"));
+        assert!(csv.contains(",crate::notification,1.00,,0,1,0,0,0,0\n"));
+
+        // A rerun takes whole samples from the cache.
+        assert_eq!(cache.trees.len(), 2);
+        let path = Cache::path(&dir).unwrap();
+        cache.save(&path).unwrap();
+        let mut cache = Cache::load(&path);
+        for modules in cache.trees.values_mut() {
+            modules[0].module = "cached".to_string();
+        }
+        let rerun = walk(&dir, &Options::default(), &mut cache).unwrap();
+        fs::remove_dir_all(&repo).unwrap();
+        assert_eq!(rerun.samples[0].modules[0].module, "cached");
+        let parsed: Trend = serde_json::from_str(&trend.to_json()).unwrap();
+        assert_eq!(parsed, trend);
+    }
+}
@@ -0,0 +1,412 @@
+//! ./analysis/lcom.rs
+//!
+//! Lack of cohesion in methods (LCOM) for every struct with `impl` blocks.
+//!
+//! Only methods taking `self` count; associated functions such as `new`
+//! build the value rather than work on it.
+//!
+//! - LCOM1: method pairs that share no field.
+//! - LCOM2: LCOM1 minus the pairs that do share a field, floored at 0.
+//! - LCOM4: connected groups of methods, linked by a shared field or a `self.` call.
+//!   1 means the struct does one thing.
+//! - TCC/LCC: share of method pairs connected directly / transitively through
+//!   the fields they reach, including through the methods they call.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use syn::visit::{self, Visit};
+
+use super::modules::Crate;
+use super::source::macro_args;
+use super::{Error, Location, SourceFile};
+
+/// Fields and sibling methods a single method touches through `self`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MethodUsage {
+    pub name: String,
+    pub fields: BTreeSet<String>,
+    pub calls: BTreeSet<String>,
+}
+
+/// Cohesion metrics of one struct.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructCohesion {
+    /// Path from the file root, e.g. `customer::CustomerProfile`.
+    pub name: String,
+    pub location: Location,
+    pub fields: Vec<String>,
+    pub methods: Vec<MethodUsage>,
+    pub lcom1: usize,
+    pub lcom2: usize,
+    pub lcom4: usize,
+    /// The LCOM4 groups, by method name.
+    pub components: Vec<Vec<String>>,
+    /// `None` with fewer than two methods, where pairs don't exist.
+    pub tcc: Option<f64>,
+    pub lcc: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CohesionReport {
+    pub file: PathBuf,
+    pub structs: Vec<StructCohesion>,
+}
+
+impl CohesionReport {
+    pub fn get(&self, name: &str) -> Option<&StructCohesion> {
+        self.structs.iter().find(|item| item.name == name)
+    }
+}
+
+pub fn analyze_file(path: impl AsRef<Path>) -> Result<CohesionReport, Error> {
+    Ok(analyze(&SourceFile::read(path)?))
+}
+
+/// Every struct of a crate outside test modules, by its module path, with
+/// names qualified from the crate root.
+pub fn analyze_crate(krate: &Crate) -> Vec<(String, StructCohesion)> {
+    let mut found = Vec::new();
+    for (index, file) in krate.files.iter().enumerate() {
+        let Some(root) = krate.file_root(index) else {
+            continue;
+        };
+        for mut cohesion in analyze(file).structs {
+            cohesion.name = format!("{}::{}", root.path, cohesion.name);
+            if let Some(module) = krate.module_containing(&cohesion.name)
+                && !module.test
+            {
+                found.push((module.path.clone(), cohesion));
+            }
+        }
+    }
+    found
+}
+
+pub fn analyze(file: &SourceFile) -> CohesionReport {
+    struct Declared {
+        module: String,
+        ident: String,
+        location: Location,
+        fields: Vec<String>,
+        methods: Vec<MethodUsage>,
+    }
+
+    let mut structs: Vec<Declared> = Vec::new();
+    let mut impls = Vec::new();
+    file.for_each_item(|scope, item| match item {
+        syn::Item::Struct(item) => structs.push(Declared {
+            module: scope.path(),
+            ident: item.ident.to_string(),
+            location: file.location(item.ident.span()),
+            fields: field_names(&item.fields),
+            methods: Vec::new(),
+        }),
+        syn::Item::Impl(item) => impls.push((scope.path(), item)),
+        _ => {}
+    });
+
+    for (module, item) in impls {
+        let syn::Type::Path(self_ty) = &*item.self_ty else {
+            continue;
+        };
+        let Some(ident) = self_ty.path.segments.last().map(|s| s.ident.to_string()) else {
+            continue;
+        };
+        let same_module = structs
+            .iter()
+            .position(|s| s.ident == ident && s.module == module);
+        let unique = || {
+            let mut candidates = (0..structs.len()).filter(|&i| structs[i].ident == ident);
+            let first = candidates.next();
+            first.filter(|_| candidates.next().is_none())
+        };
+        let Some(index) = same_module.or_else(unique) else {
+            continue;
+        };
+        for impl_item in &item.items {
+            if let syn::ImplItem::Fn(method) = impl_item
+                && method.sig.receiver().is_some()
+            {
+                let mut usage = SelfUsage::default();
+                usage.visit_block(&method.block);
+                structs[index].methods.push(MethodUsage {
+                    name: method.sig.ident.to_string(),
+                    fields: usage.fields,
+                    calls: usage.calls,
+                });
+            }
+        }
+    }
+
+    CohesionReport {
+        file: file.path().to_path_buf(),
+        structs: structs
+            .into_iter()
+            .filter(|s| !s.methods.is_empty())
+            .map(|s| {
+                let name = if s.module.is_empty() {
+                    s.ident
+                } else {
+                    format!("{}::{}", s.module, s.ident)
+                };
+                measure(name, s.location, s.fields, s.methods)
+            })
+            .collect(),
+    }
+}
+
+fn field_names(fields: &syn::Fields) -> Vec<String> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| match &field.ident {
+            Some(ident) => ident.to_string(),
+            None => index.to_string(),
+        })
+        .collect()
+}
+
+fn measure(
+    name: String,
+    location: Location,
+    fields: Vec<String>,
+    mut methods: Vec<MethodUsage>,
+) -> StructCohesion {
+    let method_names: BTreeSet<String> = methods.iter().map(|m| m.name.clone()).collect();
+    for method in &mut methods {
+        method.fields.retain(|field| fields.contains(field));
+        method.calls.retain(|call| method_names.contains(call));
+    }
+
+    let n = methods.len();
+    let pairs = || (0..n).flat_map(move |a| (a + 1..n).map(move |b| (a, b)));
+    let shares_field = |a: usize, b: usize| !methods[a].fields.is_disjoint(&methods[b].fields);
+
+    let disjoint = pairs().filter(|&(a, b)| !shares_field(a, b)).count();
+    let sharing = pairs().count() - disjoint;
+
+    let mut lcom4 = Components::new(n);
+    for (a, b) in pairs() {
+        let calls = |from: usize, to: usize| methods[from].calls.contains(&methods[to].name);
+        if shares_field(a, b) || calls(a, b) || calls(b, a) {
+            lcom4.join(a, b);
+        }
+    }
+
+    // Bieman and Kang count fields reached through invoked methods too.
+    let reached = reached_fields(&methods);
+    let mut transitive = Components::new(n);
+    let mut direct = 0;
+    for (a, b) in pairs() {
+        if !reached[a].is_disjoint(&reached[b]) {
+            direct += 1;
+            transitive.join(a, b);
+        }
+    }
+    let total = pairs().count();
+    let indirect = pairs()
+        .filter(|&(a, b)| transitive.find(a) == transitive.find(b))
+        .count();
+    let ratio = |connected: usize| (total > 0).then(|| connected as f64 / total as f64);
+
+    StructCohesion {
+        name,
+        location,
+        fields,
+        lcom1: disjoint,
+        lcom2: disjoint.saturating_sub(sharing),
+        lcom4: lcom4.count(),
+        components: lcom4
+            .groups()
+            .into_iter()
+            .map(|group| group.into_iter().map(|m| methods[m].name.clone()).collect())
+            .collect(),
+        tcc: ratio(direct),
+        lcc: ratio(indirect),
+        methods,
+    }
+}
+
+fn reached_fields(methods: &[MethodUsage]) -> Vec<BTreeSet<String>> {
+    let by_name: BTreeMap<&str, &MethodUsage> =
+        methods.iter().map(|m| (m.name.as_str(), m)).collect();
+    methods
+        .iter()
+        .map(|method| {
+            let mut fields = BTreeSet::new();
+            let mut seen = BTreeSet::new();
+            let mut pending = vec![method];
+            while let Some(current) = pending.pop() {
+                if !seen.insert(current.name.as_str()) {
+                    continue;
+                }
+                fields.extend(current.fields.iter().cloned());
+                pending.extend(current.calls.iter().filter_map(|c| by_name.get(c.as_str())));
+            }
+            fields
+        })
+        .collect()
+}
+
+/// Union-find over method indices, or any other numbered nodes.
+pub(crate) struct Components {
+    parent: Vec<usize>,
+}
+
+impl Components {
+    pub(crate) fn new(n: usize) -> Self {
+        Components {
+            parent: (0..n).collect(),
+        }
+    }
+
+    pub(crate) fn find(&mut self, node: usize) -> usize {
+        let mut root = node;
+        while self.parent[root] != root {
+            root = self.parent[root];
+        }
+        self.parent[node] = root;
+        root
+    }
+
+    pub(crate) fn join(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.parent[a.max(b)] = a.min(b);
+    }
+
+    fn count(&mut self) -> usize {
+        self.groups().len()
+    }
+
+    /// Groups in declaration order of their first member.
+    pub(crate) fn groups(&mut self) -> Vec<Vec<usize>> {
+        let mut groups: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for node in 0..self.parent.len() {
+            groups.entry(self.find(node)).or_default().push(node);
+        }
+        groups.into_values().collect()
+    }
+}
+
+/// Collects `self.field`, `self.method()` and `Self::method(..)` in a body.
+#[derive(Default)]
+struct SelfUsage {
+    fields: BTreeSet<String>,
+    calls: BTreeSet<String>,
+}
+
+impl<'ast> Visit<'ast> for SelfUsage {
+    fn visit_expr_field(&mut self, expr: &'ast syn::ExprField) {
+        if is_self(&expr.base) {
+            self.fields.insert(match &expr.member {
+                syn::Member::Named(ident) => ident.to_string(),
+                syn::Member::Unnamed(index) => index.index.to_string(),
+            });
+        }
+        visit::visit_expr_field(self, expr);
+    }
+
+    fn visit_expr_method_call(&mut self, expr: &'ast syn::ExprMethodCall) {
+        if is_self(&expr.receiver) {
+            self.calls.insert(expr.method.to_string());
+        }
+        visit::visit_expr_method_call(self, expr);
+    }
+
+    fn visit_expr_call(&mut self, expr: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(func) = &*expr.func
+            && let [first, method] = func.path.segments.iter().collect::<Vec<_>>()[..]
+            && first.ident == "Self"
+        {
+            self.calls.insert(method.ident.to_string());
+        }
+        visit::visit_expr_call(self, expr);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        for arg in macro_args(mac) {
+            self.visit_expr(&arg);
+        }
+    }
+}
+
+fn is_self(expr: &syn::Expr) -> bool {
+    matches!(expr, syn::Expr::Path(path) if path.path.is_ident("self"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report(name: &str, source: &str) -> CohesionReport {
+        analyze(&SourceFile::parse(name, source).unwrap())
+    }
+
+    #[test]
+    fn customer_profile_is_one_component() {
+        let report = report(
+            "communicational_cohesion.rs",
+            include_str!("../cohesion/communicational_cohesion.rs"),
+        );
+        let profile = report.get("customer::CustomerProfile").unwrap();
+
+        assert_eq!(profile.lcom4, 1);
+        assert_eq!(
+            profile.components,
+            vec![vec!["get_full_name", "update_email", "get_mailing_label"]]
+        );
+        // No two methods read the same field directly ...
+        assert_eq!((profile.lcom1, profile.lcom2), (3, 3));
+        // ... but all of them reach the name through `get_full_name`.
+        assert_eq!((profile.tcc, profile.lcc), (Some(1.0), Some(1.0)));
+        assert_eq!(profile.location.line, 10);
+    }
+
+    #[test]
+    fn circle_methods_share_the_radius() {
+        let report = report(
+            "function_cohesion.rs",
+            include_str!("../cohesion/function_cohesion.rs"),
+        );
+        let circle = report.get("circle_geometry::Circle").unwrap();
+
+        assert_eq!(circle.methods.len(), 2);
+        assert_eq!((circle.lcom1, circle.lcom2, circle.lcom4), (0, 0, 1));
+        assert_eq!(circle.tcc, Some(1.0));
+    }
+
+    #[test]
+    fn unrelated_method_groups_are_separate_components() {
+        let report = report(
+            "split.rs",
+            "struct Split { a: u8, b: u8, c: u8 }
+             impl Split {
+                 fn one(&self) -> u8 { self.a }
+                 fn two(&self) -> u8 { self.a + self.one() }
+                 fn three(&self) -> u8 { self.b }
+                 fn four(&mut self) { self.c = self.three() }
+             }",
+        );
+        let split = report.get("Split").unwrap();
+
+        assert_eq!(split.lcom4, 2);
+        assert_eq!(
+            split.components,
+            vec![vec!["one", "two"], vec!["three", "four"]]
+        );
+        assert_eq!((split.lcom1, split.lcom2), (5, 4));
+        assert_eq!(split.tcc, Some(2.0 / 6.0));
+    }
+
+    #[test]
+    fn structs_with_the_same_name_in_different_modules_stay_apart() {
+        let report = report(
+            "stamp_coupling.rs",
+            "mod a { pub struct Record { id: u8 } impl Record { fn id(&self) -> u8 { self.id } } }
+             mod b { pub struct Record { id: u8 } }",
+        );
+        assert_eq!(report.structs.len(), 1);
+        assert_eq!(report.structs[0].name, "a::Record");
+    }
+}
@@ -0,0 +1,144 @@
+//! ./analysis/level.rs
+//!
+//! The coupling ladder from `coupling.rs` and the cohesion ladder from
+//! `cohesion.rs`, as types the analyzers can report.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Coupling levels, ordered from loosest to tightest so `max` picks the worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CouplingLevel {
+    Data,
+    Stamp,
+    Control,
+    External,
+    Common,
+    Content,
+}
+
+impl CouplingLevel {
+    pub const ALL: [CouplingLevel; 6] = [
+        CouplingLevel::Data,
+        CouplingLevel::Stamp,
+        CouplingLevel::Control,
+        CouplingLevel::External,
+        CouplingLevel::Common,
+        CouplingLevel::Content,
+    ];
+
+    /// Position on the ladder, matching the catalog rank of its example.
+    pub fn rank(self) -> u8 {
+        self as u8 + 1
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CouplingLevel::Data => "data",
+            CouplingLevel::Stamp => "stamp",
+            CouplingLevel::Control => "control",
+            CouplingLevel::External => "external",
+            CouplingLevel::Common => "common",
+            CouplingLevel::Content => "content",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|level| level.as_str() == name)
+    }
+
+    /// Name of the catalog example that illustrates this level.
+    pub fn example(self) -> String {
+        format!("{}_coupling", self.as_str())
+    }
+}
+
+impl fmt::Display for CouplingLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Cohesion levels, ordered from strongest to weakest so `max` picks the worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CohesionLevel {
+    Function,
+    Sequence,
+    Communicational,
+    Procedural,
+    Temporal,
+    Logical,
+    Coincidental,
+}
+
+impl CohesionLevel {
+    pub const ALL: [CohesionLevel; 7] = [
+        CohesionLevel::Function,
+        CohesionLevel::Sequence,
+        CohesionLevel::Communicational,
+        CohesionLevel::Procedural,
+        CohesionLevel::Temporal,
+        CohesionLevel::Logical,
+        CohesionLevel::Coincidental,
+    ];
+
+    /// Position on the ladder, matching the catalog rank of its example.
+    pub fn rank(self) -> u8 {
+        self as u8 + 1
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CohesionLevel::Function => "function",
+            CohesionLevel::Sequence => "sequence",
+            CohesionLevel::Communicational => "communicational",
+            CohesionLevel::Procedural => "procedural",
+            CohesionLevel::Temporal => "temporal",
+            CohesionLevel::Logical => "logical",
+            CohesionLevel::Coincidental => "coincidental",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|level| level.as_str() == name)
+    }
+
+    /// Name of the catalog example that illustrates this level.
+    pub fn example(self) -> String {
+        format!("{}_cohesion", self.as_str())
+    }
+}
+
+impl fmt::Display for CohesionLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::Catalog;
+
+    #[test]
+    fn ranks_match_the_catalog() {
+        for level in CouplingLevel::ALL {
+            let example = Catalog::get(&level.example()).unwrap();
+            assert_eq!(example.rank(), level.rank());
+            assert_eq!(CouplingLevel::parse(level.as_str()), Some(level));
+        }
+        assert!(CouplingLevel::Content > CouplingLevel::Data);
+    }
+
+    #[test]
+    fn cohesion_ranks_match_the_catalog() {
+        for level in CohesionLevel::ALL {
+            let example = Catalog::get(&level.example()).unwrap();
+            assert_eq!(example.rank(), level.rank());
+            assert_eq!(CohesionLevel::parse(level.as_str()), Some(level));
+        }
+    }
+}
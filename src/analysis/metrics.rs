@@ -0,0 +1,190 @@
+//! ./analysis/metrics.rs
+//!
+//! Robert C. Martin's package metrics, per module:
+//!
+//! - Ca, afferent coupling: modules depending on this one
+//! - Ce, efferent coupling: modules and crates this one depends on
+//! - I = Ce / (Ca + Ce), instability, 0 when nothing depends either way
+//! - A, abstractness: share of traits and generic items among its items
+//! - D = |A + I - 1|, distance from the main sequence
+//!
+//! Dependencies are the edges of [`dependencies::classify`]. A module using
+//! its own submodules is composing them rather than coupling to a peer, so
+//! those edges are left out.
+//!
+//! [`dependencies::classify`]: super::dependencies::classify
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use std::path::Path;
+
+use super::Error;
+use super::dependencies::classify;
+use super::modules::Crate;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleMetrics {
+    pub module: String,
+    pub afferent: usize,
+    pub efferent: usize,
+    pub instability: f64,
+    pub abstractness: f64,
+    pub distance: f64,
+}
+
+pub fn measure_crate(dir: impl AsRef<Path>) -> Result<Vec<ModuleMetrics>, Error> {
+    Ok(measure(&Crate::load(dir)?))
+}
+
+/// Metrics of every module outside test modules, in declaration order.
+pub fn measure(krate: &Crate) -> Vec<ModuleMetrics> {
+    let mut incoming: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    let mut outgoing: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    let edges = classify(krate);
+    for edge in &edges {
+        if edge.to.starts_with(&format!("{}::", edge.from)) {
+            continue;
+        }
+        outgoing.entry(&edge.from).or_default().insert(&edge.to);
+        incoming.entry(&edge.to).or_default().insert(&edge.from);
+    }
+
+    krate
+        .modules
+        .iter()
+        .filter(|module| !module.test)
+        .map(|module| {
+            let path = module.path.as_str();
+            let afferent = incoming.get(path).map_or(0, BTreeSet::len);
+            let efferent = outgoing.get(path).map_or(0, BTreeSet::len);
+            let instability = match afferent + efferent {
+                0 => 0.0,
+                total => efferent as f64 / total as f64,
+            };
+            let (abstract_items, items) = module.items.iter().filter_map(is_abstract).fold(
+                (0, 0),
+                |(abstract_items, items), is_abstract| {
+                    (abstract_items + is_abstract as usize, items + 1)
+                },
+            );
+            let abstractness = match items {
+                0 => 0.0,
+                items => abstract_items as f64 / items as f64,
+            };
+            ModuleMetrics {
+                module: module.path.clone(),
+                afferent,
+                efferent,
+                instability,
+                abstractness,
+                distance: (abstractness + instability - 1.0).abs(),
+            }
+        })
+        .collect()
+}
+
+/// Whether an item is abstract, `None` for items that don't count
+/// either way, such as imports, impls and submodules.
+fn is_abstract(item: &syn::Item) -> Option<bool> {
+    let generic = |generics: &syn::Generics| generics.type_params().next().is_some();
+    match item {
+        syn::Item::Trait(_) => Some(true),
+        syn::Item::Struct(item) => Some(generic(&item.generics)),
+        syn::Item::Enum(item) => Some(generic(&item.generics)),
+        syn::Item::Union(item) => Some(generic(&item.generics)),
+        syn::Item::Type(item) => Some(generic(&item.generics)),
+        syn::Item::Fn(item) => {
+            let impl_trait = item.sig.inputs.iter().any(
+                |input| matches!(input, syn::FnArg::Typed(typed) if takes_impl_trait(&typed.ty)),
+            );
+            Some(generic(&item.sig.generics) || impl_trait)
+        }
+        _ => None,
+    }
+}
+
+fn takes_impl_trait(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::ImplTrait(_) => true,
+        syn::Type::Reference(ty) => takes_impl_trait(&ty.elem),
+        syn::Type::Paren(ty) => takes_impl_trait(&ty.elem),
+        _ => false,
+    }
+}
+
+/// The metrics as an aligned text table, one module per row.
+pub fn table(metrics: &[ModuleMetrics]) -> String {
+    let width = metrics
+        .iter()
+        .map(|row| row.module.len())
+        .max()
+        .unwrap_or(0)
+        .max("module".len());
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<width$}  {:>3}  {:>3}  {:>4}  {:>4}  {:>4}",
+        "module", "Ca", "Ce", "I", "A", "D"
+    );
+    for row in metrics {
+        let _ = writeln!(
+            out,
+            "{:<width$}  {:>3}  {:>3}  {:>4.2}  {:>4.2}  {:>4.2}",
+            row.module, row.afferent, row.efferent, row.instability, row.abstractness, row.distance
+        );
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::SourceFile;
+
+    const MODULE: &str = "crate::coupling::control_coupling";
+
+    fn module<'a>(metrics: &'a [ModuleMetrics], name: &str) -> &'a ModuleMetrics {
+        let path = format!("{}::{}", MODULE, name);
+        metrics.iter().find(|row| row.module == path).unwrap()
+    }
+
+    #[test]
+    fn traits_are_stable_and_the_generator_is_not() {
+        let metrics = measure_crate(env!("CARGO_MANIFEST_DIR")).unwrap();
+
+        let traits = module(&metrics, "traits");
+        assert_eq!(traits.efferent, 0);
+        assert!(traits.afferent >= 3);
+        assert_eq!(traits.instability, 0.0);
+        assert_eq!(traits.abstractness, 1.0);
+        assert_eq!(traits.distance, 0.0);
+
+        let generator = module(&metrics, "report_generator");
+        assert_eq!((generator.afferent, generator.efferent), (0, 1));
+        assert_eq!(generator.instability, 1.0);
+        assert_eq!(generator.abstractness, 0.0);
+
+        let generator_v2 = module(&metrics, "report_generator_v2");
+        assert_eq!(generator_v2.abstractness, 1.0);
+    }
+
+    #[test]
+    fn table_lists_every_module() {
+        let file = SourceFile::parse(
+            "lib.rs",
+            "mod shapes { pub trait Shape {} pub struct Square; }
+             mod draw { pub fn draw<S: super::shapes::Shape>(_: S) {} }",
+        )
+        .unwrap();
+        let metrics = measure(&Crate::from_file("demo", file).unwrap());
+        let shapes = &metrics[1];
+        assert_eq!(shapes.abstractness, 0.5);
+        assert_eq!(shapes.afferent, 1);
+
+        let table = table(&metrics);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines[0], "module          Ca   Ce     I     A     D");
+        assert_eq!(lines[2], "crate::shapes    1    0  0.00  0.50  0.50");
+        assert_eq!(lines[3], "crate::draw      0    1  1.00  1.00  1.00");
+    }
+}
@@ -0,0 +1,696 @@
+//! ./analysis/modules.rs
+//!
+//! The module tree of a crate, following `mod` declarations from the crate
+//! root, and a small name resolver so a path used in one module can be traced
+//! to the item it names in another.
+//!
+//! Resolution is syntactic: it knows `use`, `self`, `super`, `crate` and glob
+//! imports, but nothing about types, so method calls stay unresolved.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::Span;
+
+use super::source::is_cfg_test;
+use super::{Error, Location, SourceFile};
+
+/// Crates that ship with the compiler; references to them are not coupling.
+const STANDARD_CRATES: [&str; 3] = ["std", "core", "alloc"];
+
+/// What kind of item a name refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemKind {
+    Fn,
+    Struct,
+    Enum,
+    Trait,
+    Static,
+    Const,
+    Type,
+    Union,
+}
+
+/// A module, either a file of its own or an inline `mod name { .. }`.
+pub struct Module {
+    /// Full path, e.g. `crate::coupling::control_coupling::formatter`.
+    pub path: String,
+    /// Index into [`Crate::files`].
+    pub file: usize,
+    /// Declared under `#[cfg(test)]`, directly or through a parent.
+    pub test: bool,
+    /// The module's own items; nested modules appear as `syn::Item::Mod`
+    /// and have their own entry.
+    pub items: Vec<syn::Item>,
+    parent: Option<usize>,
+    children: BTreeMap<String, usize>,
+    /// Named items by identifier, as index into `items`.
+    defs: BTreeMap<String, usize>,
+    /// `use` aliases to the path segments they stand for.
+    imports: BTreeMap<String, Vec<String>>,
+    globs: Vec<Vec<String>>,
+    /// Directory that holds the files of `mod name;` children.
+    dir: PathBuf,
+}
+
+impl Module {
+    /// Last path segment, `crate` for the root.
+    pub fn name(&self) -> &str {
+        self.path.rsplit("::").next().unwrap_or(&self.path)
+    }
+
+    /// Named item defined directly in this module.
+    pub fn item(&self, name: &str) -> Option<&syn::Item> {
+        self.defs.get(name).map(|&index| &self.items[index])
+    }
+}
+
+/// A function or method body found in a module.
+#[derive(Clone, Copy)]
+pub struct Function<'c> {
+    pub module: usize,
+    /// The `Self` type for methods.
+    pub owner: Option<&'c syn::Type>,
+    pub sig: &'c syn::Signature,
+    pub block: &'c syn::Block,
+}
+
+/// What a path resolves to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    Module(usize),
+    Item {
+        module: usize,
+        name: String,
+    },
+    /// Something from another crate, by crate name.
+    External(String),
+}
+
+/// All modules of one crate.
+pub struct Crate {
+    pub name: String,
+    pub files: Vec<SourceFile>,
+    pub modules: Vec<Module>,
+}
+
+impl Crate {
+    /// Loads the crate in `dir`, rooted at `src/lib.rs` or else `src/main.rs`.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::load_open(dir, &BTreeMap::new())
+    }
+
+    /// Like [`Crate::load`], but the files in `open` are taken with the text
+    /// given, as an editor holds them before they are saved.
+    pub fn load_open(
+        dir: impl AsRef<Path>,
+        open: &BTreeMap<PathBuf, String>,
+    ) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        let lib = dir.join("src/lib.rs");
+        let root = if lib.exists() || open.contains_key(&lib) {
+            lib
+        } else {
+            dir.join("src/main.rs")
+        };
+        let name = dir
+            .canonicalize()
+            .ok()
+            .and_then(|dir| {
+                dir.file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+            })
+            .unwrap_or_else(|| "crate".to_string());
+        Self::with_sources(name, read(&root, open)?, open)
+    }
+
+    /// Loads a crate from its root file.
+    pub fn from_root(name: impl Into<String>, root: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::from_file(name, SourceFile::read(root)?)
+    }
+
+    /// A crate made of a single parsed file; `mod name;` children are read
+    /// from next to it.
+    pub fn from_file(name: impl Into<String>, file: SourceFile) -> Result<Self, Error> {
+        Self::with_sources(name, file, &BTreeMap::new())
+    }
+
+    fn with_sources(
+        name: impl Into<String>,
+        file: SourceFile,
+        open: &BTreeMap<PathBuf, String>,
+    ) -> Result<Self, Error> {
+        let dir = file.path().parent().unwrap_or(Path::new("")).to_path_buf();
+        let items = file.syntax().items.clone();
+        let mut krate = Crate {
+            name: name.into(),
+            files: vec![file],
+            modules: Vec::new(),
+        };
+        krate.add_module("crate".to_string(), None, 0, false, items, dir, open)?;
+        Ok(krate)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn add_module(
+        &mut self,
+        path: String,
+        parent: Option<usize>,
+        file: usize,
+        test: bool,
+        items: Vec<syn::Item>,
+        dir: PathBuf,
+        open: &BTreeMap<PathBuf, String>,
+    ) -> Result<usize, Error> {
+        let index = self.modules.len();
+        let mut module = Module {
+            path,
+            file,
+            test,
+            items,
+            parent,
+            children: BTreeMap::new(),
+            defs: BTreeMap::new(),
+            imports: BTreeMap::new(),
+            globs: Vec::new(),
+            dir,
+        };
+        for (position, item) in module.items.iter().enumerate() {
+            if let Some((name, _)) = item_name(item) {
+                module.defs.insert(name, position);
+            }
+            if let syn::Item::Use(item) = item {
+                flatten_use(
+                    &item.tree,
+                    &mut Vec::new(),
+                    &mut module.imports,
+                    &mut module.globs,
+                );
+            }
+        }
+        let children: Vec<syn::ItemMod> = module
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                syn::Item::Mod(item) => Some(item.clone()),
+                _ => None,
+            })
+            .collect();
+        self.modules.push(module);
+
+        for child in children {
+            let name = child.ident.to_string();
+            let path = format!("{}::{}", self.modules[index].path, name);
+            let test = test || is_cfg_test(&child.attrs);
+            let dir = self.modules[index].dir.clone();
+            let child_index = match child.content {
+                Some((_, items)) => {
+                    self.add_module(path, Some(index), file, test, items, dir.join(&name), open)?
+                }
+                None => {
+                    let (source, child_dir) = match path_attr(&child.attrs) {
+                        // Relative to the declaring file's own directory at
+                        // its top level, to the module directory inside
+                        // inline modules.
+                        Some(relative) => {
+                            let base = if self.is_file_root(index) {
+                                self.files[file].path().parent().unwrap_or(Path::new(""))
+                            } else {
+                                &dir
+                            };
+                            let source = base.join(relative);
+                            let child_dir = source.parent().unwrap_or(Path::new("")).to_path_buf();
+                            (source, child_dir)
+                        }
+                        None => module_file(&dir, &name),
+                    };
+                    let source_file = read(&source, open)?;
+                    let items = source_file.syntax().items.clone();
+                    self.files.push(source_file);
+                    let file = self.files.len() - 1;
+                    self.add_module(path, Some(index), file, test, items, child_dir, open)?
+                }
+            };
+            self.modules[index].children.insert(name, child_index);
+        }
+        Ok(index)
+    }
+
+    /// Whether the module is a file of its own rather than inline.
+    fn is_file_root(&self, module: usize) -> bool {
+        self.modules[module]
+            .parent
+            .is_none_or(|parent| self.modules[parent].file != self.modules[module].file)
+    }
+
+    /// The module a file holds at its top level.
+    pub fn file_root(&self, file: usize) -> Option<&Module> {
+        self.modules
+            .iter()
+            .enumerate()
+            .find(|(index, module)| module.file == file && self.is_file_root(*index))
+            .map(|(_, module)| module)
+    }
+
+    /// The innermost module containing the item or module at `path`.
+    pub fn module_containing(&self, path: &str) -> Option<&Module> {
+        self.modules
+            .iter()
+            .filter(|module| {
+                path.strip_prefix(&module.path)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .max_by_key(|module| module.path.len())
+    }
+
+    pub fn module(&self, path: &str) -> Option<&Module> {
+        self.modules.iter().find(|module| module.path == path)
+    }
+
+    pub fn index_of(&self, path: &str) -> Option<usize> {
+        self.modules.iter().position(|module| module.path == path)
+    }
+
+    pub fn file_of(&self, module: usize) -> &SourceFile {
+        &self.files[self.modules[module].file]
+    }
+
+    pub fn location(&self, module: usize, span: Span) -> Location {
+        self.file_of(module).location(span)
+    }
+
+    /// The source text a span covers, on one line.
+    pub fn snippet(&self, module: usize, span: Span) -> String {
+        let text = self.file_of(module).text();
+        let (start, end) = (span.start(), span.end());
+        let lines: Vec<&str> = text
+            .lines()
+            .skip(start.line.saturating_sub(1))
+            .take(end.line + 1 - start.line)
+            .collect();
+        let mut out = String::new();
+        for (offset, line) in lines.iter().enumerate() {
+            let from = if offset == 0 { start.column } else { 0 };
+            let to = if offset + 1 == lines.len() {
+                end.column
+            } else {
+                line.chars().count()
+            };
+            let part: String = line
+                .chars()
+                .skip(from)
+                .take(to.saturating_sub(from))
+                .collect();
+            if !out.is_empty() {
+                out.push(' ');
+            }
+            out.push_str(part.trim());
+        }
+        out
+    }
+
+    /// Every free function and method with a body, test modules included.
+    pub fn functions(&self) -> impl Iterator<Item = Function<'_>> {
+        self.modules.iter().enumerate().flat_map(|(index, module)| {
+            module.items.iter().flat_map(move |item| match item {
+                syn::Item::Fn(item) => vec![Function {
+                    module: index,
+                    owner: None,
+                    sig: &item.sig,
+                    block: &item.block,
+                }],
+                syn::Item::Impl(item) => item
+                    .items
+                    .iter()
+                    .filter_map(|impl_item| match impl_item {
+                        syn::ImplItem::Fn(method) => Some(Function {
+                            module: index,
+                            owner: Some(&*item.self_ty),
+                            sig: &method.sig,
+                            block: &method.block,
+                        }),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => Vec::new(),
+            })
+        })
+    }
+
+    /// Full path of a function, e.g. `crate::customer::CustomerProfile::new`.
+    pub fn function_path(&self, function: &Function) -> String {
+        let module = &self.modules[function.module].path;
+        match function.owner {
+            Some(syn::Type::Path(owner)) => match owner.path.segments.last() {
+                Some(ty) => format!("{}::{}::{}", module, ty.ident, function.sig.ident),
+                None => format!("{}::{}", module, function.sig.ident),
+            },
+            _ => format!("{}::{}", module, function.sig.ident),
+        }
+    }
+
+    /// Full path of a resolved target, the crate name for other crates.
+    pub fn target_path(&self, target: &Target) -> String {
+        match target {
+            Target::Module(module) => self.modules[*module].path.clone(),
+            Target::Item { module, name } => format!("{}::{}", self.modules[*module].path, name),
+            Target::External(name) => name.clone(),
+        }
+    }
+
+    /// The item a resolved target points at.
+    pub fn item(&self, target: &Target) -> Option<&syn::Item> {
+        match target {
+            Target::Item { module, name } => self.modules[*module].item(name),
+            _ => None,
+        }
+    }
+
+    /// Kind of the item a resolved target points at.
+    pub fn kind(&self, target: &Target) -> Option<ItemKind> {
+        self.item(target).and_then(item_name).map(|(_, kind)| kind)
+    }
+
+    /// Resolves `path` as written inside module `from`.
+    pub fn resolve(&self, from: usize, path: &syn::Path) -> Option<Target> {
+        let segments: Vec<String> = path.segments.iter().map(|s| s.ident.to_string()).collect();
+        self.resolve_segments(from, &segments, 0)
+    }
+
+    /// Resolves a path given as its segments, e.g. `["super", "Format"]`.
+    pub fn resolve_segments_from(&self, from: usize, segments: &[String]) -> Option<Target> {
+        self.resolve_segments(from, segments, 0)
+    }
+
+    /// Resolves a single identifier as seen from module `from`.
+    pub fn resolve_name(&self, from: usize, name: &str) -> Option<Target> {
+        self.resolve_segments(from, &[name.to_string()], 0)
+    }
+
+    fn resolve_segments(&self, from: usize, segments: &[String], depth: usize) -> Option<Target> {
+        // Imports that point at each other would otherwise never end.
+        if depth > 16 {
+            return None;
+        }
+        let (first, rest) = segments.split_first()?;
+        let mut target = match first.as_str() {
+            "crate" => Target::Module(0),
+            "self" => Target::Module(from),
+            "super" => Target::Module(self.modules[from].parent?),
+            "Self" => return None,
+            name => self.lookup(from, name, depth, !rest.is_empty())?,
+        };
+        for segment in rest {
+            target = match target {
+                Target::Module(module) if segment == "super" => {
+                    Target::Module(self.modules[module].parent?)
+                }
+                Target::Module(module) => self.member(module, segment, depth)?,
+                // Variants, associated items and anything after them
+                // belong to the item already found.
+                found => return Some(found),
+            };
+        }
+        Some(target)
+    }
+
+    /// A name in module scope: children, items, imports, then globs.
+    fn lookup(&self, from: usize, name: &str, depth: usize, qualified: bool) -> Option<Target> {
+        if let Some(found) = self.member(from, name, depth) {
+            return Some(found);
+        }
+        for glob in &self.modules[from].globs {
+            if let Some(Target::Module(module)) = self.resolve_segments(from, glob, depth + 1)
+                && let Some(found) = self.member(module, name, depth + 1)
+            {
+                return Some(found);
+            }
+        }
+        // Only a qualified path can name another crate; a bare unresolved
+        // identifier is a local, a generic or something from the prelude,
+        // and `Vec::new` or `u32::MAX` start with a type, not a crate.
+        (qualified && is_crate_name(name)).then(|| Target::External(name.to_string()))
+    }
+
+    /// A name defined or imported directly in `module`.
+    fn member(&self, module: usize, name: &str, depth: usize) -> Option<Target> {
+        let scope = &self.modules[module];
+        if let Some(&child) = scope.children.get(name) {
+            return Some(Target::Module(child));
+        }
+        if scope.defs.contains_key(name) {
+            return Some(Target::Item {
+                module,
+                name: name.to_string(),
+            });
+        }
+        let import = scope.imports.get(name)?;
+        if let [krate, ..] = import.as_slice()
+            && STANDARD_CRATES.contains(&krate.as_str())
+        {
+            return Some(Target::External(krate.clone()));
+        }
+        self.resolve_segments(module, import, depth + 1)
+    }
+}
+
+fn is_crate_name(name: &str) -> bool {
+    const PRIMITIVES: [&str; 17] = [
+        "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32",
+        "i64", "i128", "isize", "f32", "f64",
+    ];
+    name.starts_with(|c: char| c.is_ascii_lowercase() || c == '_') && !PRIMITIVES.contains(&name)
+}
+
+/// Whether a crate name is part of the standard distribution.
+pub fn is_standard_crate(name: &str) -> bool {
+    STANDARD_CRATES.contains(&name)
+}
+
+/// The file a `#[path = "..."]` attribute points a module at.
+fn path_attr(attrs: &[syn::Attribute]) -> Option<String> {
+    attrs.iter().find_map(|attr| match &attr.meta {
+        syn::Meta::NameValue(meta) if meta.path.is_ident("path") => match &meta.value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(path),
+                ..
+            }) => Some(path.value()),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+/// Where `mod name;` declared in a module with child directory `dir` lives.
+fn module_file(dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+    let flat = dir.join(format!("{}.rs", name));
+    if flat.exists() {
+        (flat, dir.join(name))
+    } else {
+        (dir.join(name).join("mod.rs"), dir.join(name))
+    }
+}
+
+/// A source file from `open` if it is there, from disk otherwise.
+fn read(path: &Path, open: &BTreeMap<PathBuf, String>) -> Result<SourceFile, Error> {
+    match open.get(path) {
+        Some(text) => SourceFile::parse(path, text.as_str()),
+        None => SourceFile::read(path),
+    }
+}
+
+pub(crate) fn item_name(item: &syn::Item) -> Option<(String, ItemKind)> {
+    let (ident, kind) = match item {
+        syn::Item::Fn(item) => (&item.sig.ident, ItemKind::Fn),
+        syn::Item::Struct(item) => (&item.ident, ItemKind::Struct),
+        syn::Item::Enum(item) => (&item.ident, ItemKind::Enum),
+        syn::Item::Trait(item) => (&item.ident, ItemKind::Trait),
+        syn::Item::Static(item) => (&item.ident, ItemKind::Static),
+        syn::Item::Const(item) => (&item.ident, ItemKind::Const),
+        syn::Item::Type(item) => (&item.ident, ItemKind::Type),
+        syn::Item::Union(item) => (&item.ident, ItemKind::Union),
+        _ => return None,
+    };
+    Some((ident.to_string(), kind))
+}
+
+pub(crate) fn flatten_use(
+    tree: &syn::UseTree,
+    prefix: &mut Vec<String>,
+    imports: &mut BTreeMap<String, Vec<String>>,
+    globs: &mut Vec<Vec<String>>,
+) {
+    match tree {
+        syn::UseTree::Path(path) => {
+            prefix.push(path.ident.to_string());
+            flatten_use(&path.tree, prefix, imports, globs);
+            prefix.pop();
+        }
+        syn::UseTree::Name(name) if name.ident == "self" => {
+            if let Some(last) = prefix.last() {
+                imports.insert(last.clone(), prefix.clone());
+            }
+        }
+        syn::UseTree::Name(name) => {
+            let mut path = prefix.clone();
+            path.push(name.ident.to_string());
+            imports.insert(name.ident.to_string(), path);
+        }
+        syn::UseTree::Rename(rename) => {
+            let mut path = prefix.clone();
+            if rename.ident != "self" {
+                path.push(rename.ident.to_string());
+            }
+            imports.insert(rename.rename.to_string(), path);
+        }
+        syn::UseTree::Glob(_) => globs.push(prefix.clone()),
+        syn::UseTree::Group(group) => {
+            for tree in &group.items {
+                flatten_use(tree, prefix, imports, globs);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn this_crate() -> Crate {
+        Crate::load(env!("CARGO_MANIFEST_DIR")).unwrap()
+    }
+
+    #[test]
+    fn follows_file_and_inline_modules() {
+        let krate = this_crate();
+        let formatter = krate
+            .module("crate::coupling::control_coupling::formatter")
+            .unwrap();
+
+        assert_eq!(formatter.name(), "formatter");
+        assert!(!formatter.test);
+        assert!(formatter.item("format_data").is_some());
+        assert!(
+            krate
+                .module("crate::coupling::control_coupling::tests")
+                .unwrap()
+                .test
+        );
+    }
+
+    #[test]
+    fn lists_functions_and_methods() {
+        let krate = this_crate();
+        let paths: Vec<String> = krate
+            .functions()
+            .map(|function| krate.function_path(&function))
+            .collect();
+
+        assert!(paths.contains(
+            &"crate::coupling::stamp_coupling::notification::send_telemetry".to_string()
+        ));
+        assert!(
+            paths.contains(
+                &"crate::cohesion::function_cohesion::circle_geometry::Circle::calculate_area"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn resolves_imports_and_relative_paths() {
+        let krate = this_crate();
+        let caller = krate
+            .index_of("crate::coupling::control_coupling::report_generator")
+            .unwrap();
+        let formatter = krate
+            .index_of("crate::coupling::control_coupling::formatter")
+            .unwrap();
+
+        let path: syn::Path = syn::parse_str("formatter::format_data").unwrap();
+        assert_eq!(
+            krate.resolve(caller, &path),
+            Some(Target::Item {
+                module: formatter,
+                name: "format_data".to_string()
+            })
+        );
+        let variant: syn::Path = syn::parse_str("Format::Xml").unwrap();
+        assert_eq!(
+            krate.kind(&krate.resolve(caller, &variant).unwrap()),
+            Some(ItemKind::Enum)
+        );
+        assert_eq!(krate.resolve_name(caller, "data"), None);
+    }
+
+    #[test]
+    fn resolves_glob_imports_and_other_crates() {
+        let krate = this_crate();
+        let tests = krate
+            .index_of("crate::coupling::control_coupling::tests")
+            .unwrap();
+        assert!(matches!(
+            krate.resolve_name(tests, "generate_report"),
+            Some(Target::Item { .. })
+        ));
+
+        let loader = krate
+            .index_of("crate::coupling::external_coupling::data_loader")
+            .unwrap();
+        let reader: syn::Path = syn::parse_str("csv::Reader::from_reader").unwrap();
+        assert_eq!(
+            krate.resolve(loader, &reader),
+            Some(Target::External("csv".to_string()))
+        );
+        let file: syn::Path = syn::parse_str("File::open").unwrap();
+        assert_eq!(
+            krate.resolve(loader, &file),
+            Some(Target::External("std".to_string()))
+        );
+        let vec: syn::Path = syn::parse_str("Vec::new").unwrap();
+        assert_eq!(krate.resolve(loader, &vec), None);
+    }
+
+    #[test]
+    fn follows_path_attributes() {
+        let dir = std::env::temp_dir().join(format!("modules-path-{}", std::process::id()));
+        let write = |path: &str, text: &str| {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, text).unwrap();
+        };
+        write(
+            "src/lib.rs",
+            "#[path = \"platform/unix.rs\"] mod sys; mod outer { #[path = \"deep.rs\"] mod deep; }",
+        );
+        write("src/platform/unix.rs", "mod helpers; pub fn open() {}");
+        write("src/platform/helpers.rs", "pub fn close() {}");
+        write("src/outer/deep.rs", "pub struct Deep;");
+
+        let krate = Crate::load(&dir).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(krate.module("crate::sys").unwrap().item("open").is_some());
+        assert!(
+            krate
+                .module("crate::sys::helpers")
+                .unwrap()
+                .item("close")
+                .is_some()
+        );
+        assert!(
+            krate
+                .module("crate::outer::deep")
+                .unwrap()
+                .item("Deep")
+                .is_some()
+        );
+        assert_eq!(krate.file_root(1).unwrap().path, "crate::sys");
+        assert_eq!(
+            krate
+                .module_containing("crate::sys::helpers::close")
+                .unwrap()
+                .path,
+            "crate::sys::helpers"
+        );
+    }
+}
@@ -0,0 +1,477 @@
+//! ./analysis/report.rs
+//!
+//! The findings of every detector in one list, serializable as versioned
+//! JSON or as SARIF 2.1.0 for code-scanning tools.
+//!
+//! Each coupling and cohesion level is a rule with a stable ID such as
+//! `coupling/stamp` or `cohesion/temporal`, and its severity follows its rank
+//! on the ladder.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::external::{self, Adapters};
+use super::modules::Crate;
+use super::shared_state::{self, SharedKind};
+use super::{CohesionLevel, CouplingLevel, Error, Location, affinity, clusters, control, stamp};
+use crate::catalog::Catalog;
+
+/// Bumped whenever a field of [`Report`] changes meaning or goes away.
+pub const SCHEMA_VERSION: u32 = 1;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// A level on either ladder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(tag = "ladder", content = "level", rename_all = "lowercase")]
+pub enum Level {
+    Coupling(CouplingLevel),
+    Cohesion(CohesionLevel),
+}
+
+impl Level {
+    /// Every level, in the order of the SARIF rules.
+    pub fn all() -> impl Iterator<Item = Level> {
+        CouplingLevel::ALL
+            .into_iter()
+            .map(Level::Coupling)
+            .chain(CohesionLevel::ALL.into_iter().map(Level::Cohesion))
+    }
+
+    /// Stable rule ID, e.g. `coupling/stamp`.
+    pub fn rule_id(self) -> String {
+        match self {
+            Level::Coupling(level) => format!("coupling/{}", level),
+            Level::Cohesion(level) => format!("cohesion/{}", level),
+        }
+    }
+
+    pub fn rank(self) -> u8 {
+        match self {
+            Level::Coupling(level) => level.rank(),
+            Level::Cohesion(level) => level.rank(),
+        }
+    }
+
+    /// The lower third of a ladder is a note, the middle a warning and
+    /// the top an error.
+    pub fn severity(self) -> Severity {
+        match self {
+            Level::Coupling(level) => match level.rank() {
+                1..=2 => Severity::Note,
+                3..=4 => Severity::Warning,
+                _ => Severity::Error,
+            },
+            Level::Cohesion(level) => match level.rank() {
+                1..=2 => Severity::Note,
+                3..=5 => Severity::Warning,
+                _ => Severity::Error,
+            },
+        }
+    }
+
+    /// Name of the catalog example, e.g. `stamp_coupling`.
+    pub fn example(self) -> String {
+        match self {
+            Level::Coupling(level) => level.example(),
+            Level::Cohesion(level) => level.example(),
+        }
+    }
+}
+
+/// SARIF result levels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Note => "note",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    pub rule: String,
+    #[serde(flatten)]
+    pub level: Level,
+    pub severity: Severity,
+    /// Path of the function, module or item the finding is about.
+    pub item: String,
+    pub message: String,
+    pub location: Location,
+}
+
+impl Finding {
+    fn new(level: Level, item: String, message: String, location: Location) -> Self {
+        Finding {
+            rule: level.rule_id(),
+            level,
+            severity: level.severity(),
+            item,
+            message,
+            location,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub schema_version: u32,
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    /// Sorted by file, then position.
+    pub findings: Vec<Finding>,
+}
+
+impl Report {
+    /// Analyzes the crate in `dir`, with file paths relative to it.
+    pub fn load(dir: impl AsRef<Path>, adapters: &Adapters) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        let mut report = Self::build(&Crate::load(dir)?, adapters);
+        for finding in &mut report.findings {
+            if let Ok(relative) = finding.location.file.strip_prefix(dir) {
+                finding.location.file = relative.to_path_buf();
+            }
+        }
+        Ok(report)
+    }
+
+    /// Runs every detector over the crate.
+    pub fn build(krate: &Crate, adapters: &Adapters) -> Self {
+        let mut findings = Vec::new();
+
+        for finding in stamp::detect(krate) {
+            let message = format!(
+                "`{}` takes the whole `{}` but reads only {}; pass what it needs: `{}`",
+                finding.parameter,
+                short(&finding.struct_path),
+                finding.used.join(", "),
+                finding.suggestion
+            );
+            findings.push(Finding::new(
+                Level::Coupling(CouplingLevel::Stamp),
+                finding.function,
+                message,
+                finding.location,
+            ));
+        }
+
+        for finding in control::detect(krate) {
+            let message = format!(
+                "`{}: {}` selects what `{}` does ({}); {}",
+                finding.parameter,
+                finding.flag,
+                short(&finding.function),
+                finding.variants.join(", "),
+                finding.suggestion
+            );
+            findings.push(Finding::new(
+                Level::Coupling(CouplingLevel::Control),
+                finding.function,
+                message,
+                finding.location,
+            ));
+        }
+
+        for violation in external::check(krate, adapters) {
+            let message = format!(
+                "`{}` names `{}`, which is tied to another crate, outside the adapter modules",
+                short(&violation.module),
+                short(&violation.item)
+            );
+            findings.push(Finding::new(
+                Level::Coupling(CouplingLevel::External),
+                violation.module,
+                message,
+                violation.location,
+            ));
+        }
+
+        for item in shared_state::detect(krate) {
+            let modules: Vec<&str> = item.modules().into_iter().collect();
+            let message = match item.kind {
+                SharedKind::RawPointer => format!(
+                    "`{}` reaches into memory it does not own through pointer arithmetic or a transmute",
+                    short(&item.item)
+                ),
+                _ => format!(
+                    "`{}` is global state used by {}",
+                    short(&item.item),
+                    modules.join(", ")
+                ),
+            };
+            findings.push(Finding::new(
+                Level::Coupling(item.level()),
+                item.item,
+                message,
+                item.location,
+            ));
+        }
+
+        for module in affinity::analyze(krate, affinity::DEFAULT_THRESHOLD) {
+            if module.level != CohesionLevel::Coincidental {
+                continue;
+            }
+            let shared: Vec<&str> = module
+                .missing
+                .iter()
+                .copied()
+                .filter(|&signal| signal != "links")
+                .collect();
+            let mut missing = Vec::new();
+            if let Some((last, rest)) = shared.split_last() {
+                missing.push(match rest {
+                    [] => format!("share no {}", last),
+                    _ => format!("share no {} or {}", rest.join(", "), last),
+                });
+            }
+            if module.missing.contains(&"links") {
+                missing.push("none calls or feeds another".to_string());
+            }
+            let message = format!(
+                "the items of `{}` have low relatedness {:.2} < threshold {:.2}{}",
+                short(&module.module),
+                module.relatedness,
+                affinity::DEFAULT_THRESHOLD,
+                match missing.as_slice() {
+                    [] => String::new(),
+                    _ => format!(": they {}", missing.join(" and ")),
+                }
+            );
+            findings.push(Finding::new(
+                Level::Cohesion(module.level),
+                module.module,
+                message,
+                module.location,
+            ));
+        }
+
+        for finding in clusters::detect(krate) {
+            let steps: Vec<String> = finding
+                .clusters
+                .iter()
+                .map(|cluster| format!("`{}`", cluster.signature()))
+                .collect();
+            let message = format!(
+                "`{}` runs {} steps that share no data; extract {}",
+                short(&finding.function),
+                steps.len(),
+                steps.join(", ")
+            );
+            findings.push(Finding::new(
+                Level::Cohesion(finding.level),
+                finding.function,
+                message,
+                finding.location,
+            ));
+        }
+
+        findings
+            .sort_by(|a, b| (&a.location, &a.rule, &a.item).cmp(&(&b.location, &b.rule, &b.item)));
+        Report {
+            schema_version: SCHEMA_VERSION,
+            crate_name: krate.name.clone(),
+            findings,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a report always serializes")
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// The report as a SARIF 2.1.0 log with a single run.
+    pub fn to_sarif(&self) -> String {
+        let levels: Vec<Level> = Level::all().collect();
+        let rules: Vec<serde_json::Value> = levels
+            .iter()
+            .map(|level| {
+                let name = level.example();
+                let description = Catalog::get(&name)
+                    .map(|example| example.summary())
+                    .unwrap_or_default();
+                json!({
+                    "id": level.rule_id(),
+                    "name": name,
+                    "shortDescription": { "text": name.replace('_', " ") },
+                    "fullDescription": { "text": description },
+                    "defaultConfiguration": { "level": level.severity().as_str() },
+                    "properties": { "rank": level.rank() },
+                })
+            })
+            .collect();
+        let results: Vec<serde_json::Value> = self
+            .findings
+            .iter()
+            .map(|finding| {
+                let location = &finding.location;
+                json!({
+                    "ruleId": finding.rule,
+                    "ruleIndex": levels.iter().position(|level| *level == finding.level),
+                    "level": finding.severity.as_str(),
+                    "message": { "text": finding.message },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": {
+                                "uri": location.file.to_string_lossy().replace('\\', "/"),
+                            },
+                            "region": {
+                                "startLine": location.line,
+                                "startColumn": location.column,
+                                "endLine": location.end_line,
+                                "endColumn": location.end_column,
+                            },
+                        },
+                        "logicalLocations": [{ "fullyQualifiedName": finding.item }],
+                    }],
+                })
+            })
+            .collect();
+        let log = json!({
+            "$schema": SARIF_SCHEMA,
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "coupling-cohesion",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules,
+                    },
+                },
+                "results": results,
+                // Locations count characters, not the UTF-16 units SARIF
+                // assumes otherwise.
+                "columnKind": "unicodeCodePoints",
+            }],
+        });
+        serde_json::to_string_pretty(&log).expect("a SARIF log always serializes")
+    }
+}
+
+/// The last two segments of a path, enough to recognize it in a message.
+fn short(path: &str) -> String {
+    let segments: Vec<&str> = path.split("::").collect();
+    segments[segments.len().saturating_sub(2)..].join("::")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::OnceLock;
+
+    fn this_crate() -> &'static Report {
+        static REPORT: OnceLock<Report> = OnceLock::new();
+        REPORT.get_or_init(|| {
+            let adapters = Adapters::new([
+                "coupling::external_coupling::data_loader",
+                "coupling::external_coupling::parser",
+            ]);
+            Report::load(env!("CARGO_MANIFEST_DIR"), &adapters).unwrap()
+        })
+    }
+
+    fn finding(rule: &str, item: &str) -> &'static Finding {
+        this_crate()
+            .findings
+            .iter()
+            .find(|finding| finding.rule == rule && finding.item.ends_with(item))
+            .unwrap_or_else(|| panic!("no {} finding for {}", rule, item))
+    }
+
+    #[test]
+    fn detector_findings_carry_rule_and_region() {
+        let stamp = finding("coupling/stamp", "notification::send_telemetry");
+        assert_eq!(stamp.severity, Severity::Note);
+        assert_eq!(
+            stamp.location.file,
+            Path::new("src/coupling/stamp_coupling.rs")
+        );
+        assert_eq!((stamp.location.line, stamp.location.end_line), (23, 23));
+        assert!(stamp.message.contains("send_telemetry(id: u64)"));
+
+        let control = finding("coupling/control", "formatter::format_data");
+        assert_eq!(control.severity, Severity::Warning);
+        assert_eq!(control.location.line, 18);
+        assert!(control.location.end_line > 18);
+
+        let temporal = finding("cohesion/temporal", "system::startup");
+        assert_eq!(temporal.severity, Severity::Warning);
+        let coincidental = finding("cohesion/coincidental", "utils");
+        assert_eq!(coincidental.severity, Severity::Error);
+        assert_eq!(
+            coincidental.message,
+            "the items of `coincidental_cohesion::utils` have low relatedness 0.00 < threshold 0.20: \
+             they share no types, calls or vocabulary and none calls or feeds another"
+        );
+        assert_eq!(
+            finding("coupling/content", "state_one_ptr").severity,
+            Severity::Error
+        );
+    }
+
+    #[test]
+    fn json_is_versioned_and_round_trips() {
+        let json = this_crate().to_json();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["schema_version"], SCHEMA_VERSION);
+        let stamp = value["findings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|finding| finding["rule"] == "coupling/stamp")
+            .unwrap();
+        assert_eq!(stamp["ladder"], "coupling");
+        assert_eq!(stamp["level"], "stamp");
+        assert_eq!(stamp["severity"], "note");
+
+        assert_eq!(&Report::from_json(&json).unwrap(), this_crate());
+    }
+
+    #[test]
+    fn sarif_lists_every_level_as_a_rule() {
+        let sarif: serde_json::Value = serde_json::from_str(&this_crate().to_sarif()).unwrap();
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        assert_eq!(run["columnKind"], "unicodeCodePoints");
+        assert_eq!(rules.len(), 13);
+        assert_eq!(rules[1]["id"], "coupling/stamp");
+        assert_eq!(rules[10]["id"], "cohesion/temporal");
+        assert_eq!(rules[5]["defaultConfiguration"]["level"], "error");
+
+        let result = run["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|result| {
+                result["ruleId"] == "coupling/control"
+                    && result["locations"][0]["logicalLocations"][0]["fullyQualifiedName"]
+                        .as_str()
+                        .is_some_and(|name| name.ends_with("formatter::format_data"))
+            })
+            .unwrap();
+        assert_eq!(result["ruleIndex"], 2);
+        assert_eq!(result["level"], "warning");
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(
+            location["artifactLocation"]["uri"],
+            "src/coupling/control_coupling.rs"
+        );
+        assert_eq!(location["region"]["startLine"], 18);
+        assert_eq!(location["region"]["startColumn"], 9);
+    }
+}
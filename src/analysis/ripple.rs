@@ -0,0 +1,857 @@
+//! ./analysis/ripple.rs
+//!
+//! The blast radius of renaming or removing one item: every module,
+//! function and test that names it and would have to change along with it.
+//! `content_coupling.rs` asks "what happens if we change name"; this counts
+//! and lists the answer.
+//!
+//! Targets are paths such as `Storage::view_count` for a field,
+//! `Storage::show_view_count` or `Reportable::view_count` for a method,
+//! `Format::Xml` for a variant, or `HtmlFormatter` for a whole item,
+//! qualified as far as needed to tell them apart. A field wins over a method
+//! of the same name, and a method from a trait impl stands for the trait's,
+//! since renaming it means renaming it everywhere the trait is implemented.
+//!
+//! Renaming and removing break the same places, so one list serves both.
+//! Paths are resolved; fields and methods are matched on receivers whose
+//! type is known from a parameter, `self`, a generic bound, or a typed or
+//! constructed local. A receiver of unknown type with the same member name
+//! is kept as a possible site.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+use proc_macro2::Span;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+use super::Location;
+use super::dependencies::{peel, typed_params};
+use super::modules::{Crate, ItemKind, Target, flatten_use, item_name};
+use super::source::macro_args;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetKind {
+    Field,
+    Method,
+    Variant,
+    Item,
+}
+
+impl TargetKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TargetKind::Field => "field",
+            TargetKind::Method => "method",
+            TargetKind::Variant => "variant",
+            TargetKind::Item => "item",
+        }
+    }
+}
+
+/// A place naming the target.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Site {
+    pub module: String,
+    /// Path of the enclosing function or method, `None` outside one, as
+    /// for an import or an impl header.
+    pub function: Option<String>,
+    pub test: bool,
+    /// Matched by name only, on a receiver of unknown type.
+    pub possible: bool,
+    /// The reference as written.
+    pub snippet: String,
+    pub location: Location,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ripple {
+    /// Full path, e.g. `crate::coupling::content_coupling::Storage::view_count`.
+    pub target: String,
+    pub kind: TargetKind,
+    /// In source order.
+    pub sites: Vec<Site>,
+}
+
+impl Ripple {
+    pub fn modules(&self) -> BTreeSet<&str> {
+        self.sites.iter().map(|site| site.module.as_str()).collect()
+    }
+
+    pub fn functions(&self) -> BTreeSet<&str> {
+        self.sites
+            .iter()
+            .filter_map(|site| site.function.as_deref())
+            .collect()
+    }
+
+    /// Test functions, and test modules for sites outside a function.
+    pub fn tests(&self) -> BTreeSet<&str> {
+        self.sites
+            .iter()
+            .filter(|site| site.test)
+            .map(|site| site.function.as_deref().unwrap_or(&site.module))
+            .collect()
+    }
+
+    pub fn possible(&self) -> usize {
+        self.sites.iter().filter(|site| site.possible).count()
+    }
+
+    /// A count line, then one line per site; possible ones marked `?`.
+    pub fn listing(&self) -> String {
+        let mut out = String::new();
+        let _ = write!(
+            out,
+            "{} ({}): {} sites in {} modules, {} functions, {} tests",
+            self.target,
+            self.kind.as_str(),
+            self.sites.len(),
+            self.modules().len(),
+            self.functions().len(),
+            self.tests().len()
+        );
+        match self.possible() {
+            0 => out.push('\n'),
+            possible => {
+                let _ = writeln!(out, ", {} possible", possible);
+            }
+        }
+        for site in &self.sites {
+            let _ = writeln!(
+                out,
+                "  {}{} {}  {}",
+                if site.possible { "?" } else { " " },
+                site.location,
+                site.function.as_deref().unwrap_or(&site.module),
+                site.snippet
+            );
+        }
+        out
+    }
+}
+
+/// Blast radii side by side, one target per row.
+pub fn table(ripples: &[Ripple]) -> String {
+    let width = ripples
+        .iter()
+        .map(|ripple| ripple.target.len())
+        .max()
+        .unwrap_or(0)
+        .max("target".len());
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:<width$}  {:<7}  {:>5}  {:>7}  {:>9}  {:>5}",
+        "target", "kind", "sites", "modules", "functions", "tests"
+    );
+    for ripple in ripples {
+        let _ = writeln!(
+            out,
+            "{:<width$}  {:<7}  {:>5}  {:>7}  {:>9}  {:>5}",
+            ripple.target,
+            ripple.kind.as_str(),
+            ripple.sites.len(),
+            ripple.modules().len(),
+            ripple.functions().len(),
+            ripple.tests().len()
+        );
+    }
+    out
+}
+
+/// Every site that changes with `target`, `None` when it names nothing.
+/// With several matches the first in declaration order is taken.
+pub fn ripple(krate: &Crate, target: &str) -> Option<Ripple> {
+    let impls = impls(krate);
+    let (goal, path) = locate(krate, &impls, target)?;
+    let kind = match goal {
+        Goal::Field { .. } => TargetKind::Field,
+        Goal::Method { .. } => TargetKind::Method,
+        Goal::Variant { .. } => TargetKind::Variant,
+        Goal::Item(_) => TargetKind::Item,
+    };
+
+    let mut sites = Vec::new();
+    for (index, module) in krate.modules.iter().enumerate() {
+        let mut finder = Finder {
+            krate,
+            goal: &goal,
+            impls: &impls,
+            module: index,
+            context: Context::default(),
+            sites: &mut sites,
+        };
+        for item in &module.items {
+            finder.context = Context {
+                test: module.test,
+                ..Context::default()
+            };
+            finder.visit_module_item(item);
+        }
+    }
+    sites.sort_by(|a: &Site, b: &Site| a.location.cmp(&b.location));
+    sites.dedup_by(|a, b| a.location == b.location);
+    Some(Ripple {
+        target: path,
+        kind,
+        sites,
+    })
+}
+
+/// What is being renamed, by the item that owns it.
+#[derive(Debug, Clone, PartialEq)]
+enum Goal {
+    Field { owner: Target, name: String },
+    Method { owner: Target, name: String },
+    Variant { owner: Target, name: String },
+    Item(Target),
+}
+
+/// An impl block, resolved from its module.
+struct Impl {
+    self_ty: Option<Target>,
+    trait_: Option<Target>,
+    methods: Vec<String>,
+}
+
+fn impls(krate: &Crate) -> Vec<Impl> {
+    let mut found = Vec::new();
+    for (index, module) in krate.modules.iter().enumerate() {
+        for item in &module.items {
+            let syn::Item::Impl(item) = item else {
+                continue;
+            };
+            let resolve = |ty: &syn::Type| match peel(ty) {
+                syn::Type::Path(ty) => krate.resolve(index, &ty.path),
+                _ => None,
+            };
+            found.push(Impl {
+                self_ty: resolve(&item.self_ty),
+                trait_: item
+                    .trait_
+                    .as_ref()
+                    .and_then(|(_, path, _)| krate.resolve(index, path)),
+                methods: item
+                    .items
+                    .iter()
+                    .filter_map(|item| match item {
+                        syn::ImplItem::Fn(method) => Some(method.sig.ident.to_string()),
+                        _ => None,
+                    })
+                    .collect(),
+            });
+        }
+    }
+    found
+}
+
+/// The goal `target` names and its full path.
+fn locate(krate: &Crate, impls: &[Impl], target: &str) -> Option<(Goal, String)> {
+    let target = target.strip_prefix("crate::").unwrap_or(target);
+    let matches = |path: &str, wanted: &str| {
+        let path = path.strip_prefix("crate::").unwrap_or(path);
+        path == wanted || path.ends_with(&format!("::{}", wanted))
+    };
+    let member = target.rsplit_once("::");
+
+    for (index, module) in krate.modules.iter().enumerate() {
+        if module.test {
+            continue;
+        }
+        for item in &module.items {
+            let Some((name, _)) = item_name(item) else {
+                continue;
+            };
+            let path = format!("{}::{}", module.path, name);
+            let owner = Target::Item {
+                module: index,
+                name,
+            };
+            if matches(&path, target) {
+                return Some((Goal::Item(owner), path));
+            }
+            let Some((_, member)) = member.filter(|(owner, _)| matches(&path, owner)) else {
+                continue;
+            };
+            let name = member.to_string();
+            let full = format!("{}::{}", path, member);
+            let goal = match item {
+                syn::Item::Struct(item)
+                    if item
+                        .fields
+                        .iter()
+                        .any(|field| field.ident.as_ref().is_some_and(|ident| ident == member)) =>
+                {
+                    Goal::Field { owner, name }
+                }
+                syn::Item::Enum(item) if item.variants.iter().any(|v| v.ident == member) => {
+                    Goal::Variant { owner, name }
+                }
+                syn::Item::Trait(item)
+                    if item.items.iter().any(
+                        |item| matches!(item, syn::TraitItem::Fn(method) if method.sig.ident == member),
+                    ) =>
+                {
+                    Goal::Method { owner, name }
+                }
+                _ => {
+                    let Some(found) = impls.iter().find(|found| {
+                        found.self_ty.as_ref() == Some(&owner)
+                            && found.methods.iter().any(|method| method == member)
+                    }) else {
+                        continue;
+                    };
+                    match &found.trait_ {
+                        Some(trait_) => {
+                            let trait_path = krate.target_path(trait_);
+                            return Some((
+                                Goal::Method {
+                                    owner: trait_.clone(),
+                                    name,
+                                },
+                                format!("{}::{}", trait_path, member),
+                            ));
+                        }
+                        None => Goal::Method { owner, name },
+                    }
+                }
+            };
+            return Some((goal, full));
+        }
+    }
+    None
+}
+
+/// What is known inside the item being visited.
+#[derive(Default)]
+struct Context {
+    function: Option<String>,
+    test: bool,
+    self_ty: Option<Target>,
+    /// Types of locals and parameters; traits for generic ones.
+    vars: BTreeMap<String, Vec<Target>>,
+    /// Trait bounds of generic parameters.
+    bounds: BTreeMap<String, Vec<Target>>,
+    /// `use` declarations inside the function body.
+    imports: BTreeMap<String, Vec<String>>,
+    globs: Vec<Vec<String>>,
+}
+
+struct Finder<'a> {
+    krate: &'a Crate,
+    goal: &'a Goal,
+    impls: &'a [Impl],
+    module: usize,
+    context: Context,
+    sites: &'a mut Vec<Site>,
+}
+
+impl Finder<'_> {
+    fn visit_module_item(&mut self, item: &syn::Item) {
+        let module_path = self.krate.modules[self.module].path.clone();
+        match item {
+            // Submodules are visited as modules of their own.
+            syn::Item::Mod(_) => {}
+            syn::Item::Fn(item) => {
+                self.context.function = Some(format!("{}::{}", module_path, item.sig.ident));
+                self.context.test |= is_test(&item.attrs);
+                self.add_signature(&item.sig);
+                visit::visit_item_fn(self, item);
+            }
+            syn::Item::Impl(item) => {
+                self.context.self_ty = self.types_of(&item.self_ty).into_iter().next();
+                self.add_generics(&item.generics);
+                let trait_ = item
+                    .trait_
+                    .as_ref()
+                    .and_then(|(_, path, _)| self.resolve(path));
+                visit::visit_generics(self, &item.generics);
+                if let Some((_, path, _)) = &item.trait_ {
+                    self.visit_path(path);
+                }
+                self.visit_type(&item.self_ty);
+
+                let owner = type_name(&item.self_ty);
+                for impl_item in &item.items {
+                    let syn::ImplItem::Fn(method) = impl_item else {
+                        self.context.function = None;
+                        visit::visit_impl_item(self, impl_item);
+                        continue;
+                    };
+                    self.enter(format!("{}::{}::{}", module_path, owner, method.sig.ident));
+                    self.context.test |= is_test(&method.attrs);
+                    self.add_signature(&method.sig);
+                    if let Goal::Method { owner, name } = self.goal
+                        && trait_.as_ref() == Some(owner)
+                        && method.sig.ident == name
+                    {
+                        self.site(method.sig.ident.span(), false);
+                    }
+                    visit::visit_impl_item_fn(self, method);
+                }
+            }
+            syn::Item::Trait(item) => {
+                self.context.self_ty = Some(Target::Item {
+                    module: self.module,
+                    name: item.ident.to_string(),
+                });
+                self.add_generics(&item.generics);
+                for trait_item in &item.items {
+                    match trait_item {
+                        syn::TraitItem::Fn(method) => {
+                            self.enter(format!(
+                                "{}::{}::{}",
+                                module_path, item.ident, method.sig.ident
+                            ));
+                            self.add_signature(&method.sig);
+                            visit::visit_trait_item_fn(self, method);
+                        }
+                        other => {
+                            self.context.function = None;
+                            visit::visit_trait_item(self, other);
+                        }
+                    }
+                }
+            }
+            other => self.visit_item(other),
+        }
+    }
+
+    /// Starts a method, keeping what its impl or trait declared.
+    fn enter(&mut self, function: String) {
+        self.context.function = Some(function);
+        self.context.vars.clear();
+        self.context.imports.clear();
+        self.context.globs.clear();
+    }
+
+    fn site(&mut self, span: Span, possible: bool) {
+        self.sites.push(Site {
+            module: self.krate.modules[self.module].path.clone(),
+            function: self.context.function.clone(),
+            test: self.context.test,
+            possible,
+            snippet: self.krate.snippet(self.module, span),
+            location: self.krate.location(self.module, span),
+        });
+    }
+
+    fn resolve(&self, path: &syn::Path) -> Option<Target> {
+        let segments: Vec<String> = path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect();
+        self.resolve_segments(&segments)
+    }
+
+    /// Resolves with the function's own imports and `Self` in scope.
+    fn resolve_segments(&self, segments: &[String]) -> Option<Target> {
+        let (first, rest) = segments.split_first()?;
+        if first == "Self" {
+            return self.context.self_ty.clone();
+        }
+        if let Some(import) = self.context.imports.get(first) {
+            let full: Vec<String> = import.iter().chain(rest).cloned().collect();
+            return self.krate.resolve_segments_from(self.module, &full);
+        }
+        if let Some(found) = self.krate.resolve_segments_from(self.module, segments) {
+            return Some(found);
+        }
+        self.context.globs.iter().find_map(|glob| {
+            let full: Vec<String> = glob.iter().chain(segments).cloned().collect();
+            self.krate.resolve_segments_from(self.module, &full)
+        })
+    }
+
+    /// The types or traits a written type stands for.
+    fn types_of(&self, ty: &syn::Type) -> Vec<Target> {
+        match peel(ty) {
+            syn::Type::Path(ty) => match ty.path.get_ident() {
+                Some(ident) if self.context.bounds.contains_key(&ident.to_string()) => {
+                    self.context.bounds[&ident.to_string()].clone()
+                }
+                _ => self.resolve(&ty.path).into_iter().collect(),
+            },
+            syn::Type::ImplTrait(ty) => self.traits_of(&ty.bounds),
+            syn::Type::TraitObject(ty) => self.traits_of(&ty.bounds),
+            _ => Vec::new(),
+        }
+    }
+
+    fn traits_of<'b>(
+        &self,
+        bounds: impl IntoIterator<Item = &'b syn::TypeParamBound>,
+    ) -> Vec<Target> {
+        bounds
+            .into_iter()
+            .filter_map(|bound| match bound {
+                syn::TypeParamBound::Trait(bound) => self.resolve(&bound.path),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn add_generics(&mut self, generics: &syn::Generics) {
+        for param in generics.type_params() {
+            let traits = self.traits_of(&param.bounds);
+            self.context
+                .bounds
+                .entry(param.ident.to_string())
+                .or_default()
+                .extend(traits);
+        }
+        for predicate in generics.where_clause.iter().flat_map(|w| &w.predicates) {
+            if let syn::WherePredicate::Type(predicate) = predicate
+                && let syn::Type::Path(ty) = &predicate.bounded_ty
+                && let Some(ident) = ty.path.get_ident()
+            {
+                let traits = self.traits_of(&predicate.bounds);
+                self.context
+                    .bounds
+                    .entry(ident.to_string())
+                    .or_default()
+                    .extend(traits);
+            }
+        }
+    }
+
+    fn add_signature(&mut self, sig: &syn::Signature) {
+        self.add_generics(&sig.generics);
+        for (name, ty) in typed_params(sig) {
+            let types = self.types_of(ty);
+            self.context.vars.insert(name, types);
+        }
+    }
+
+    /// Types of a receiver expression, `None` when unknown.
+    fn receiver_types(&self, expr: &syn::Expr) -> Option<Vec<Target>> {
+        match expr {
+            syn::Expr::Path(expr) => {
+                let ident = expr.path.get_ident()?;
+                if ident == "self" {
+                    return self.context.self_ty.clone().map(|ty| vec![ty]);
+                }
+                self.context
+                    .vars
+                    .get(&ident.to_string())
+                    .filter(|types| !types.is_empty())
+                    .cloned()
+            }
+            syn::Expr::Paren(expr) => self.receiver_types(&expr.expr),
+            syn::Expr::Reference(expr) => self.receiver_types(&expr.expr),
+            syn::Expr::Unary(expr) if matches!(expr.op, syn::UnOp::Deref(_)) => {
+                self.receiver_types(&expr.expr)
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether a value of one of `types` has the goal's `owner` as its type
+    /// or one of its traits.
+    fn has_owner(&self, types: &[Target], owner: &Target) -> bool {
+        types.iter().any(|ty| {
+            ty == owner
+                || self.impls.iter().any(|found| {
+                    found.self_ty.as_ref() == Some(ty) && found.trait_.as_ref() == Some(owner)
+                })
+        })
+    }
+
+    /// Records a member access on `receiver` if it may reach `owner`.
+    fn member_site(&mut self, receiver: &syn::Expr, owner: &Target, span: Span) {
+        match self.receiver_types(receiver) {
+            Some(types) if self.has_owner(&types, owner) => self.site(span, false),
+            Some(_) => {}
+            None => self.site(span, true),
+        }
+    }
+
+    fn path_names_goal(&self, segments: &[String]) -> bool {
+        match self.goal {
+            // `Self` keeps working after a rename.
+            Goal::Item(target) => {
+                segments.first().is_some_and(|first| first != "Self")
+                    && self.resolve_segments(segments).as_ref() == Some(target)
+            }
+            Goal::Variant { owner, name } | Goal::Method { owner, name } => {
+                let Some((last, prefix)) = segments.split_last() else {
+                    return false;
+                };
+                last == name
+                    && !prefix.is_empty()
+                    && self
+                        .resolve_segments(prefix)
+                        .is_some_and(|found| self.has_owner(&[found], owner))
+            }
+            Goal::Field { .. } => false,
+        }
+    }
+
+    /// The type a local is initialized with, from a constructor call, a
+    /// struct literal or a unit struct.
+    fn constructed(&self, expr: &syn::Expr) -> Vec<Target> {
+        let path = match expr {
+            syn::Expr::Call(call) => match &*call.func {
+                syn::Expr::Path(func) => &func.path,
+                _ => return Vec::new(),
+            },
+            syn::Expr::Struct(expr) => &expr.path,
+            syn::Expr::Path(expr) => &expr.path,
+            syn::Expr::Reference(expr) => return self.constructed(&expr.expr),
+            _ => return Vec::new(),
+        };
+        self.resolve(path)
+            .filter(|found| {
+                matches!(
+                    self.krate.kind(found),
+                    Some(ItemKind::Struct | ItemKind::Enum | ItemKind::Union | ItemKind::Type)
+                )
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+impl<'ast> Visit<'ast> for Finder<'_> {
+    fn visit_item_fn(&mut self, item: &'ast syn::ItemFn) {
+        // A function nested in a body; its parameters join the outer ones.
+        self.add_signature(&item.sig);
+        visit::visit_item_fn(self, item);
+    }
+
+    fn visit_item_mod(&mut self, _: &'ast syn::ItemMod) {}
+
+    // syn gives `&self` an implied `&Self` type, which is never written.
+    fn visit_receiver(&mut self, _: &'ast syn::Receiver) {}
+
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        let mut imports = BTreeMap::new();
+        let mut globs = Vec::new();
+        flatten_use(&item.tree, &mut Vec::new(), &mut imports, &mut globs);
+        if imports
+            .values()
+            .any(|segments| self.path_names_goal(segments))
+        {
+            self.site(item.tree.span(), false);
+        }
+        if self.context.function.is_some() {
+            self.context.imports.extend(imports);
+            self.context.globs.extend(globs);
+        }
+    }
+
+    fn visit_path(&mut self, path: &'ast syn::Path) {
+        let segments: Vec<String> = path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect();
+        if self.path_names_goal(&segments) {
+            self.site(path.span(), false);
+        }
+        visit::visit_path(self, path);
+    }
+
+    fn visit_expr_field(&mut self, expr: &'ast syn::ExprField) {
+        if let Goal::Field { owner, name } = self.goal
+            && matches!(&expr.member, syn::Member::Named(ident) if ident == name)
+        {
+            self.member_site(&expr.base, owner, expr.member.span());
+        }
+        visit::visit_expr_field(self, expr);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        if let Goal::Method { owner, name } = self.goal
+            && call.method == name
+        {
+            self.member_site(&call.receiver, owner, call.method.span());
+        }
+        visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_expr_struct(&mut self, expr: &'ast syn::ExprStruct) {
+        if let Goal::Field { owner, name } = self.goal
+            && self.resolve(&expr.path).as_ref() == Some(owner)
+        {
+            for field in &expr.fields {
+                if matches!(&field.member, syn::Member::Named(ident) if ident == name) {
+                    self.site(field.member.span(), false);
+                }
+            }
+        }
+        visit::visit_expr_struct(self, expr);
+    }
+
+    fn visit_pat_struct(&mut self, pat: &'ast syn::PatStruct) {
+        if let Goal::Field { owner, name } = self.goal
+            && self.resolve(&pat.path).as_ref() == Some(owner)
+        {
+            for field in &pat.fields {
+                if matches!(&field.member, syn::Member::Named(ident) if ident == name) {
+                    self.site(field.member.span(), false);
+                }
+            }
+        }
+        visit::visit_pat_struct(self, pat);
+    }
+
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        let (name, types) = match &local.pat {
+            syn::Pat::Type(pat) => match &*pat.pat {
+                syn::Pat::Ident(ident) => (Some(&ident.ident), self.types_of(&pat.ty)),
+                _ => (None, Vec::new()),
+            },
+            syn::Pat::Ident(ident) => (
+                Some(&ident.ident),
+                local
+                    .init
+                    .as_ref()
+                    .map_or_else(Vec::new, |init| self.constructed(&init.expr)),
+            ),
+            _ => (None, Vec::new()),
+        };
+        if let Some(name) = name {
+            self.context.vars.insert(name.to_string(), types);
+        }
+        visit::visit_local(self, local);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        for arg in macro_args(mac) {
+            self.visit_expr(&arg);
+        }
+        visit::visit_macro(self, mac);
+    }
+}
+
+fn is_test(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path().is_ident("test"))
+}
+
+fn type_name(ty: &syn::Type) -> String {
+    match peel(ty) {
+        syn::Type::Path(ty) => ty
+            .path
+            .segments
+            .last()
+            .map_or_else(String::new, |segment| segment.ident.to_string()),
+        _ => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::SourceFile;
+
+    const CONTENT: &str = "crate::coupling::content_coupling";
+    const CONTROL: &str = "crate::coupling::control_coupling";
+
+    fn this_crate() -> Crate {
+        Crate::load(env!("CARGO_MANIFEST_DIR")).unwrap()
+    }
+
+    #[test]
+    fn storage_field_and_methods() {
+        let krate = this_crate();
+
+        let field = ripple(&krate, "content_coupling::Storage::view_count").unwrap();
+        assert_eq!(field.kind, TargetKind::Field);
+        assert_eq!(field.target, format!("{}::Storage::view_count", CONTENT));
+        assert_eq!(
+            field.functions(),
+            BTreeSet::from([
+                format!("{}::Storage::show_view_count", CONTENT).as_str(),
+                format!("{}::Storage::view_count", CONTENT).as_str(),
+                format!("{}::Storage::modify_count", CONTENT).as_str(),
+            ])
+        );
+        assert_eq!(field.possible(), 0);
+
+        let method = ripple(&krate, "Storage::show_view_count").unwrap();
+        assert_eq!(method.kind, TargetKind::Method);
+        assert_eq!(method.sites.len(), 2);
+        assert_eq!(
+            method.tests(),
+            BTreeSet::from([format!("{}::tests::external_library", CONTENT).as_str()])
+        );
+
+        let contract = ripple(&krate, "Reportable::view_count").unwrap();
+        assert_eq!(
+            contract.functions(),
+            BTreeSet::from([
+                format!("{}::Storage::view_count", CONTENT).as_str(),
+                format!("{}::solution", CONTENT).as_str(),
+                format!("{}::tests::external_library_contract", CONTENT).as_str(),
+            ])
+        );
+    }
+
+    #[test]
+    fn control_flag_ripples_further_than_its_solution() {
+        let krate = this_crate();
+
+        let problem = ripple(&krate, "control_coupling::formatter::Format::Html").unwrap();
+        assert_eq!(problem.kind, TargetKind::Variant);
+        assert_eq!(
+            problem.functions(),
+            BTreeSet::from([
+                format!("{}::formatter::format_data", CONTROL).as_str(),
+                format!("{}::problem", CONTROL).as_str(),
+                format!("{}::tests::caller_dictate_callee", CONTROL).as_str(),
+            ])
+        );
+        assert_eq!(problem.tests().len(), 1);
+
+        let solution = ripple(&krate, "control_coupling::html_formatter::HtmlFormatter").unwrap();
+        assert_eq!(solution.kind, TargetKind::Item);
+        assert_eq!(
+            solution.functions(),
+            BTreeSet::from([format!("{}::solution", CONTROL).as_str()])
+        );
+        assert!(solution.tests().is_empty());
+
+        let table = table(&[problem, solution]);
+        assert!(table.starts_with("target"));
+        assert_eq!(table.lines().count(), 3);
+    }
+
+    #[test]
+    fn unknown_receivers_are_possible_sites() {
+        let file = SourceFile::parse(
+            "lib.rs",
+            "pub struct Thing { pub name: String }
+             pub struct Other { pub name: String }
+             fn make() -> Thing { Thing { name: String::new() } }
+             fn known(thing: &Thing) -> &str { &thing.name }
+             fn other(other: &Other) -> &str { &other.name }
+             fn unknown() -> String { make().name }",
+        )
+        .unwrap();
+        let krate = Crate::from_file("demo", file).unwrap();
+        let ripple = ripple(&krate, "Thing::name").unwrap();
+
+        let sites: Vec<(&str, bool)> = ripple
+            .sites
+            .iter()
+            .map(|site| (site.function.as_deref().unwrap(), site.possible))
+            .collect();
+        assert_eq!(
+            sites,
+            [
+                ("crate::make", false),
+                ("crate::known", false),
+                ("crate::unknown", true)
+            ]
+        );
+        assert!(
+            ripple
+                .listing()
+                .contains("3 sites in 1 modules, 3 functions, 0 tests, 1 possible")
+        );
+        assert!(super::ripple(&krate, "Thing::missing").is_none());
+    }
+}
@@ -0,0 +1,590 @@
+//! ./analysis/shared_state.rs
+//!
+//! Common and content coupling through state that is not passed around:
+//!
+//! - `static mut` items, always
+//! - statics holding a lock or cell (`Mutex`, `RwLock`, `OnceLock`, ...),
+//!   once more than one module touches them
+//! - pointer arithmetic (`offset`, `byte_offset`, `add`, ...) in `unsafe` code
+//! - `transmute` of a pointer into an integer
+//!
+//! Findings are grouped by the item shared, listing every access to it.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+
+use proc_macro2::Span;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+use super::dependencies::{POINTER_ARITHMETIC, peel};
+use super::modules::{Crate, Function};
+use super::source::macro_args;
+use super::{CouplingLevel, Error, Location};
+
+/// Types that make a `static` mutable from anywhere.
+const GLOBAL_CELLS: [&str; 10] = [
+    "Mutex",
+    "RwLock",
+    "OnceLock",
+    "OnceCell",
+    "LazyLock",
+    "Lazy",
+    "RefCell",
+    "Cell",
+    "UnsafeCell",
+    "AtomicPtr",
+];
+
+/// Methods through which a shared static gets changed.
+const WRITES: [&str; 15] = [
+    "write",
+    "set",
+    "get_mut",
+    "get_or_init",
+    "store",
+    "swap",
+    "replace",
+    "take",
+    "insert",
+    "push",
+    "fetch_add",
+    "remove",
+    "pop",
+    "extend",
+    "clear",
+];
+
+/// Methods handing out a guard over a static's contents. Whether they
+/// read or write depends on what is done with the guard: assigning
+/// through it or calling one of the [`WRITES`] on it writes.
+const GUARDS: [&str; 1] = ["lock"];
+
+const INTEGERS: [&str; 12] = [
+    "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64", "i128", "isize",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SharedKind {
+    StaticMut,
+    /// A static lock or cell, including atomics.
+    GlobalCell,
+    /// Memory reached through a raw pointer.
+    RawPointer,
+}
+
+impl SharedKind {
+    pub fn level(self) -> CouplingLevel {
+        match self {
+            SharedKind::StaticMut | SharedKind::GlobalCell => CouplingLevel::Common,
+            SharedKind::RawPointer => CouplingLevel::Content,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AccessKind {
+    Read,
+    Write,
+    PointerArithmetic,
+    Transmute,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Access {
+    pub module: String,
+    pub function: String,
+    pub kind: AccessKind,
+    pub location: Location,
+}
+
+/// One piece of shared state and everything that touches it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedItem {
+    /// Path of the static, or `function::pointer` for raw pointers.
+    pub item: String,
+    pub kind: SharedKind,
+    pub location: Location,
+    pub accesses: Vec<Access>,
+}
+
+impl SharedItem {
+    pub fn level(&self) -> CouplingLevel {
+        self.kind.level()
+    }
+
+    /// Every module that reads or writes the item.
+    pub fn modules(&self) -> BTreeSet<&str> {
+        self.accesses
+            .iter()
+            .map(|access| access.module.as_str())
+            .collect()
+    }
+}
+
+pub fn detect_crate(dir: impl AsRef<Path>) -> Result<Vec<SharedItem>, Error> {
+    Ok(detect(&Crate::load(dir)?))
+}
+
+/// Shared state reached from code outside test modules.
+pub fn detect(krate: &Crate) -> Vec<SharedItem> {
+    let mut items: BTreeMap<String, SharedItem> = BTreeMap::new();
+    for (module, scope) in krate.modules.iter().enumerate() {
+        for item in &scope.items {
+            if let syn::Item::Static(item) = item {
+                let kind = if matches!(item.mutability, syn::StaticMutability::Mut(_)) {
+                    SharedKind::StaticMut
+                } else if holds_global_cell(&item.ty) {
+                    SharedKind::GlobalCell
+                } else {
+                    continue;
+                };
+                let path = format!("{}::{}", scope.path, item.ident);
+                items.insert(
+                    path.clone(),
+                    SharedItem {
+                        item: path,
+                        kind,
+                        location: krate.location(module, item.ident.span()),
+                        accesses: Vec::new(),
+                    },
+                );
+            }
+        }
+    }
+
+    for function in krate.functions() {
+        if krate.modules[function.module].test {
+            continue;
+        }
+        let mut accesses = Accesses {
+            krate,
+            function: &function,
+            unsafe_code: function.sig.unsafety.is_some(),
+            integer_binding: false,
+            guards: HashMap::new(),
+            found: Vec::new(),
+        };
+        accesses.visit_block(function.block);
+        let path = krate.function_path(&function);
+        for (item, kind, span) in accesses.found {
+            let access = Access {
+                module: krate.modules[function.module].path.clone(),
+                function: path.clone(),
+                kind,
+                location: krate.location(function.module, span),
+            };
+            match item {
+                Shared::Static(item) => {
+                    if let Some(shared) = items.get_mut(&item) {
+                        shared.accesses.push(access);
+                    }
+                }
+                Shared::Pointer(pointer) => {
+                    let item = format!("{}::{}", path, pointer);
+                    items
+                        .entry(item.clone())
+                        .or_insert_with(|| SharedItem {
+                            item,
+                            kind: SharedKind::RawPointer,
+                            location: access.location.clone(),
+                            accesses: Vec::new(),
+                        })
+                        .accesses
+                        .push(access);
+                }
+            }
+        }
+    }
+
+    items
+        .into_values()
+        .filter(|item| match item.kind {
+            SharedKind::GlobalCell => item.modules().len() > 1,
+            _ => true,
+        })
+        .collect()
+}
+
+fn holds_global_cell(ty: &syn::Type) -> bool {
+    struct Cells(bool);
+
+    impl<'ast> Visit<'ast> for Cells {
+        fn visit_path_segment(&mut self, segment: &'ast syn::PathSegment) {
+            let name = segment.ident.to_string();
+            self.0 |= GLOBAL_CELLS.contains(&name.as_str()) || name.starts_with("Atomic");
+            visit::visit_path_segment(self, segment);
+        }
+    }
+
+    let mut cells = Cells(false);
+    cells.visit_type(ty);
+    cells.0
+}
+
+fn is_integer(ty: &syn::Type) -> bool {
+    matches!(peel(ty), syn::Type::Path(ty) if INTEGERS.iter().any(|int| ty.path.is_ident(int)))
+}
+
+enum Shared {
+    Static(String),
+    Pointer(String),
+}
+
+struct Accesses<'c, 'f> {
+    krate: &'c Crate,
+    function: &'f Function<'c>,
+    unsafe_code: bool,
+    /// Inside the initializer of a `let` typed as an integer.
+    integer_binding: bool,
+    /// Locals holding a guard, with the access the lock was recorded as.
+    guards: HashMap<String, usize>,
+    found: Vec<(Shared, AccessKind, Span)>,
+}
+
+/// What an expression reaches a static's contents through.
+enum Guarded<'e> {
+    /// A `lock()` on the static, given as its receiver.
+    Lock(&'e syn::Expr),
+    /// A local guard, by the index of its access.
+    Local(usize),
+}
+
+impl Accesses<'_, '_> {
+    /// The static a path expression names, if it is one.
+    fn static_path(&self, expr: &syn::Expr) -> Option<String> {
+        let syn::Expr::Path(expr) = expr else {
+            return None;
+        };
+        let target = self.krate.resolve(self.function.module, &expr.path)?;
+        match self.krate.item(&target) {
+            Some(syn::Item::Static(_)) => Some(self.krate.target_path(&target)),
+            _ => None,
+        }
+    }
+
+    fn record_static(&mut self, expr: &syn::Expr, kind: AccessKind) -> bool {
+        match self.static_path(expr) {
+            Some(path) => {
+                self.found.push((Shared::Static(path), kind, expr.span()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The guard `expr` goes through, like `*STATE.lock().unwrap()` or
+    /// `guard.field`.
+    fn guarded<'e>(&self, mut expr: &'e syn::Expr) -> Option<Guarded<'e>> {
+        loop {
+            expr = match expr {
+                syn::Expr::Paren(inner) => &inner.expr,
+                syn::Expr::Unary(inner) if matches!(inner.op, syn::UnOp::Deref(_)) => &inner.expr,
+                syn::Expr::Field(inner) => &inner.base,
+                syn::Expr::Index(inner) => &inner.expr,
+                syn::Expr::Path(path) => {
+                    let ident = path.path.get_ident()?.to_string();
+                    return self.guards.get(&ident).copied().map(Guarded::Local);
+                }
+                _ => return self.guard(expr).map(Guarded::Lock),
+            }
+        }
+    }
+
+    /// The static `expr` locks, unwrapped or not: `STATE.lock().unwrap()`.
+    fn guard<'e>(&self, mut expr: &'e syn::Expr) -> Option<&'e syn::Expr> {
+        loop {
+            expr = match expr {
+                syn::Expr::Paren(inner) => &inner.expr,
+                syn::Expr::Try(inner) => &inner.expr,
+                syn::Expr::MethodCall(call)
+                    if call.method == "unwrap" || call.method == "expect" =>
+                {
+                    &call.receiver
+                }
+                syn::Expr::MethodCall(call)
+                    if GUARDS.contains(&call.method.to_string().as_str())
+                        && self.static_path(&call.receiver).is_some() =>
+                {
+                    return Some(&call.receiver);
+                }
+                _ => return None,
+            }
+        }
+    }
+
+    /// Records a write through a guard, if `expr` goes through one.
+    fn write_through(&mut self, expr: &syn::Expr) -> bool {
+        match self.guarded(expr) {
+            Some(Guarded::Lock(receiver)) => self.record_static(receiver, AccessKind::Write),
+            Some(Guarded::Local(at)) => {
+                self.found[at].1 = AccessKind::Write;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn pointer(&mut self, expr: &syn::Expr, kind: AccessKind, span: Span) {
+        let pointer = self.krate.snippet(self.function.module, expr.span());
+        self.found.push((Shared::Pointer(pointer), kind, span));
+    }
+}
+
+impl<'ast> Visit<'ast> for Accesses<'_, '_> {
+    fn visit_expr_path(&mut self, expr: &'ast syn::ExprPath) {
+        // Reached only when no write was recognized around it.
+        if let Some(path) = self.static_path(&syn::Expr::Path(expr.clone())) {
+            self.found
+                .push((Shared::Static(path), AccessKind::Read, expr.span()));
+        }
+    }
+
+    fn visit_expr_assign(&mut self, expr: &'ast syn::ExprAssign) {
+        if !self.record_static(&expr.left, AccessKind::Write) && !self.write_through(&expr.left) {
+            self.visit_expr(&expr.left);
+        }
+        self.visit_expr(&expr.right);
+    }
+
+    fn visit_expr_binary(&mut self, expr: &'ast syn::ExprBinary) {
+        let compound = matches!(
+            expr.op,
+            syn::BinOp::AddAssign(_)
+                | syn::BinOp::SubAssign(_)
+                | syn::BinOp::MulAssign(_)
+                | syn::BinOp::DivAssign(_)
+                | syn::BinOp::RemAssign(_)
+                | syn::BinOp::BitXorAssign(_)
+                | syn::BinOp::BitAndAssign(_)
+                | syn::BinOp::BitOrAssign(_)
+                | syn::BinOp::ShlAssign(_)
+                | syn::BinOp::ShrAssign(_)
+        );
+        if !(compound
+            && (self.record_static(&expr.left, AccessKind::Write)
+                || self.write_through(&expr.left)))
+        {
+            self.visit_expr(&expr.left);
+        }
+        self.visit_expr(&expr.right);
+    }
+
+    fn visit_expr_reference(&mut self, expr: &'ast syn::ExprReference) {
+        if expr.mutability.is_none()
+            || !(self.record_static(&expr.expr, AccessKind::Write)
+                || self.write_through(&expr.expr))
+        {
+            visit::visit_expr_reference(self, expr);
+        }
+    }
+
+    fn visit_expr_method_call(&mut self, expr: &'ast syn::ExprMethodCall) {
+        let method = expr.method.to_string();
+        let write = WRITES.contains(&method.as_str());
+        let kind = if write {
+            AccessKind::Write
+        } else {
+            AccessKind::Read
+        };
+        if !(self.record_static(&expr.receiver, kind)
+            || (write && self.write_through(&expr.receiver)))
+        {
+            if self.unsafe_code && POINTER_ARITHMETIC.contains(&method.as_str()) {
+                self.pointer(&expr.receiver, AccessKind::PointerArithmetic, expr.span());
+            }
+            self.visit_expr(&expr.receiver);
+        }
+        for arg in &expr.args {
+            self.visit_expr(arg);
+        }
+    }
+
+    fn visit_expr_call(&mut self, expr: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(func) = &*expr.func
+            && let Some(last) = func.path.segments.last()
+            && last.ident == "transmute"
+            && let Some(pointer) = expr.args.first()
+        {
+            let to_integer = match &last.arguments {
+                syn::PathArguments::AngleBracketed(args) => {
+                    matches!(args.args.last(), Some(syn::GenericArgument::Type(ty)) if is_integer(ty))
+                }
+                _ => self.integer_binding,
+            };
+            if to_integer {
+                self.pointer(pointer, AccessKind::Transmute, expr.span());
+            }
+        }
+        visit::visit_expr_call(self, expr);
+    }
+
+    fn visit_local(&mut self, local: &'ast syn::Local) {
+        // `let guard = STATE.lock().unwrap();` reads until the guard is
+        // written through.
+        if let (syn::Pat::Ident(pat), Some(init)) = (&local.pat, &local.init)
+            && let Some(receiver) = self.guard(&init.expr)
+            && self.record_static(receiver, AccessKind::Read)
+        {
+            self.guards
+                .insert(pat.ident.to_string(), self.found.len() - 1);
+            return;
+        }
+        let integer = self.integer_binding;
+        self.integer_binding = matches!(&local.pat, syn::Pat::Type(pat) if is_integer(&pat.ty));
+        visit::visit_local(self, local);
+        self.integer_binding = integer;
+    }
+
+    fn visit_expr_unsafe(&mut self, expr: &'ast syn::ExprUnsafe) {
+        let unsafe_code = self.unsafe_code;
+        self.unsafe_code = true;
+        visit::visit_expr_unsafe(self, expr);
+        self.unsafe_code = unsafe_code;
+    }
+
+    fn visit_expr_closure(&mut self, expr: &'ast syn::ExprClosure) {
+        let integer = self.integer_binding;
+        self.integer_binding = false;
+        visit::visit_expr_closure(self, expr);
+        self.integer_binding = integer;
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        for arg in macro_args(mac) {
+            self.visit_expr(&arg);
+        }
+    }
+
+    fn visit_item(&mut self, _: &'ast syn::Item) {
+        // Nested items are functions of their own.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::SourceFile;
+
+    fn detect_source(source: &str) -> Vec<SharedItem> {
+        let file = SourceFile::parse("lib.rs", source).unwrap();
+        detect(&Crate::from_file("demo", file).unwrap())
+    }
+
+    #[test]
+    fn neighbouring_state_reached_through_pointers() {
+        let items = detect_crate(env!("CARGO_MANIFEST_DIR")).unwrap();
+        let problem = "crate::coupling::common_coupling::problem";
+
+        let one = items
+            .iter()
+            .find(|item| item.item == format!("{}::state_one_ptr", problem))
+            .unwrap();
+        assert_eq!(one.level(), CouplingLevel::Content);
+        let kinds: Vec<AccessKind> = one.accesses.iter().map(|access| access.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![AccessKind::PointerArithmetic, AccessKind::PointerArithmetic]
+        );
+        assert_eq!(
+            one.modules(),
+            BTreeSet::from(["crate::coupling::common_coupling"])
+        );
+
+        // Turning a pointer into an address through `transmute` counts too.
+        let items = detect_source(
+            "fn peek(states: &[i64; 2]) -> i64 {
+                 let first = &states[0] as *const i64;
+                 let addr: usize = unsafe { std::mem::transmute(first) };
+                 let _: u64 = unsafe { std::mem::transmute::<*const i64, u64>(first) };
+                 addr as i64
+             }",
+        );
+        let kinds: Vec<AccessKind> = items[0].accesses.iter().map(|access| access.kind).collect();
+        assert_eq!(items[0].item, "crate::peek::first");
+        assert_eq!(kinds, vec![AccessKind::Transmute, AccessKind::Transmute]);
+    }
+
+    #[test]
+    fn static_mut_lists_readers_and_writers() {
+        let items = detect_source(
+            "mod config { pub static mut VERBOSE: bool = false; }
+             mod cli { pub fn quiet() { unsafe { super::config::VERBOSE = false } } }
+             mod logger {
+                 use super::config::VERBOSE;
+                 pub fn log(line: &str) { if unsafe { VERBOSE } { println!(\"{}\", line) } }
+             }",
+        );
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].item, "crate::config::VERBOSE");
+        assert_eq!(items[0].kind, SharedKind::StaticMut);
+        let accesses: Vec<(&str, AccessKind)> = items[0]
+            .accesses
+            .iter()
+            .map(|access| (access.module.as_str(), access.kind))
+            .collect();
+        assert_eq!(
+            accesses,
+            vec![
+                ("crate::cli", AccessKind::Write),
+                ("crate::logger", AccessKind::Read)
+            ]
+        );
+    }
+
+    #[test]
+    fn global_locks_count_once_shared() {
+        let items = detect_source(
+            "use std::sync::Mutex;
+             static COUNTER: Mutex<u32> = Mutex::new(0);
+             static PRIVATE: Mutex<u32> = Mutex::new(0);
+             fn bump() { *COUNTER.lock().unwrap() += 1; *PRIVATE.lock().unwrap() += 1; }
+             mod report { pub fn show() -> u32 { *super::COUNTER.lock().unwrap() } }",
+        );
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].item, "crate::COUNTER");
+        assert_eq!(items[0].kind, SharedKind::GlobalCell);
+        assert_eq!(
+            items[0].modules(),
+            BTreeSet::from(["crate", "crate::report"])
+        );
+    }
+
+    #[test]
+    fn locks_write_only_through_their_guard() {
+        let items = detect_source(
+            "use std::sync::Mutex;
+             static STATE: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+             fn size() -> usize { STATE.lock().unwrap().len() }
+             fn add() { STATE.lock().unwrap().push(1); }
+             fn reset() { *STATE.lock().unwrap() = Vec::new(); }
+             fn first() -> u8 { let guard = STATE.lock().unwrap(); guard[0] }
+             fn clear() { let mut guard = STATE.lock().unwrap(); guard.clear(); }
+             fn zero() { let mut guard = STATE.lock().unwrap(); guard[0] = 0; }
+             mod report { pub fn show() -> usize { super::STATE.lock().unwrap().len() } }",
+        );
+        let kinds: Vec<(&str, AccessKind)> = items[0]
+            .accesses
+            .iter()
+            .map(|access| (access.function.as_str(), access.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("crate::size", AccessKind::Read),
+                ("crate::add", AccessKind::Write),
+                ("crate::reset", AccessKind::Write),
+                ("crate::first", AccessKind::Read),
+                ("crate::clear", AccessKind::Write),
+                ("crate::zero", AccessKind::Write),
+                ("crate::report::show", AccessKind::Read),
+            ]
+        );
+    }
+
+    #[test]
+    fn integer_arithmetic_outside_unsafe_is_fine() {
+        let items = detect_source("fn sum(a: u8, b: u8) -> u8 { a.add(b) }");
+        assert!(items.is_empty());
+    }
+}
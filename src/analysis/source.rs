@@ -0,0 +1,252 @@
+//! ./analysis/source.rs
+//!
+//! Reading and parsing source files, and pointing back into them.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::Span;
+use serde::{Deserialize, Serialize};
+use syn::punctuated::Punctuated;
+
+/// Why a source file could not be analyzed.
+#[derive(Debug)]
+pub enum Error {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        source: syn::Error,
+    },
+    /// A malformed TOML file, such as `Cargo.toml` or `cohesion-coupling.toml`.
+    Config {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    /// A `git` command that exited with an error.
+    Git {
+        command: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Parse { path, source } => {
+                let start = source.span().start();
+                write!(
+                    f,
+                    "{}:{}:{}: {}",
+                    path.display(),
+                    start.line,
+                    start.column + 1,
+                    source
+                )
+            }
+            Error::Config { path, source } => write!(f, "{}: {}", path.display(), source),
+            Error::Git { command, message } => write!(f, "`{}`: {}", command, message),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::Parse { source, .. } => Some(source),
+            Error::Config { source, .. } => Some(source),
+            Error::Git { .. } => None,
+        }
+    }
+}
+
+/// A region of a source file, 1-based like compiler diagnostics; the end
+/// column is exclusive.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Location {
+    pub file: PathBuf,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl Location {
+    pub fn new(file: &Path, span: Span) -> Self {
+        let (start, end) = (span.start(), span.end());
+        Location {
+            file: file.to_path_buf(),
+            line: start.line,
+            column: start.column + 1,
+            end_line: end.line,
+            end_column: end.column + 1,
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file.display(), self.line, self.column)
+    }
+}
+
+/// Where an item sits inside a file: the inline modules around it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Scope {
+    pub modules: Vec<String>,
+    /// Inside a `#[cfg(test)]` module.
+    pub test: bool,
+}
+
+impl Scope {
+    /// `a::b` style path of the enclosing modules, empty at file level.
+    pub fn path(&self) -> String {
+        self.modules.join("::")
+    }
+
+    /// Path of `name` as seen from the file root.
+    pub fn qualify(&self, name: &str) -> String {
+        if self.modules.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}::{}", self.path(), name)
+        }
+    }
+}
+
+/// A parsed Rust source file.
+pub struct SourceFile {
+    path: PathBuf,
+    text: String,
+    syntax: syn::File,
+}
+
+impl SourceFile {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(path, text)
+    }
+
+    pub fn parse(path: impl AsRef<Path>, text: impl Into<String>) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        let text = text.into();
+        match syn::parse_file(&text) {
+            Ok(syntax) => Ok(SourceFile { path, text, syntax }),
+            Err(source) => Err(Error::Parse { path, source }),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn syntax(&self) -> &syn::File {
+        &self.syntax
+    }
+
+    pub fn location(&self, span: Span) -> Location {
+        Location::new(&self.path, span)
+    }
+
+    /// Calls `visit` for every item, descending into inline modules.
+    pub fn for_each_item<'a>(&'a self, mut visit: impl FnMut(&Scope, &'a syn::Item)) {
+        walk(&self.syntax.items, &mut Scope::default(), &mut visit);
+    }
+}
+
+fn walk<'a>(
+    items: &'a [syn::Item],
+    scope: &mut Scope,
+    visit: &mut impl FnMut(&Scope, &'a syn::Item),
+) {
+    for item in items {
+        visit(scope, item);
+        if let syn::Item::Mod(module) = item
+            && let Some((_, content)) = &module.content
+        {
+            let test = scope.test;
+            scope.test |= is_cfg_test(&module.attrs);
+            scope.modules.push(module.ident.to_string());
+            walk(content, scope, visit);
+            scope.modules.pop();
+            scope.test = test;
+        }
+    }
+}
+
+/// Arguments of a function-like macro such as `println!` or `format!`,
+/// which syn otherwise leaves as raw tokens. Empty if they aren't expressions.
+pub(crate) fn macro_args(mac: &syn::Macro) -> Vec<syn::Expr> {
+    mac.parse_body_with(Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated)
+        .map(|args| args.into_iter().collect())
+        .unwrap_or_default()
+}
+
+/// Whether the attributes carry `#[cfg(test)]`.
+pub(crate) fn is_cfg_test(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path().is_ident("cfg")
+            && attr
+                .parse_args::<syn::Ident>()
+                .is_ok_and(|ident| ident == "test")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walks_inline_modules_with_their_scope() {
+        let file = SourceFile::parse(
+            "lib.rs",
+            "mod outer { mod inner { struct A; } } #[cfg(test)] mod test { fn t() {} }",
+        )
+        .unwrap();
+
+        let mut seen = Vec::new();
+        file.for_each_item(|scope, item| {
+            if let syn::Item::Struct(item) = item {
+                seen.push((scope.qualify(&item.ident.to_string()), scope.test));
+            }
+            if let syn::Item::Fn(item) = item {
+                seen.push((scope.qualify(&item.sig.ident.to_string()), scope.test));
+            }
+        });
+        assert_eq!(
+            seen,
+            vec![
+                ("outer::inner::A".to_string(), false),
+                ("test::t".to_string(), true)
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_format_macro_arguments() {
+        let mac: syn::Macro = syn::parse_str(r#"println!("{} {}", self.name, 1 + 2)"#).unwrap();
+        assert_eq!(macro_args(&mac).len(), 3);
+
+        let mac: syn::Macro = syn::parse_str("vec![0; 3]").unwrap();
+        assert!(macro_args(&mac).is_empty());
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_file() {
+        let error = SourceFile::parse("broken.rs", "fn (").err().unwrap();
+        assert!(error.to_string().starts_with("broken.rs:1:"));
+    }
+}
@@ -0,0 +1,188 @@
+//! ./analysis/stamp.rs
+//!
+//! Stamp coupling: functions taking a whole struct, by value or by
+//! reference, but reading only some of its fields.
+//!
+//! A parameter that is also used as a whole (passed on, matched, called
+//! on) is skipped, since its body alone doesn't show which fields are needed.
+
+use std::path::Path;
+
+use syn::spanned::Spanned;
+
+use super::dependencies::{field_accesses, struct_param, typed_params};
+use super::modules::{Crate, Function, Target};
+use super::{Error, Location};
+
+/// A struct parameter carrying fields its function never reads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StampFinding {
+    /// Full path of the function, e.g. `crate::notification::send_telemetry`.
+    pub function: String,
+    pub parameter: String,
+    /// Full path of the parameter's struct.
+    pub struct_path: String,
+    pub used: Vec<String>,
+    pub unused: Vec<String>,
+    pub location: Location,
+    /// The signature with the struct replaced by the fields it needs,
+    /// e.g. `send_telemetry(id: u64)`.
+    pub suggestion: String,
+}
+
+impl StampFinding {
+    /// Share of the struct's fields that are carried along for nothing.
+    pub fn unused_ratio(&self) -> f64 {
+        self.unused.len() as f64 / (self.used.len() + self.unused.len()) as f64
+    }
+}
+
+pub fn detect_crate(dir: impl AsRef<Path>) -> Result<Vec<StampFinding>, Error> {
+    Ok(detect(&Crate::load(dir)?))
+}
+
+/// Stamp-coupled parameters of every function outside test modules.
+pub fn detect(krate: &Crate) -> Vec<StampFinding> {
+    let mut findings = Vec::new();
+    for function in krate.functions() {
+        if krate.modules[function.module].test {
+            continue;
+        }
+        for (parameter, ty) in typed_params(function.sig) {
+            let Some(target) = struct_param(krate, function.module, ty) else {
+                continue;
+            };
+            let Some(accessed) = field_accesses(function.block, &parameter) else {
+                continue;
+            };
+            let fields = declared_fields(krate, &target);
+            let (used, unused): (Vec<_>, Vec<_>) =
+                fields.iter().partition(|(name, _)| accessed.contains(name));
+            if unused.is_empty() {
+                continue;
+            }
+            let by_ref = matches!(ty, syn::Type::Reference(_));
+            findings.push(StampFinding {
+                function: krate.function_path(&function),
+                struct_path: krate.target_path(&target),
+                used: used.iter().map(|(name, _)| name.clone()).collect(),
+                unused: unused.iter().map(|(name, _)| name.clone()).collect(),
+                location: krate.location(function.module, ty.span()),
+                suggestion: suggest(krate, &function, &parameter, &used, by_ref),
+                parameter,
+            });
+        }
+    }
+    findings
+}
+
+/// `(name, type)` of a struct's named fields, types as written.
+pub(crate) fn declared_fields(krate: &Crate, target: &Target) -> Vec<(String, String)> {
+    let (Target::Item { module, .. }, Some(syn::Item::Struct(item))) = (target, krate.item(target))
+    else {
+        return Vec::new();
+    };
+    item.fields
+        .iter()
+        .filter_map(|field| {
+            let name = field.ident.as_ref()?.to_string();
+            Some((name, krate.snippet(*module, field.ty.span())))
+        })
+        .collect()
+}
+
+fn suggest(
+    krate: &Crate,
+    function: &Function,
+    parameter: &str,
+    used: &[&(String, String)],
+    by_ref: bool,
+) -> String {
+    let inputs: Vec<String> = function
+        .sig
+        .inputs
+        .iter()
+        .flat_map(|input| match input {
+            syn::FnArg::Typed(typed)
+                if matches!(&*typed.pat, syn::Pat::Ident(pat) if pat.ident == parameter) =>
+            {
+                used.iter()
+                    .map(|(name, ty)| format!("{}: {}", name, narrowed(ty, by_ref)))
+                    .collect()
+            }
+            input => vec![krate.snippet(function.module, input.span())],
+        })
+        .collect();
+    format!("{}({})", function.sig.ident, inputs.join(", "))
+}
+
+/// How a field is best passed on its own: copies stay values, and what
+/// was borrowed through the struct stays borrowed.
+pub(crate) fn narrowed(ty: &str, by_ref: bool) -> String {
+    const COPY: [&str; 17] = [
+        "bool", "char", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64",
+        "i128", "isize", "f32", "f64", "()",
+    ];
+    match ty {
+        _ if !by_ref || COPY.contains(&ty) => ty.to_string(),
+        "String" => "&str".to_string(),
+        _ => format!("&{}", ty),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::SourceFile;
+
+    fn detect_source(source: &str) -> Vec<StampFinding> {
+        let file = SourceFile::parse("lib.rs", source).unwrap();
+        detect(&Crate::from_file("demo", file).unwrap())
+    }
+
+    #[test]
+    fn send_telemetry_only_needs_the_id() {
+        let findings = detect_crate(env!("CARGO_MANIFEST_DIR")).unwrap();
+        let finding = findings
+            .iter()
+            .find(|finding| finding.function.ends_with("notification::send_telemetry"))
+            .unwrap();
+
+        assert_eq!(finding.parameter, "record");
+        assert!(
+            finding
+                .struct_path
+                .ends_with("stamp_coupling::telemetry::Record")
+        );
+        assert_eq!(finding.used, vec!["id"]);
+        assert_eq!(finding.unused, vec!["name", "email", "address"]);
+        assert_eq!(finding.unused_ratio(), 0.75);
+        assert_eq!(finding.suggestion, "send_telemetry(id: u64)");
+        assert_eq!(finding.location.line, 23);
+    }
+
+    #[test]
+    fn borrowed_fields_stay_borrowed() {
+        let findings = detect_source(
+            "struct User { name: String, tags: Vec<String>, age: u8, email: String }
+             fn greet(prefix: &str, user: &User) -> String {
+                 format!(\"{} {} {:?} {}\", prefix, user.name, user.tags, user.age)
+             }",
+        );
+        assert_eq!(findings.len(), 1);
+        assert_eq!(
+            findings[0].suggestion,
+            "greet(prefix: &str, name: &str, tags: &Vec<String>, age: u8)"
+        );
+    }
+
+    #[test]
+    fn whole_struct_use_and_full_use_are_not_stamp_coupling() {
+        let findings = detect_source(
+            "struct Point { x: i32, y: i32 }
+             fn length(p: Point) -> i32 { p.x + p.y }
+             fn forward(p: &Point) -> i32 { length_of(p) }",
+        );
+        assert!(findings.is_empty());
+    }
+}
@@ -0,0 +1,185 @@
+//! ./analysis/summary.rs
+//!
+//! One table per crate with everything the detectors found, module by
+//! module: the package metrics, the worst LCOM4 of its structs, and its
+//! findings, with the most severe one named.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use super::external::Adapters;
+use super::lcom;
+use super::metrics::{self, ModuleMetrics};
+use super::modules::Crate;
+use super::report::{Finding, Level, Report};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModuleSummary {
+    pub metrics: ModuleMetrics,
+    /// Highest LCOM4 among the module's structs, `None` without structs.
+    pub lcom4: Option<usize>,
+    pub findings: usize,
+    /// The most severe finding's level, the higher rank breaking ties.
+    pub worst: Option<Level>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CrateSummary {
+    pub name: String,
+    pub functions: usize,
+    /// Modules outside test modules, in declaration order.
+    pub modules: Vec<ModuleSummary>,
+    /// Findings per rule ID.
+    pub rules: BTreeMap<String, usize>,
+}
+
+/// Runs every detector over the crate.
+pub fn summarize(krate: &Crate, adapters: &Adapters) -> CrateSummary {
+    let report = Report::build(krate, adapters);
+    let structs = lcom::analyze_crate(krate);
+    let mut rules: BTreeMap<String, usize> = BTreeMap::new();
+    for finding in &report.findings {
+        *rules.entry(finding.rule.clone()).or_default() += 1;
+    }
+
+    let modules = metrics::measure(krate)
+        .into_iter()
+        .map(|metrics| {
+            let lcom4 = structs
+                .iter()
+                .filter(|(module, _)| *module == metrics.module)
+                .map(|(_, cohesion)| cohesion.lcom4)
+                .max();
+            let findings: Vec<&Finding> = report
+                .findings
+                .iter()
+                .filter(|finding| {
+                    krate
+                        .module_containing(&finding.item)
+                        .is_some_and(|module| module.path == metrics.module)
+                })
+                .collect();
+            let worst = findings
+                .iter()
+                .max_by_key(|finding| (finding.severity, finding.level.rank()))
+                .map(|finding| finding.level);
+            ModuleSummary {
+                metrics,
+                lcom4,
+                findings: findings.len(),
+                worst,
+            }
+        })
+        .collect();
+
+    CrateSummary {
+        name: krate.name.clone(),
+        functions: krate
+            .functions()
+            .filter(|function| !krate.modules[function.module].test)
+            .count(),
+        modules,
+        rules,
+    }
+}
+
+impl CrateSummary {
+    /// A heading, an aligned table with one module per row, and the
+    /// findings per rule.
+    pub fn table(&self) -> String {
+        let width = self
+            .modules
+            .iter()
+            .map(|row| row.metrics.module.len())
+            .max()
+            .unwrap_or(0)
+            .max("module".len());
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{} ({} modules, {} functions)",
+            self.name,
+            self.modules.len(),
+            self.functions
+        );
+        let _ = writeln!(
+            out,
+            "{:<width$}  {:>3}  {:>3}  {:>4}  {:>5}  {:>8}  worst",
+            "module", "Ca", "Ce", "I", "LCOM4", "findings"
+        );
+        for row in &self.modules {
+            let lcom4 = row.lcom4.map_or("-".to_string(), |lcom4| lcom4.to_string());
+            let worst = row.worst.map_or("-".to_string(), Level::rule_id);
+            let _ = writeln!(
+                out,
+                "{:<width$}  {:>3}  {:>3}  {:>4.2}  {:>5}  {:>8}  {}",
+                row.metrics.module,
+                row.metrics.afferent,
+                row.metrics.efferent,
+                row.metrics.instability,
+                lcom4,
+                row.findings,
+                worst
+            );
+        }
+        let rules: Vec<String> = self
+            .rules
+            .iter()
+            .map(|(rule, count)| format!("{} {}", rule, count))
+            .collect();
+        let _ = writeln!(
+            out,
+            "findings: {}",
+            if rules.is_empty() {
+                "none".to_string()
+            } else {
+                rules.join(", ")
+            }
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{CouplingLevel, SourceFile};
+
+    #[test]
+    fn table_has_a_row_per_module() {
+        let file = SourceFile::parse(
+            "lib.rs",
+            "mod telemetry {
+                 pub struct Record { pub id: u64, pub name: String }
+                 impl Record {
+                     fn id(&self) -> u64 { self.id }
+                     fn name(&self) -> &str { &self.name }
+                 }
+             }
+             mod notification {
+                 use super::telemetry::Record;
+                 pub fn send(record: &Record) -> u64 { record.id }
+             }",
+        )
+        .unwrap();
+        let krate = Crate::from_file("demo", file).unwrap();
+        let summary = summarize(&krate, &Adapters::default());
+        assert_eq!(summary.functions, 3);
+        assert_eq!(summary.modules[1].lcom4, Some(2));
+        assert_eq!(
+            summary.modules[2].worst,
+            Some(Level::Coupling(CouplingLevel::Stamp))
+        );
+
+        assert_eq!(
+            summary.table(),
+            "demo (3 modules, 3 functions)
+module                Ca   Ce     I  LCOM4  findings  worst
+crate                  0    0  0.00      -         0  -
+crate::telemetry       1    0  0.00      2         0  -
+crate::notification    0    1  1.00      -         1  coupling/stamp
+findings: coupling/stamp 1
+"
+        );
+    }
+}
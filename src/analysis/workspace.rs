@@ -0,0 +1,287 @@
+//! ./analysis/workspace.rs
+//!
+//! The crates of a Cargo workspace, read from its manifests alone so it
+//! works offline and without running cargo.
+//!
+//! Members come from `workspace.members`, where a trailing `*` matches every
+//! directory holding a `Cargo.toml`, minus `workspace.exclude`. A package
+//! manifest at the workspace root counts as a member too. Each member is
+//! analyzed from its library root, `[lib] path` or `src/lib.rs`, or else its
+//! first binary.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use toml::Table;
+
+use super::Error;
+use super::modules::Crate;
+
+pub const MANIFEST: &str = "Cargo.toml";
+
+/// A package of the workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    /// Package name from its manifest.
+    pub name: String,
+    pub dir: PathBuf,
+    /// The crate root file, e.g. `src/lib.rs`.
+    pub root: PathBuf,
+}
+
+impl Member {
+    pub fn load(&self) -> Result<Crate, Error> {
+        Crate::from_root(&self.name, &self.root)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Workspace {
+    pub dir: PathBuf,
+    /// Sorted by directory.
+    pub members: Vec<Member>,
+}
+
+impl Workspace {
+    /// The workspace `start` is in: the nearest `Cargo.toml` above it
+    /// with a `[workspace]` table, or else the nearest one at all. A
+    /// relative `start` is resolved first, so `.` climbs past the current
+    /// directory.
+    pub fn find(start: impl AsRef<Path>) -> Result<Self, Error> {
+        let start = start.as_ref();
+        let start = fs::canonicalize(start)
+            .or_else(|_| std::path::absolute(start))
+            .unwrap_or_else(|_| start.to_path_buf());
+        let mut nearest = None;
+        for dir in start.ancestors() {
+            let manifest = dir.join(MANIFEST);
+            if !manifest.is_file() {
+                continue;
+            }
+            if read_manifest(&manifest)?.contains_key("workspace") {
+                return Self::read(&manifest);
+            }
+            nearest.get_or_insert(manifest);
+        }
+        match nearest {
+            Some(manifest) => Self::read(&manifest),
+            None => Err(Error::Io {
+                path: start.join(MANIFEST),
+                source: std::io::ErrorKind::NotFound.into(),
+            }),
+        }
+    }
+
+    /// The workspace a `Cargo.toml` declares, or its single package.
+    pub fn read(manifest: impl AsRef<Path>) -> Result<Self, Error> {
+        let manifest = manifest.as_ref();
+        let dir = manifest.parent().unwrap_or(Path::new("")).to_path_buf();
+        let table = read_manifest(manifest)?;
+
+        let mut dirs = Vec::new();
+        if table.contains_key("package") {
+            dirs.push(dir.clone());
+        }
+        if let Some(workspace) = table.get("workspace").and_then(|value| value.as_table()) {
+            let excluded: Vec<PathBuf> = strings(workspace, "exclude")
+                .map(|path| dir.join(path))
+                .collect();
+            for pattern in strings(workspace, "members") {
+                for member in expand(&dir, pattern) {
+                    if !excluded.contains(&member) && !dirs.contains(&member) {
+                        dirs.push(member);
+                    }
+                }
+            }
+        }
+        dirs.sort();
+
+        let mut members = Vec::new();
+        for member in dirs {
+            if let Some(member) = package(&member)? {
+                members.push(member);
+            }
+        }
+        Ok(Workspace { dir, members })
+    }
+}
+
+/// The member a package directory holds, `None` without a crate root.
+fn package(dir: &Path) -> Result<Option<Member>, Error> {
+    let table = read_manifest(&dir.join(MANIFEST))?;
+    let Some(package) = table.get("package").and_then(|value| value.as_table()) else {
+        return Ok(None);
+    };
+    let name = package
+        .get("name")
+        .and_then(|value| value.as_str())
+        .map_or_else(|| dir_name(dir), str::to_string);
+
+    let declared = |section: &str| match table.get(section) {
+        Some(toml::Value::Table(target)) => target.get("path").and_then(|path| path.as_str()),
+        Some(toml::Value::Array(targets)) => targets
+            .iter()
+            .find_map(|target| target.get("path").and_then(|path| path.as_str())),
+        _ => None,
+    };
+    let root = [
+        declared("lib").map(|path| dir.join(path)),
+        Some(dir.join("src/lib.rs")),
+        declared("bin").map(|path| dir.join(path)),
+        Some(dir.join("src/main.rs")),
+    ]
+    .into_iter()
+    .flatten()
+    .find(|root| root.is_file());
+    Ok(root.map(|root| Member {
+        name,
+        dir: dir.to_path_buf(),
+        root,
+    }))
+}
+
+/// Member directories a `members` entry names; `*` only as the last segment.
+fn expand(dir: &Path, pattern: &str) -> Vec<PathBuf> {
+    let Some(parent) = pattern.strip_suffix("*") else {
+        return vec![dir.join(pattern)];
+    };
+    let Ok(entries) = fs::read_dir(dir.join(parent)) else {
+        return Vec::new();
+    };
+    let mut found: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.join(MANIFEST).is_file())
+        .collect();
+    found.sort();
+    found
+}
+
+fn strings<'a>(table: &'a Table, key: &str) -> impl Iterator<Item = &'a str> {
+    table
+        .get(key)
+        .and_then(|value| value.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|value| value.as_str())
+}
+
+fn read_manifest(path: &Path) -> Result<Table, Error> {
+    let text = fs::read_to_string(path).map_err(|source| Error::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    text.parse().map_err(|source| Error::Config {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn dir_name(dir: &Path) -> String {
+    dir.file_name().map_or_else(
+        || "crate".to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn this_crate_is_a_single_package() {
+        let nested = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/analysis");
+        let workspace = Workspace::find(nested).unwrap();
+        assert_eq!(workspace.members.len(), 1);
+        let member = &workspace.members[0];
+        assert_eq!(member.name, "coupling-cohesin-presenation");
+        assert!(member.root.ends_with("src/lib.rs"));
+        assert!(
+            member
+                .load()
+                .unwrap()
+                .module("crate::analysis::workspace")
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn members_are_expanded_and_excluded() {
+        let dir = std::env::temp_dir().join(format!("workspace-{}", std::process::id()));
+        let write = |path: &str, text: &str| {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\", \"tool\"]\nexclude = [\"crates/old\"]",
+        );
+        write("crates/core/Cargo.toml", "[package]\nname = \"core-lib\"");
+        write("crates/core/src/lib.rs", "pub mod model;");
+        write("crates/core/src/model.rs", "pub struct Model;");
+        write("crates/old/Cargo.toml", "[package]\nname = \"old\"");
+        write("crates/old/src/lib.rs", "");
+        write(
+            "tool/Cargo.toml",
+            "[package]\nname = \"tool\"\n[[bin]]\nname = \"tool\"\npath = \"cli.rs\"",
+        );
+        write("tool/cli.rs", "fn main() {}");
+
+        let workspace = Workspace::find(dir.join("crates/core/src")).unwrap();
+        let names: Vec<&str> = workspace
+            .members
+            .iter()
+            .map(|member| member.name.as_str())
+            .collect();
+        assert_eq!(names, ["core-lib", "tool"]);
+        assert!(workspace.members[1].root.ends_with("tool/cli.rs"));
+        let core = workspace.members[0].load().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(core.module("crate::model").unwrap().item("Model").is_some());
+    }
+
+    /// Set in the child process `found_from_a_member_by_relative_path`
+    /// starts inside a member, where it expects the workspace's members.
+    const MEMBERS: &str = "WORKSPACE_TEST_MEMBERS";
+
+    #[test]
+    fn found_from_a_member_by_relative_path() {
+        // `.` means the working directory, which a test can only change
+        // for the whole process; so the lookup runs in a child.
+        if let Ok(members) = std::env::var(MEMBERS) {
+            let workspace = Workspace::find(".").unwrap();
+            let names: Vec<&str> = workspace
+                .members
+                .iter()
+                .map(|member| member.name.as_str())
+                .collect();
+            assert_eq!(names.join(","), members);
+            return;
+        }
+
+        let dir = std::env::temp_dir().join(format!("workspace-relative-{}", std::process::id()));
+        let write = |path: &str, text: &str| {
+            let path = dir.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        };
+        write("Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]");
+        write("crates/a/Cargo.toml", "[package]\nname = \"a\"");
+        write("crates/a/src/lib.rs", "");
+        write("crates/b/Cargo.toml", "[package]\nname = \"b\"");
+        write("crates/b/src/lib.rs", "");
+
+        let status = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "analysis::workspace::tests::found_from_a_member_by_relative_path",
+            ])
+            .current_dir(dir.join("crates/a"))
+            .env(MEMBERS, "a,b")
+            .status()
+            .unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(status.success());
+    }
+}
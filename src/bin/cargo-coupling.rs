@@ -0,0 +1,73 @@
+//! ./bin/cargo-coupling.rs
+//!
+//! Runs every detector over each crate of a local workspace and prints a
+//! summary table per crate. Installed on the `PATH`, it is `cargo coupling`.
+//!
+//! cargo coupling [--manifest-path Cargo.toml] [DIR]
+
+use std::env;
+use std::process::ExitCode;
+
+use coupling_cohesin_presenation::analysis::Error;
+use coupling_cohesin_presenation::analysis::external::Adapters;
+use coupling_cohesin_presenation::analysis::gate::Config;
+use coupling_cohesin_presenation::analysis::summary;
+use coupling_cohesin_presenation::analysis::workspace::{Member, Workspace};
+
+const USAGE: &str = "usage: cargo coupling [--manifest-path Cargo.toml] [DIR]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+    // Cargo passes the subcommand name on to the binary.
+    if args.first() == Some(&"coupling") {
+        args.remove(0);
+    }
+
+    let workspace = match args.as_slice() {
+        [] => Workspace::find("."),
+        ["--manifest-path", manifest] => Workspace::read(manifest),
+        [dir] if !dir.starts_with('-') => Workspace::find(dir),
+        _ => return usage_error("unexpected arguments"),
+    };
+    let workspace = match workspace {
+        Ok(workspace) => workspace,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return ExitCode::from(2);
+        }
+    };
+
+    let mut failed = false;
+    for (index, member) in workspace.members.iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
+        match analyze(member) {
+            Ok(table) => print!("{}", table),
+            Err(error) => {
+                eprintln!("error: {}: {}", member.name, error);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("error: {}\n{}", message, USAGE);
+    ExitCode::from(2)
+}
+
+/// The member's summary table, with the adapter modules its
+/// `cohesion-coupling.toml` allows.
+fn analyze(member: &Member) -> Result<String, Error> {
+    let config = Config::find(&member.dir)?;
+    let krate = member.load()?;
+    let summary = summary::summarize(&krate, &Adapters::new(&config.adapters));
+    Ok(summary.table())
+}
@@ -0,0 +1,41 @@
+//! ./bin/coupling-dashboard.rs
+//!
+//! Writes the dashboard of a crate to stdout as one HTML file, using the
+//! adapters its `cohesion-coupling.toml` allows.
+//!
+//! coupling-dashboard [--dir DIR] > dashboard.html
+
+use std::env;
+use std::process::ExitCode;
+
+use coupling_cohesin_presenation::analysis::dashboard;
+use coupling_cohesin_presenation::analysis::external::Adapters;
+use coupling_cohesin_presenation::analysis::gate::Config;
+
+const USAGE: &str = "usage: coupling-dashboard [--dir DIR] > dashboard.html";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let dir = match args.as_slice() {
+        ["--dir", dir] => *dir,
+        [] => ".",
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let html = Config::find(dir)
+        .and_then(|config| dashboard::render_crate(dir, &Adapters::new(&config.adapters)));
+    match html {
+        Ok(html) => {
+            print!("{}", html);
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
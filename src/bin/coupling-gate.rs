@@ -0,0 +1,101 @@
+//! ./bin/coupling-gate.rs
+//!
+//! Fails the build when a crate breaks the limits in its
+//! `cohesion-coupling.toml`.
+//!
+//! coupling-gate [--config FILE] [DIR]
+
+use std::env;
+use std::process::ExitCode;
+
+use coupling_cohesin_presenation::analysis::gate::{self, Config, Outcome};
+
+const USAGE: &str = "usage: coupling-gate [--config FILE] [DIR]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    let (config, dir) = match args.as_slice() {
+        [] => (None, "."),
+        [dir] if !dir.starts_with('-') => (None, *dir),
+        ["--config", file] => (Some(*file), "."),
+        ["--config", file, dir] => (Some(*file), *dir),
+        _ => return usage_error("unexpected arguments"),
+    };
+    let config = match config {
+        Some(file) => Config::read(file),
+        None => Config::find(dir),
+    };
+    let outcome = match config.and_then(|config| gate::check_crate(dir, &config)) {
+        Ok(outcome) => outcome,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return ExitCode::from(2);
+        }
+    };
+
+    print!("{}", summary(&outcome));
+    if outcome.passed() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("error: {}\n{}", message, USAGE);
+    ExitCode::from(2)
+}
+
+/// One line per violation, then the verdict.
+fn summary(outcome: &Outcome) -> String {
+    let mut out = String::new();
+    for violation in &outcome.violations {
+        out.push_str(&format!(
+            "{}: {}: {}\n",
+            violation.location, violation.rule, violation.message
+        ));
+    }
+    out.push_str(&format!(
+        "{}: {} violation(s), {} suppressed\n",
+        if outcome.passed() { "passed" } else { "failed" },
+        outcome.violations.len(),
+        outcome.suppressed.len()
+    ));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coupling_cohesin_presenation::analysis::Location;
+    use coupling_cohesin_presenation::analysis::gate::Violation;
+
+    #[test]
+    fn lists_violations_before_the_verdict() {
+        let violation = Violation {
+            rule: "coupling/control".to_string(),
+            module: "crate::formatter".to_string(),
+            item: "crate::formatter::format_data".to_string(),
+            message: "`format` selects the branch".to_string(),
+            location: Location {
+                file: "src/lib.rs".into(),
+                line: 17,
+                column: 9,
+                end_line: 30,
+                end_column: 10,
+            },
+        };
+        let outcome = Outcome {
+            violations: vec![violation.clone()],
+            suppressed: vec![violation],
+        };
+        assert_eq!(
+            summary(&outcome),
+            "src/lib.rs:17:9: coupling/control: `format` selects the branch\n\
+             failed: 1 violation(s), 1 suppressed\n"
+        );
+        assert!(summary(&Outcome::default()).starts_with("passed"));
+    }
+}
@@ -0,0 +1,125 @@
+//! ./bin/coupling-history.rs
+//!
+//! Writes how a crate's coupling and cohesion moved over its git history.
+//!
+//! coupling-history [--samples N] [--rev REV] [--csv FILE] [--json FILE] [DIR]
+//!
+//! Without `--csv` or `--json` the CSV goes to stdout.
+
+use std::env;
+use std::fs;
+use std::process::ExitCode;
+
+use coupling_cohesin_presenation::analysis::Error;
+use coupling_cohesin_presenation::analysis::history::{self, Cache, Options};
+
+const USAGE: &str =
+    "usage: coupling-history [--samples N] [--rev REV] [--csv FILE] [--json FILE] [DIR]";
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Args {
+    options: Options,
+    csv: Option<String>,
+    json: Option<String>,
+    dir: Option<String>,
+}
+
+impl Args {
+    fn parse(args: &[&str]) -> Result<Self, String> {
+        let mut parsed = Args::default();
+        let mut rest = args;
+        while let Some((first, tail)) = rest.split_first() {
+            rest = match (*first, tail) {
+                ("--samples", [value, tail @ ..]) => {
+                    parsed.options.samples = value
+                        .parse()
+                        .map_err(|_| format!("`{}` is not a number of samples", value))?;
+                    tail
+                }
+                ("--rev", [value, tail @ ..]) => {
+                    parsed.options.rev = value.to_string();
+                    tail
+                }
+                ("--csv", [value, tail @ ..]) => {
+                    parsed.csv = Some(value.to_string());
+                    tail
+                }
+                ("--json", [value, tail @ ..]) => {
+                    parsed.json = Some(value.to_string());
+                    tail
+                }
+                (dir, tail) if !dir.starts_with('-') && parsed.dir.is_none() => {
+                    parsed.dir = Some(dir.to_string());
+                    tail
+                }
+                (arg, _) => return Err(format!("unexpected argument `{}`", arg)),
+            };
+        }
+        Ok(parsed)
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let args = match Args::parse(&args) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("error: {}\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &Args) -> Result<(), Error> {
+    let dir = args.dir.as_deref().unwrap_or(".");
+    let cache_path = Cache::path(dir)?;
+    let mut cache = Cache::load(&cache_path);
+    let trend = history::walk(dir, &args.options, &mut cache)?;
+    cache.save(&cache_path)?;
+
+    for skipped in &trend.skipped {
+        eprintln!("skipped {}: {}", &skipped.commit[..12], skipped.reason);
+    }
+    let write = |path: &str, text: String| {
+        fs::write(path, text).map_err(|source| Error::Io {
+            path: path.into(),
+            source,
+        })
+    };
+    if let Some(path) = &args.csv {
+        write(path, trend.to_csv())?;
+    }
+    if let Some(path) = &args.json {
+        write(path, trend.to_json())?;
+    }
+    if args.csv.is_none() && args.json.is_none() {
+        print!("{}", trend.to_csv());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_flags_in_any_order() {
+        let args = Args::parse(&["--json", "t.json", "crates/app", "--samples", "20"]).unwrap();
+        assert_eq!(args.options.samples, 20);
+        assert_eq!(args.options.rev, "HEAD");
+        assert_eq!(args.json.as_deref(), Some("t.json"));
+        assert_eq!(args.dir.as_deref(), Some("crates/app"));
+
+        assert!(Args::parse(&["--samples", "many"]).is_err());
+        assert!(Args::parse(&["a", "b"]).is_err());
+        assert!(Args::parse(&["--csv"]).is_err());
+    }
+}
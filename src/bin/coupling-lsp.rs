@@ -0,0 +1,23 @@
+//! ./bin/coupling-lsp.rs
+//!
+//! The language server over stdio, for editors to start and talk to. It
+//! takes no arguments; the crate is the workspace the editor opens.
+//!
+//! coupling-lsp
+
+use std::io::{self, BufReader};
+use std::process::ExitCode;
+
+use coupling_cohesin_presenation::lsp::Server;
+
+fn main() -> ExitCode {
+    let mut server = Server::new();
+    match server.run(BufReader::new(io::stdin().lock()), io::stdout().lock()) {
+        Ok(()) if server.shut_down() => ExitCode::SUCCESS,
+        Ok(()) => ExitCode::FAILURE,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::from(2)
+        }
+    }
+}
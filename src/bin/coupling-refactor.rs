@@ -0,0 +1,95 @@
+//! ./bin/coupling-refactor.rs
+//!
+//! Prints a refactoring as a unified diff, to review and then apply with
+//! `git apply`; refuses with the reason when it can't be done safely.
+//!
+//! coupling-refactor [--dir DIR] narrow FUNCTION [PARAMETER]
+//! coupling-refactor [--dir DIR] polymorphism FUNCTION
+//! coupling-refactor [--dir DIR] extract FUNCTION
+//! coupling-refactor narrow notification::send_telemetry | git apply
+//! coupling-refactor polymorphism formatter::format_data | git apply
+//! coupling-refactor extract temporal_cohesion::system::startup | git apply
+
+use std::env;
+use std::process::ExitCode;
+
+use coupling_cohesin_presenation::analysis::modules::Crate;
+use coupling_cohesin_presenation::refactor::{extract, narrow, polymorphism};
+
+const USAGE: &str = "usage: coupling-refactor [--dir DIR] narrow FUNCTION [PARAMETER]
+       coupling-refactor [--dir DIR] polymorphism FUNCTION
+       coupling-refactor [--dir DIR] extract FUNCTION";
+
+enum Command<'a> {
+    Narrow {
+        function: &'a str,
+        parameter: Option<&'a str>,
+    },
+    Polymorphism {
+        function: &'a str,
+    },
+    Extract {
+        function: &'a str,
+    },
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (dir, command) = match args.as_slice() {
+        ["--dir", dir, command @ ..] => (*dir, command),
+        command => (".", command),
+    };
+    let command = match command {
+        ["narrow", function] => Command::Narrow {
+            function,
+            parameter: None,
+        },
+        ["narrow", function, parameter] => Command::Narrow {
+            function,
+            parameter: Some(parameter),
+        },
+        ["polymorphism", function] => Command::Polymorphism { function },
+        ["extract", function] => Command::Extract { function },
+        _ => {
+            eprintln!("error: unexpected arguments\n{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let krate = match Crate::load(dir) {
+        Ok(krate) => krate,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return ExitCode::from(2);
+        }
+    };
+    let patch = match command {
+        Command::Narrow {
+            function,
+            parameter,
+        } => narrow::narrow(&krate, function, parameter).map_err(|refusal| refusal.to_string()),
+        Command::Polymorphism { function } => polymorphism::replace_conditional(&krate, function)
+            .map_err(|refusal| refusal.to_string()),
+        Command::Extract { function } => match extract::extract(&krate, function) {
+            Ok(extraction) => {
+                eprintln!(
+                    "checked: no local left dangling in {}",
+                    extraction.checked.join(", ")
+                );
+                Ok(extraction.patch)
+            }
+            Err(refusal) => Err(refusal.to_string()),
+        },
+    };
+    match patch {
+        Ok(patch) => {
+            print!("{}", patch.diff(dir));
+            ExitCode::SUCCESS
+        }
+        Err(refusal) => {
+            eprintln!("refused: {}", refusal);
+            ExitCode::FAILURE
+        }
+    }
+}
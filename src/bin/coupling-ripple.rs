@@ -0,0 +1,66 @@
+//! ./bin/coupling-ripple.rs
+//!
+//! Lists what would have to change if an item were renamed or removed, and
+//! compares the blast radius of several, e.g. a problem and its solution.
+//!
+//! coupling-ripple [--dir DIR] TARGET...
+//! coupling-ripple formatter::Format::Html html_formatter::HtmlFormatter
+
+use std::env;
+use std::path::Path;
+use std::process::ExitCode;
+
+use coupling_cohesin_presenation::analysis::modules::Crate;
+use coupling_cohesin_presenation::analysis::ripple::{self, Ripple};
+
+const USAGE: &str = "usage: coupling-ripple [--dir DIR] TARGET...";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (dir, targets) = match args.as_slice() {
+        ["--dir", dir, targets @ ..] => (*dir, targets),
+        targets => (".", targets),
+    };
+    if targets.is_empty() || targets.iter().any(|target| target.starts_with('-')) {
+        eprintln!("error: missing target\n{}", USAGE);
+        return ExitCode::from(2);
+    }
+
+    let krate = match Crate::load(dir) {
+        Ok(krate) => krate,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut ripples: Vec<Ripple> = Vec::new();
+    for target in targets {
+        match ripple::ripple(&krate, target) {
+            Some(mut ripple) => {
+                for site in &mut ripple.sites {
+                    if let Ok(relative) = site.location.file.strip_prefix(Path::new(dir)) {
+                        site.location.file = relative.to_path_buf();
+                    }
+                }
+                ripples.push(ripple);
+            }
+            None => {
+                eprintln!(
+                    "error: `{}` names no field, method, variant or item",
+                    target
+                );
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    for ripple in &ripples {
+        print!("{}", ripple.listing());
+    }
+    if ripples.len() > 1 {
+        println!();
+        print!("{}", ripple::table(&ripples));
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,64 @@
+//! ./bin/coupling-slides.rs
+//!
+//! Writes the talk's slide deck to stdout, as Markdown for Marp or as one
+//! HTML file in the layout of a reveal.js deck, with the speaker notes
+//! found next to each example under DIR; or the run sheet timing the talk.
+//! Examples without notes are reported as warnings.
+//!
+//! coupling-slides [--dir DIR] marp > deck.md
+//! coupling-slides [--dir DIR] reveal > deck.html
+//! coupling-slides [--dir DIR] run-sheet
+
+use std::env;
+use std::process::ExitCode;
+
+use coupling_cohesin_presenation::slides::Deck;
+use coupling_cohesin_presenation::slides::notes::Plan;
+
+const USAGE: &str = "usage: coupling-slides [--dir DIR] marp|reveal|run-sheet";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (dir, output) = match args.as_slice() {
+        ["--dir", dir, output] => (*dir, *output),
+        [output] => (".", *output),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    if !matches!(output, "marp" | "reveal" | "run-sheet") {
+        eprintln!("{}", USAGE);
+        return ExitCode::from(2);
+    }
+
+    let plan = match Plan::load(dir) {
+        Ok(plan) => plan,
+        Err(error) => {
+            eprintln!("error: {}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+    for warning in &plan.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    if output == "run-sheet" {
+        print!("{}", plan.run_sheet());
+        return ExitCode::SUCCESS;
+    }
+    match Deck::build(&plan) {
+        Ok(deck) if output == "marp" => {
+            print!("{}", deck.to_marp());
+            ExitCode::SUCCESS
+        }
+        Ok(deck) => {
+            print!("{}", deck.to_reveal());
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
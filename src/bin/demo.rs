@@ -0,0 +1,146 @@
+//! ./bin/demo.rs
+//!
+//! Runs the examples from a terminal during the talk.
+//!
+//! demo list
+//! demo run temporal_cohesion --variant problem|solution|both
+
+use std::env;
+use std::process::ExitCode;
+
+use coupling_cohesin_presenation::catalog::{Catalog, Example};
+use coupling_cohesin_presenation::harness::capture;
+
+const USAGE: &str = "usage: demo list
+       demo run <example> [--variant problem|solution|both]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variant {
+    Problem,
+    Solution,
+    Both,
+}
+
+impl Variant {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "problem" => Some(Variant::Problem),
+            "solution" => Some(Variant::Solution),
+            "both" => Some(Variant::Both),
+            _ => None,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["list"] => {
+            list();
+            ExitCode::SUCCESS
+        }
+        ["run", name, rest @ ..] => {
+            let variant = match rest {
+                [] => Variant::Both,
+                ["--variant", value] => match Variant::parse(value) {
+                    Some(variant) => variant,
+                    None => return usage_error(&format!("unknown variant `{}`", value)),
+                },
+                _ => return usage_error("unexpected arguments"),
+            };
+            match Catalog::get(name) {
+                Some(example) => {
+                    run(example, variant);
+                    ExitCode::SUCCESS
+                }
+                None => usage_error(&format!("unknown example `{}`, see `demo list`", name)),
+            }
+        }
+        _ => usage_error("missing command"),
+    }
+}
+
+fn usage_error(message: &str) -> ExitCode {
+    eprintln!("error: {}\n{}", message, USAGE);
+    ExitCode::from(2)
+}
+
+fn list() {
+    for example in Catalog::examples() {
+        println!(
+            "{:<9} {} {:<25} {}",
+            example.kind().as_str(),
+            example.rank(),
+            example.name(),
+            example.summary()
+        );
+    }
+}
+
+fn run(example: &Example, variant: Variant) {
+    let problem = || match example.problem() {
+        Some(demo) => capture(demo),
+        None => "(no problem variant, this is the good form)\n".to_string(),
+    };
+    let solution = || capture(example.solution());
+
+    match variant {
+        Variant::Problem => print!("{}", problem()),
+        Variant::Solution => print!("{}", solution()),
+        Variant::Both => print!(
+            "{}",
+            side_by_side("problem", &problem(), "solution", &solution())
+        ),
+    }
+}
+
+/// Lays two transcripts out in labelled columns.
+fn side_by_side(left_label: &str, left: &str, right_label: &str, right: &str) -> String {
+    let left_lines: Vec<&str> = left.lines().collect();
+    let right_lines: Vec<&str> = right.lines().collect();
+    let width = left_lines
+        .iter()
+        .chain([&left_label])
+        .map(|line| line.chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut out = format!("{:<width$} | {}\n", left_label, right_label);
+    out.push_str(&format!(
+        "{}-+-{}\n",
+        "-".repeat(width),
+        "-".repeat(right_label.len())
+    ));
+    for row in 0..left_lines.len().max(right_lines.len()) {
+        let left = left_lines.get(row).copied().unwrap_or("");
+        let right = right_lines.get(row).copied().unwrap_or("");
+        out.push_str(format!("{:<width$} | {}", left, right).trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_are_padded_to_the_longest_left_line() {
+        let out = side_by_side("problem", "a\nlonger line\n", "solution", "b\n");
+        assert_eq!(
+            out,
+            "problem     | solution\n\
+             ------------+---------\n\
+             a           | b\n\
+             longer line |\n"
+        );
+    }
+
+    #[test]
+    fn parses_variants() {
+        assert_eq!(Variant::parse("both"), Some(Variant::Both));
+        assert_eq!(Variant::parse("neither"), None);
+    }
+}
@@ -0,0 +1,184 @@
+//! ./catalog.rs
+//!
+//! Registry of every cohesion and coupling example in the crate,
+//! so tooling can enumerate the demos instead of hard-coding module names.
+
+use crate::{cohesion, coupling};
+
+/// A runnable variant of an example.
+pub type Demo = fn();
+
+/// Which ladder an example belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Kind {
+    Cohesion,
+    Coupling,
+}
+
+impl Kind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Kind::Cohesion => "cohesion",
+            Kind::Coupling => "coupling",
+        }
+    }
+}
+
+/// One example module with its place on the good-to-bad scale.
+pub struct Example {
+    name: &'static str,
+    kind: Kind,
+    rank: u8,
+    file: &'static str,
+    source: &'static str,
+    problem: Option<Demo>,
+    solution: Demo,
+}
+
+impl Example {
+    /// Module name, e.g. `temporal_cohesion`.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    /// Position on the ladder, 1 being the best form of its kind.
+    pub fn rank(&self) -> u8 {
+        self.rank
+    }
+
+    /// Path of the module relative to the crate root.
+    pub fn file(&self) -> &'static str {
+        self.file
+    }
+
+    /// Full source of the module.
+    pub fn source(&self) -> &'static str {
+        self.source
+    }
+
+    /// The description paragraph of the module's `//!` docs.
+    pub fn summary(&self) -> String {
+        module_summary(self.source)
+    }
+
+    /// The variant showing the smell, if the example has one.
+    /// Examples of good cohesion or coupling only come with a solution.
+    pub fn problem(&self) -> Option<Demo> {
+        self.problem
+    }
+
+    pub fn solution(&self) -> Demo {
+        self.solution
+    }
+}
+
+/// Every example, cohesion first, each ladder ordered from best to worst.
+pub struct Catalog;
+
+impl Catalog {
+    pub fn examples() -> &'static [Example] {
+        &EXAMPLES
+    }
+
+    pub fn get(name: &str) -> Option<&'static Example> {
+        EXAMPLES.iter().find(|example| example.name == name)
+    }
+
+    pub fn of_kind(kind: Kind) -> impl Iterator<Item = &'static Example> {
+        EXAMPLES.iter().filter(move |example| example.kind == kind)
+    }
+}
+
+macro_rules! example {
+    ($kind:ident, $rank:expr, $ladder:ident::$module:ident) => {
+        example!(@build $kind, $rank, $ladder::$module, None)
+    };
+    ($kind:ident, $rank:expr, $ladder:ident::$module:ident, problem) => {
+        example!(@build $kind, $rank, $ladder::$module, Some($ladder::$module::problem as Demo))
+    };
+    (@build $kind:ident, $rank:expr, $ladder:ident::$module:ident, $problem:expr) => {
+        Example {
+            name: stringify!($module),
+            kind: Kind::$kind,
+            rank: $rank,
+            file: concat!("src/", stringify!($ladder), "/", stringify!($module), ".rs"),
+            source: include_str!(concat!(stringify!($ladder), "/", stringify!($module), ".rs")),
+            problem: $problem,
+            solution: $ladder::$module::solution,
+        }
+    };
+}
+
+// Ranks follow the order in `cohesion.rs` and `coupling.rs`.
+static EXAMPLES: [Example; 13] = [
+    example!(Cohesion, 1, cohesion::function_cohesion),
+    example!(Cohesion, 2, cohesion::sequence_cohesion),
+    example!(Cohesion, 3, cohesion::communicational_cohesion, problem),
+    example!(Cohesion, 4, cohesion::procedural_cohesion, problem),
+    example!(Cohesion, 5, cohesion::temporal_cohesion, problem),
+    example!(Cohesion, 6, cohesion::logical_cohesion, problem),
+    example!(Cohesion, 7, cohesion::coincidental_cohesion, problem),
+    example!(Coupling, 1, coupling::data_coupling),
+    example!(Coupling, 2, coupling::stamp_coupling, problem),
+    example!(Coupling, 3, coupling::control_coupling, problem),
+    example!(Coupling, 4, coupling::external_coupling, problem),
+    example!(Coupling, 5, coupling::common_coupling, problem),
+    example!(Coupling, 6, coupling::content_coupling, problem),
+];
+
+/// Extracts the description from `//!` docs, skipping the `./file.rs` header
+/// and stopping at the first blank doc line.
+fn module_summary(source: &str) -> String {
+    source
+        .lines()
+        .map_while(|line| line.strip_prefix("//!"))
+        .map(str::trim)
+        .skip_while(|line| line.starts_with("./") || line.is_empty())
+        .take_while(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_every_example_once() {
+        assert_eq!(Catalog::examples().len(), 13);
+        assert_eq!(Catalog::of_kind(Kind::Cohesion).count(), 7);
+        assert_eq!(Catalog::of_kind(Kind::Coupling).count(), 6);
+    }
+
+    #[test]
+    fn ranks_follow_ladders() {
+        let coupling: Vec<_> = Catalog::of_kind(Kind::Coupling)
+            .map(|example| (example.rank(), example.name()))
+            .collect();
+        assert_eq!(coupling.first(), Some(&(1, "data_coupling")));
+        assert_eq!(coupling.last(), Some(&(6, "content_coupling")));
+
+        let worst_cohesion = Catalog::get("coincidental_cohesion").unwrap();
+        assert_eq!(worst_cohesion.rank(), 7);
+    }
+
+    #[test]
+    fn summary_comes_from_module_docs() {
+        let example = Catalog::get("common_coupling").unwrap();
+        assert_eq!(
+            example.summary(),
+            "Common coupling occurs when two or more modules share access to the same global, mutable state"
+        );
+        assert_eq!(example.file(), "src/coupling/common_coupling.rs");
+        assert!(
+            Catalog::get("function_cohesion")
+                .unwrap()
+                .problem()
+                .is_none()
+        );
+    }
+}
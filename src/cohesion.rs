@@ -4,13 +4,13 @@
 //! A good software design will have high cohesion.
 
 #[rustfmt::skip]
-mod function_cohesion;
-mod coincidental_cohesion;
-mod communicational_cohesion;
-mod logical_cohesion;
-mod procedural_cohesion;
-mod sequence_cohesion;
-mod temporal_cohesion;
+pub(crate) mod function_cohesion;
+pub(crate) mod coincidental_cohesion;
+pub(crate) mod communicational_cohesion;
+pub(crate) mod logical_cohesion;
+pub(crate) mod procedural_cohesion;
+pub(crate) mod sequence_cohesion;
+pub(crate) mod temporal_cohesion;
 
 // mod function_cohesion;
 // mod sequence_cohesion;
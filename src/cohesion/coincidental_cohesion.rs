@@ -31,3 +31,14 @@ mod math_utils {
         angle.cos()
     }
 }
+
+/// Demo
+pub(crate) fn problem() {
+    println!("cos(0.0) = {}", utils::calculate_cosine(0.0));
+    utils::greet("World");
+    println!("0xff = {:?}", utils::parse_hex_to_u32("ff"));
+}
+
+pub(crate) fn solution() {
+    println!("cos(0.0) = {}", math_utils::calculate_cosine(0.0));
+}
@@ -76,3 +76,30 @@ mod reporting_module {
         )
     }
 }
+
+/// Demo
+pub(crate) fn problem() {
+    let mut profile = customer::CustomerProfile::new(
+        "Alice",
+        "Smith",
+        "alice.smith@example.com",
+        "123 Main Street",
+    );
+
+    println!("Full Name: {}", profile.get_full_name());
+    println!("Mailing Label:\n{}", profile.get_mailing_label());
+
+    profile.update_email("alice.s@newmail.com");
+    println!("New email set, profile is updated.");
+}
+
+pub(crate) fn solution() {
+    let profile = customer::CustomerProfile::new(
+        "Alice",
+        "Smith",
+        "alice.smith@example.com",
+        "123 Main Street",
+    );
+
+    println!("{}", reporting_module::generate_customer_summary(&profile));
+}
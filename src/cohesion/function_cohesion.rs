@@ -31,21 +31,34 @@ mod circle_geometry {
     }
 }
 
+/// Demo
+pub(crate) fn solution() {
+    use circle_geometry::Circle;
+
+    let my_circle = Circle::new(5.0);
+    println!("Circle with radius 5.0:");
+    println!("Area: {}", my_circle.calculate_area());
+    println!("Circumference: {}", my_circle.calculate_circumference());
+}
+
 #[cfg(test)]
 mod test {
+    use crate::harness::Transcript;
     use super::circle_geometry;
 
     #[test]
     fn circle_geometry_calculation() {
-        use circle_geometry::Circle;
+        Transcript::record(|| {
+            use circle_geometry::Circle;
 
-        let my_circle = Circle::new(5.0);
-        let area = my_circle.calculate_area();
-        let circumference = my_circle.calculate_circumference();
+            let my_circle = Circle::new(5.0);
+            let area = my_circle.calculate_area();
+            let circumference = my_circle.calculate_circumference();
 
-        println!("Circle with radius 5.0:");
-        println!("Area: {}", area);
-        println!("Circumference: {}", circumference);
-        assert!(false);
+            println!("Circle with radius 5.0:");
+            println!("Area: {}", area);
+            println!("Circumference: {}", circumference);
+        })
+        .assert_snapshot("function_cohesion.circle_geometry_calculation");
     }
 }
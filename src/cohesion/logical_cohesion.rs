@@ -78,3 +78,23 @@ mod loggers {
         }
     }
 }
+
+/// Demo
+pub(crate) fn problem() {
+    use logging::{LogDestination, log_message};
+
+    log_message("System started.", LogDestination::Console);
+    log_message("User logged in.", LogDestination::File);
+    log_message(
+        "Error: Database connection failed.",
+        LogDestination::Database,
+    );
+}
+
+pub(crate) fn solution() {
+    use loggers::{ConsoleLogger, DatabaseLogger, FileLogger, Logger};
+
+    ConsoleLogger.log("System started.");
+    FileLogger.log("User logged in.");
+    DatabaseLogger.log("Error: Database connection failed.");
+}
@@ -41,13 +41,15 @@ mod report_generation {
 #[cfg(test)]
 mod test {
     use super::report_generation;
+    use crate::harness::Transcript;
 
     #[test]
     fn generating_report() {
-        let report = report_generation::generate_full_report();
-        println!("\n--- Generated Report ---\n{}", report);
-
-        assert!(false);
+        Transcript::record(|| {
+            let report = report_generation::generate_full_report();
+            println!("\n--- Generated Report ---\n{}", report);
+        })
+        .assert_snapshot("procedural_cohesion.generating_report");
     }
 }
 
@@ -81,7 +83,13 @@ mod report_parts {
     }
 }
 
-fn main() {
+/// Demo
+pub(crate) fn problem() {
+    let report = report_generation::generate_full_report();
+    println!("\n--- Generated Report ---\n{}", report);
+}
+
+pub(crate) fn solution() {
     use report_parts::{assemble_report, fetch_raw_data, format_cover_page, summarize_data};
 
     // The functions are called in a specific sequence, but are more flexible.
@@ -35,17 +35,29 @@ mod text_processor {
     }
 }
 
+/// Demo
+pub(crate) fn solution() {
+    let raw_text = "   Hello World from Rust!   ";
+    let processed_text = text_processor::process_text(raw_text);
+
+    println!("Original: '{}'", raw_text);
+    println!("Processed: '{}'", processed_text);
+}
+
 #[cfg(test)]
 mod test {
     use super::text_processor;
+    use crate::harness::Transcript;
 
     #[test]
     fn text_processor() {
-        let raw_text = "   Hello World from Rust!   ";
-        let processed_text = text_processor::process_text(raw_text);
+        Transcript::record(|| {
+            let raw_text = "   Hello World from Rust!   ";
+            let processed_text = text_processor::process_text(raw_text);
 
-        println!("Original: '{}'", raw_text);
-        println!("Processed: '{}'", processed_text);
-        assert!(false);
+            println!("Original: '{}'", raw_text);
+            println!("Processed: '{}'", processed_text);
+        })
+        .assert_snapshot("sequence_cohesion.text_processor");
     }
 }
@@ -39,11 +39,14 @@ mod system {
 #[cfg(test)]
 mod test {
     use super::system;
+    use crate::harness::Transcript;
 
     #[test]
     fn init_system() {
-        system::startup();
-        assert!(false);
+        Transcript::record(|| {
+            system::startup();
+        })
+        .assert_snapshot("temporal_cohesion.init_system");
     }
 }
 
@@ -84,3 +87,12 @@ mod system_v2 {
         println!("Startup complete.");
     }
 }
+
+/// Demo
+pub(crate) fn problem() {
+    system::startup();
+}
+
+pub(crate) fn solution() {
+    system_v2::startup();
+}
@@ -5,12 +5,12 @@
 //!
 
 #[rustfmt::skip]
-mod data_coupling; // High
-mod common_coupling;
-mod content_coupling;
-mod control_coupling;
-mod external_coupling;
-mod stamp_coupling; // Low
+pub(crate) mod data_coupling; // High
+pub(crate) mod common_coupling;
+pub(crate) mod content_coupling;
+pub(crate) mod control_coupling;
+pub(crate) mod external_coupling;
+pub(crate) mod stamp_coupling; // Low
 //
 // mod data_coupling; // Ok
 // mod stamp_coupling;
@@ -7,46 +7,71 @@
 use std::sync::{Arc, Mutex};
 #[cfg(test)]
 mod tests {
+    use crate::harness::Transcript;
+
     #[test]
     fn modify_shared_stated() {
-        let state_one: i64 = 11;
-        let state_two: i64 = 12;
-
-        let state_one_ptr = &state_one as *const i64;
-        let state_one_addr: usize = unsafe { std::mem::transmute(state_one_ptr) };
-        println!(
-            "state_one_ptr: {} ({:p}...0x{:x})",
-            state_one,
-            state_one_ptr,
-            state_one_addr + 7
-        );
-
-        let state_two_ptr = &state_two as *const i64;
-        let state_two_addr: usize = unsafe { std::mem::transmute(state_two_ptr) };
-        println!(
-            "state_two_ptr: {} ({:p}...0x{:x})",
-            state_two,
-            state_two_ptr,
-            state_two_addr + 7
-        );
-
-        println!("Modifing the pointer value of state_one_ptr");
-        unsafe {
-            let new_addr = state_one_ptr.byte_offset(8);
-            println!("{:p} -> {:p}", state_one_ptr, new_addr);
-            let value_from_new_addr: i64 = *new_addr;
-            println!("Value of new_addr: {}", value_from_new_addr);
-        }
-
-        // Imagine if two or more threads modifies these state in arbitrary order
-
-        assert!(false);
+        // Addresses change between runs, so the demo only prints their distance.
+        Transcript::record(super::problem).assert_snapshot("common_coupling.modify_shared_stated");
     }
 }
 
 /// Solution
 /// Use: Avoid Raw pointer
 /// And use thread-safe structs if sharing state is needed
+#[derive(Clone, Default)]
 pub struct State {
     count: Arc<Mutex<i32>>,
 }
+
+impl State {
+    pub fn increment(&self) {
+        *self.count.lock().unwrap() += 1;
+    }
+
+    pub fn count(&self) -> i32 {
+        *self.count.lock().unwrap()
+    }
+}
+
+/// Demo
+pub(crate) fn problem() {
+    // Two pieces of state that happen to live next to each other.
+    let states: [i64; 2] = [11, 12];
+
+    let state_one_ptr = states.as_ptr();
+    let state_one_addr = state_one_ptr as usize;
+    println!("state_one_ptr: {}", states[0]);
+
+    // One element on, still inside `states`.
+    let state_two_ptr = unsafe { state_one_ptr.add(1) };
+    let state_two_addr = state_two_ptr as usize;
+    println!(
+        "state_two_ptr: {} ({} bytes after state_one_ptr)",
+        states[1],
+        state_two_addr - state_one_addr
+    );
+
+    println!("Modifing the pointer value of state_one_ptr");
+    unsafe {
+        let new_addr = state_one_ptr.byte_offset(8);
+        let value_from_new_addr: i64 = *new_addr;
+        println!("Value of new_addr: {}", value_from_new_addr);
+    }
+
+    // Imagine if two or more threads modifies these state in arbitrary order
+}
+
+pub(crate) fn solution() {
+    let state = State::default();
+    let workers: Vec<_> = (0..2)
+        .map(|_| {
+            let state = state.clone();
+            std::thread::spawn(move || state.increment())
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    println!("Shared count after two threads: {}", state.count());
+}
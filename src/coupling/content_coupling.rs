@@ -35,32 +35,51 @@ pub trait Reportable {
     fn modify_count(&mut self);
 }
 
+/// Demo
+pub(crate) fn problem() {
+    let storage = Storage::default();
+    storage.show_view_count();
+}
+
+pub(crate) fn solution() {
+    fn report_view_count(rp: impl Reportable) {
+        println!("Total view count is : {}", rp.view_count());
+    }
+
+    let mut storage = Storage::default();
+    storage.modify_count();
+    report_view_count(storage);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::harness::Transcript;
 
     #[test]
     fn external_library() {
-        let storage: Storage = Storage::default();
+        Transcript::record(|| {
+            let storage: Storage = Storage::default();
 
-        storage.show_view_count();
-
-        assert!(false)
+            storage.show_view_count();
+        })
+        .assert_snapshot("content_coupling.external_library");
     }
 
     #[test]
     fn external_library_contract() {
-        let storage: Storage = Storage::default();
-
-        fn report_view_code<T>(rp: T)
-        where
-            T: Reportable,
-        {
-            println!("Total view count is : {}", rp.view_count());
-        }
+        Transcript::record(|| {
+            let storage: Storage = Storage::default();
 
-        report_view_code(storage);
+            fn report_view_code<T>(rp: T)
+            where
+                T: Reportable,
+            {
+                println!("Total view count is : {}", rp.view_count());
+            }
 
-        assert!(false)
+            report_view_code(storage);
+        })
+        .assert_snapshot("content_coupling.external_library_contract");
     }
 }
@@ -13,6 +13,7 @@ mod formatter {
         Xml,
     }
 
+    // coupling:allow(control)
     pub fn format_data(data: String, format: Format) -> String {
         match format {
             Format::PlainText => {
@@ -49,15 +50,18 @@ mod report_generator {
 #[cfg(test)]
 mod tests {
     use super::{formatter::*, report_generator::*};
+    use crate::harness::Transcript;
 
     #[test]
     fn caller_dictate_callee() {
-        let plain_report = generate_report(Format::PlainText);
-        println!("{}", plain_report);
-
-        let html_report = generate_report(Format::Html);
-        println!("{}", html_report);
-        assert!(false);
+        Transcript::record(|| {
+            let plain_report = generate_report(Format::PlainText);
+            println!("{}", plain_report);
+
+            let html_report = generate_report(Format::Html);
+            println!("{}", html_report);
+        })
+        .assert_snapshot("control_coupling.caller_dictate_callee");
     }
 }
 
@@ -106,3 +110,22 @@ mod report_generator_v2 {
         formatter.format(data)
     }
 }
+
+/// Demo
+pub(crate) fn problem() {
+    use formatter::Format;
+
+    println!("{}", report_generator::generate_report(Format::PlainText));
+    println!("{}", report_generator::generate_report(Format::Html));
+}
+
+pub(crate) fn solution() {
+    use html_formatter::HtmlFormatter;
+    use plain_text_formatter::PlainTextFormatter;
+
+    println!(
+        "{}",
+        report_generator_v2::generate_report(&PlainTextFormatter)
+    );
+    println!("{}", report_generator_v2::generate_report(&HtmlFormatter));
+}
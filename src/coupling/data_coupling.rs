@@ -23,10 +23,22 @@ mod display {
     }
 }
 
+/// Demo
+pub(crate) fn solution() {
+    let laptop = basket::Product {
+        id: 1,
+        name: "Macbook".to_string(),
+        price: 3.999,
+    };
+
+    let formatted_price = display::format_price(laptop.price);
+    println!("The price of the {} is {}", laptop.name, formatted_price);
+}
+
 #[cfg(test)]
 mod test {
 
-    use super::{display::format_price, shop::Product};
+    use super::{display::format_price, basket::Product};
 
     #[test]
     fn dislay_product() {
@@ -12,7 +12,7 @@ mod data_loader {
     #[derive(Deserialize, Debug)]
     pub struct Record {
         pub id: u32,
-        pub data: f64,
+        pub value: f64,
     }
 
     pub fn load_data(file_path: &str) -> Result<Vec<Record>, csv::Error> {
@@ -101,3 +101,24 @@ mod data_processor_v2 {
         sum / (records.len() as f64)
     }
 }
+
+/// Demo
+pub(crate) fn problem() {
+    match data_loader::load_data("data.csv") {
+        Ok(records) => println!(
+            "Average value: {}",
+            data_processor::process_records(&records)
+        ),
+        Err(e) => println!("Error loading data: {}", e),
+    }
+}
+
+pub(crate) fn solution() {
+    match parser::load_records_from_csv("data.csv") {
+        Ok(records) => println!(
+            "Average value: {}",
+            data_processor_v2::process_records(&records)
+        ),
+        Err(e) => println!("Error loading data: {}", e),
+    }
+}
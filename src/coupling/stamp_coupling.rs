@@ -57,7 +57,30 @@ mod telemetry_v2 {
 // Module now takes only the specific data it needs.
 mod notification_v2 {
     // The function is now decoupled from the internal structure of CustomerProfile.
-    pub fn send_telemetry(log_ids: [&str]) {
-        println!("Sending record with {} id", log_id);
+    pub fn send_telemetry(id: u64) {
+        println!("Sending record with {} id", id);
     }
 }
+
+/// Demo
+pub(crate) fn problem() {
+    let record = telemetry::Record {
+        id: 1,
+        name: "John".to_string(),
+        email: "john@mail.com".to_string(),
+        address: "US".to_string(),
+    };
+
+    notification::send_telemetry(&record);
+}
+
+pub(crate) fn solution() {
+    let record = telemetry_v2::Record {
+        id: 1,
+        name: "John".to_string(),
+        email: "john@mail.com".to_string(),
+        address: "US".to_string(),
+    };
+
+    notification_v2::send_telemetry(record.id);
+}
@@ -0,0 +1,134 @@
+//! ./harness.rs
+//!
+//! Captures what a demo prints so it can be shown or compared later.
+//!
+//! The crate shadows `println!` with a version that writes into the
+//! active capture, so the examples keep reading like ordinary Rust.
+//! Tests compare the captured transcript against golden files under
+//! `tests/snapshots/`; run with `UPDATE_SNAPSHOTS=1` to accept new output.
+
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::{env, fmt, fs};
+
+thread_local! {
+    static CAPTURE: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// `println!` for the examples: goes to the active capture if there is one,
+/// stdout otherwise.
+macro_rules! println {
+    () => {
+        $crate::harness::emit(format_args!("\n"))
+    };
+    ($($arg:tt)*) => {
+        $crate::harness::emit(format_args!("{}\n", format_args!($($arg)*)))
+    };
+}
+
+#[doc(hidden)]
+pub fn emit(args: fmt::Arguments) {
+    let captured = CAPTURE.with_borrow_mut(|capture| match capture {
+        Some(buffer) => {
+            fmt::Write::write_fmt(buffer, args).expect("writing to a String cannot fail");
+            true
+        }
+        None => false,
+    });
+    if !captured {
+        std::print!("{}", args);
+    }
+}
+
+/// Runs `demo` and returns everything it printed on the current thread.
+pub fn capture(demo: impl FnOnce()) -> String {
+    let mut restore = Restore(Some(CAPTURE.replace(Some(String::new()))));
+    demo();
+    let previous = restore.0.take().expect("restored only once");
+    CAPTURE.replace(previous).unwrap_or_default()
+}
+
+/// Everything a demo printed, ready to be compared against a golden file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transcript(String);
+
+impl Transcript {
+    pub fn record(demo: impl FnOnce()) -> Self {
+        Transcript(capture(demo))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Location of the golden file for `name`.
+    pub fn snapshot_path(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/snapshots")
+            .join(format!("{}.txt", name))
+    }
+
+    /// Panics unless the transcript matches `tests/snapshots/<name>.txt`.
+    /// With `UPDATE_SNAPSHOTS` set, the golden file is (re)written instead.
+    pub fn assert_snapshot(&self, name: &str) {
+        let path = Self::snapshot_path(name);
+        if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(path.parent().expect("snapshots live in a directory"))
+                .expect("Failed to create snapshot directory");
+            fs::write(&path, &self.0).expect("Failed to write snapshot");
+            return;
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+            panic!(
+                "missing snapshot {}, rerun with UPDATE_SNAPSHOTS=1 to create it",
+                path.display()
+            )
+        });
+        assert_eq!(
+            self.0, expected,
+            "transcript changed for snapshot `{}`, rerun with UPDATE_SNAPSHOTS=1 to accept it",
+            name
+        );
+    }
+}
+
+/// Puts the outer capture back if a demo panics.
+struct Restore(Option<Option<String>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        if let Some(previous) = self.0.take() {
+            CAPTURE.set(previous);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Transcript, capture};
+
+    #[test]
+    fn captures_printed_lines() {
+        let output = capture(|| {
+            println!("Hello, {}!", "World");
+            println!();
+        });
+        assert_eq!(output, "Hello, World!\n\n");
+    }
+
+    #[test]
+    fn nested_captures_do_not_leak() {
+        let outer = capture(|| {
+            println!("outer");
+            let inner = capture(|| println!("inner"));
+            assert_eq!(inner, "inner\n");
+        });
+        assert_eq!(outer, "outer\n");
+    }
+
+    #[test]
+    fn snapshot_path_is_under_tests() {
+        let path = Transcript::snapshot_path("temporal_cohesion.init_system");
+        assert!(path.ends_with("tests/snapshots/temporal_cohesion.init_system.txt"));
+    }
+}
@@ -1,4 +1,14 @@
+pub mod analysis;
+pub mod catalog;
+#[macro_use]
+pub mod harness;
+pub mod lsp;
+pub mod refactor;
+pub mod slides;
+
+#[allow(dead_code)]
 mod cohesion;
+#[allow(dead_code)]
 mod coupling;
 
 pub fn add(left: u64, right: u64) -> u64 {
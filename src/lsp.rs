@@ -0,0 +1,13 @@
+//! ./lsp.rs
+//!
+//! A language server, so the findings show up while typing instead of in
+//! CI: diagnostics for every detected smell, the module's instability and
+//! LCOM on hover, and code actions running the [refactorings](crate::refactor).
+//!
+//! Only what the editor hasn't saved is kept in memory; everything else is
+//! read from disk, and the whole crate is analyzed again on every change.
+
+mod server;
+pub mod transport;
+
+pub use server::Server;
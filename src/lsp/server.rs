@@ -0,0 +1,806 @@
+//! ./lsp/server.rs
+//!
+//! Requests and notifications the server understands, and how findings,
+//! metrics and patches are put in the protocol's terms.
+//!
+//! Positions on the wire count UTF-16 code units, the protocol's default,
+//! while spans count characters and patches bytes; every conversion goes
+//! through the text of the file.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::{Value, json};
+
+use super::transport;
+use crate::analysis::external::Adapters;
+use crate::analysis::gate::Config;
+use crate::analysis::lcom::{self, StructCohesion};
+use crate::analysis::metrics::{self, ModuleMetrics};
+use crate::analysis::modules::Crate;
+use crate::analysis::report::{Finding, Level, Report, Severity};
+use crate::analysis::{CohesionLevel, CouplingLevel, Error, Location};
+use crate::refactor::{Patch, extract, narrow, polymorphism};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_NOT_INITIALIZED: i64 = -32002;
+
+/// A language server for one crate, the workspace root the editor opens.
+#[derive(Default)]
+pub struct Server {
+    root: Option<PathBuf>,
+    /// Text of the documents open in the editor, by path.
+    open: BTreeMap<PathBuf, String>,
+    analysis: Option<Analysis>,
+    /// Files with diagnostics in the editor, to clear when they go away.
+    published: BTreeSet<PathBuf>,
+    shut_down: bool,
+    exited: bool,
+}
+
+/// What the last successful load of the crate found.
+struct Analysis {
+    krate: Crate,
+    report: Report,
+    metrics: Vec<ModuleMetrics>,
+    structs: Vec<(String, StructCohesion)>,
+}
+
+type Reply = Result<Value, (i64, String)>;
+
+impl Server {
+    pub fn new() -> Self {
+        Server::default()
+    }
+
+    /// Whether the client asked for a shutdown before leaving, which makes
+    /// for a clean exit.
+    pub fn shut_down(&self) -> bool {
+        self.shut_down
+    }
+
+    /// Serves messages from `input` until the client sends `exit` or the
+    /// stream ends.
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        while let Some(body) = transport::read(&mut input)? {
+            for message in self.receive(&body) {
+                transport::write(&mut output, &message)?;
+            }
+            if self.exited {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles one message, returning what to send back: the response to
+    /// a request, or the notifications a notification causes.
+    pub fn receive(&mut self, body: &str) -> Vec<Value> {
+        let message: Value = match serde_json::from_str(body) {
+            Ok(message) => message,
+            Err(error) => {
+                return vec![response(Value::Null, Err((PARSE_ERROR, error.to_string())))];
+            }
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // A response to a request of ours; the server sends none.
+            return Vec::new();
+        };
+        match message.get("id").cloned() {
+            Some(id) => vec![response(id, self.request(method, &params))],
+            None => self.notification(method, &params),
+        }
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Reply {
+        if method == "initialize" {
+            return Ok(self.initialize(params));
+        }
+        if self.root.is_none() {
+            return Err((SERVER_NOT_INITIALIZED, "initialize first".into()));
+        }
+        if self.shut_down {
+            return Err((INVALID_REQUEST, "the server is shutting down".into()));
+        }
+        match method {
+            "shutdown" => {
+                self.shut_down = true;
+                Ok(Value::Null)
+            }
+            "textDocument/hover" => {
+                let (path, line, character) = document_position(params)?;
+                Ok(self.hover(&path, line, character))
+            }
+            "textDocument/codeAction" => {
+                let path = document(params)?;
+                let start = params["range"]["start"]["line"].as_u64().unwrap_or(0) as usize;
+                let end = params["range"]["end"]["line"].as_u64().unwrap_or(0) as usize;
+                let only: Vec<&str> = params["context"]["only"]
+                    .as_array()
+                    .map(|kinds| kinds.iter().filter_map(Value::as_str).collect())
+                    .unwrap_or_default();
+                Ok(Value::Array(self.code_actions(
+                    &path,
+                    start + 1..=end + 1,
+                    &only,
+                )))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("`{}` is not supported", method))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        if self.root.is_none() && method != "exit" {
+            return Vec::new();
+        }
+        match method {
+            "initialized" => self.analyze(),
+            "textDocument/didOpen" => {
+                let document = &params["textDocument"];
+                match (path_of(&document["uri"]), document["text"].as_str()) {
+                    (Some(path), Some(text)) => {
+                        self.open.insert(path, text.to_string());
+                        self.analyze()
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            "textDocument/didChange" => {
+                // Full document sync: the last change holds the whole text.
+                let text = params["contentChanges"]
+                    .as_array()
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str());
+                match (path_of(&params["textDocument"]["uri"]), text) {
+                    (Some(path), Some(text)) => {
+                        self.open.insert(path, text.to_string());
+                        self.analyze()
+                    }
+                    _ => Vec::new(),
+                }
+            }
+            "textDocument/didSave" => self.analyze(),
+            "textDocument/didClose" => {
+                if let Some(path) = path_of(&params["textDocument"]["uri"]) {
+                    self.open.remove(&path);
+                }
+                self.analyze()
+            }
+            "exit" => {
+                self.exited = true;
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn initialize(&mut self, params: &Value) -> Value {
+        let root = path_of(&params["rootUri"])
+            .or_else(|| params["rootPath"].as_str().map(PathBuf::from))
+            .or_else(|| path_of(&params["workspaceFolders"][0]["uri"]))
+            .unwrap_or_else(|| PathBuf::from("."));
+        self.root = Some(root);
+        json!({
+            "capabilities": {
+                "textDocumentSync": { "openClose": true, "change": 1, "save": true },
+                "hoverProvider": true,
+                "codeActionProvider": { "codeActionKinds": ["refactor.rewrite", "refactor.extract"] },
+            },
+            "serverInfo": { "name": "coupling-lsp", "version": env!("CARGO_PKG_VERSION") },
+        })
+    }
+
+    /// Loads the crate again and publishes its findings, clearing files
+    /// that no longer have any. A file that doesn't parse gets that as its
+    /// only diagnostic, and the rest keep theirs.
+    fn analyze(&mut self) -> Vec<Value> {
+        let Some(root) = &self.root else {
+            return Vec::new();
+        };
+        let krate = match Crate::load_open(root, &self.open) {
+            Ok(krate) => krate,
+            Err(Error::Parse { path, source }) => {
+                let text = self.text(&path).unwrap_or_default();
+                let start = source.span().start();
+                let end = source.span().end();
+                let diagnostic = json!({
+                    "range": {
+                        "start": position(&text, start.line, start.column),
+                        "end": position(&text, end.line, end.column),
+                    },
+                    "severity": 1,
+                    "source": "coupling",
+                    "message": format!("not analyzed, the file doesn't parse: {}", source),
+                });
+                self.published.insert(path.clone());
+                return vec![publish(&path, vec![diagnostic])];
+            }
+            Err(error) => {
+                let message = json!({ "type": 1, "message": error.to_string() });
+                return vec![notification("window/logMessage", message)];
+            }
+        };
+        let config = Config::find(root).unwrap_or_default();
+        let analysis = Analysis {
+            report: Report::build(&krate, &Adapters::new(&config.adapters)),
+            metrics: metrics::measure(&krate),
+            structs: lcom::analyze_crate(&krate),
+            krate,
+        };
+
+        let mut by_file: BTreeMap<PathBuf, Vec<Value>> = self
+            .published
+            .iter()
+            .map(|path| (path.clone(), Vec::new()))
+            .collect();
+        for finding in &analysis.report.findings {
+            let text = analysis.text(&finding.location.file);
+            by_file
+                .entry(finding.location.file.clone())
+                .or_default()
+                .push(diagnostic(text, finding));
+        }
+        self.published = by_file
+            .iter()
+            .filter(|(_, diagnostics)| !diagnostics.is_empty())
+            .map(|(path, _)| path.clone())
+            .collect();
+        self.analysis = Some(analysis);
+        by_file
+            .into_iter()
+            .map(|(path, diagnostics)| publish(&path, diagnostics))
+            .collect()
+    }
+
+    fn text(&self, path: &Path) -> Option<String> {
+        self.open
+            .get(path)
+            .cloned()
+            .or_else(|| std::fs::read_to_string(path).ok())
+    }
+
+    /// The metrics of the innermost measured module around a position,
+    /// with the LCOM of its structs.
+    fn hover(&self, path: &Path, line: usize, character: usize) -> Value {
+        let Some(analysis) = &self.analysis else {
+            return Value::Null;
+        };
+        let Some(index) = analysis
+            .krate
+            .files
+            .iter()
+            .position(|file| file.path() == path)
+        else {
+            return Value::Null;
+        };
+        let file = &analysis.krate.files[index];
+        let column = char_column(file.text(), line, character);
+        let Some(root) = analysis.krate.file_root(index) else {
+            return Value::Null;
+        };
+        let mut modules = vec![root.path.clone()];
+        file.for_each_item(|scope, item| {
+            if let syn::Item::Mod(module) = item
+                && module.content.is_some()
+                && contains(module, line + 1, column)
+            {
+                let mut segments = vec![root.path.clone()];
+                segments.extend(scope.modules.iter().cloned());
+                segments.push(module.ident.to_string());
+                modules.push(segments.join("::"));
+            }
+        });
+        modules.sort_by_key(|module| module.matches("::").count());
+        let Some(metrics) = modules.iter().rev().find_map(|module| {
+            analysis
+                .metrics
+                .iter()
+                .find(|metrics| &metrics.module == module)
+        }) else {
+            return Value::Null;
+        };
+
+        let mut text = format!(
+            "**{}**\n\nInstability {:.2} (afferent {}, efferent {}), abstractness {:.2}, distance from the main sequence {:.2}",
+            metrics.module,
+            metrics.instability,
+            metrics.afferent,
+            metrics.efferent,
+            metrics.abstractness,
+            metrics.distance
+        );
+        for (_, cohesion) in analysis
+            .structs
+            .iter()
+            .filter(|(module, _)| module == &metrics.module)
+        {
+            let name = cohesion.name.rsplit("::").next().unwrap_or(&cohesion.name);
+            text.push_str(&format!(
+                "\n\n`{}`: LCOM4 {}, LCOM1 {}, LCOM2 {}",
+                name, cohesion.lcom4, cohesion.lcom1, cohesion.lcom2
+            ));
+            if let Some(tcc) = cohesion.tcc {
+                text.push_str(&format!(", TCC {:.2}", tcc));
+            }
+        }
+        json!({ "contents": { "kind": "markdown", "value": text } })
+    }
+
+    /// A refactoring for each finding on the lines asked about, disabled
+    /// with the reason when it refuses.
+    fn code_actions(
+        &self,
+        path: &Path,
+        lines: std::ops::RangeInclusive<usize>,
+        only: &[&str],
+    ) -> Vec<Value> {
+        let Some(analysis) = &self.analysis else {
+            return Vec::new();
+        };
+        let krate = &analysis.krate;
+        let mut actions = Vec::new();
+        let mut titles = BTreeSet::new();
+        for finding in &analysis.report.findings {
+            let location = &finding.location;
+            if location.file != path
+                || location.end_line < *lines.start()
+                || location.line > *lines.end()
+            {
+                continue;
+            }
+            let function = finding.item.as_str();
+            let short = function.rsplit("::").next().unwrap_or(function);
+            let (title, kind, patch) = match finding.level {
+                Level::Coupling(CouplingLevel::Control) => (
+                    format!("Replace the flag of `{}` with a trait", short),
+                    "refactor.rewrite",
+                    polymorphism::replace_conditional(krate, function).map_err(|r| r.to_string()),
+                ),
+                Level::Coupling(CouplingLevel::Stamp) => (
+                    format!("Pass `{}` only the fields it reads", short),
+                    "refactor.rewrite",
+                    narrow::narrow(krate, function, None).map_err(|r| r.to_string()),
+                ),
+                Level::Cohesion(CohesionLevel::Temporal | CohesionLevel::Procedural) => (
+                    format!("Extract the steps of `{}` into functions", short),
+                    "refactor.extract",
+                    extract::extract(krate, function)
+                        .map(|extraction| extraction.patch)
+                        .map_err(|r| r.to_string()),
+                ),
+                _ => continue,
+            };
+            if !only.is_empty() && !only.iter().any(|only| kind.starts_with(only)) {
+                continue;
+            }
+            if !titles.insert(title.clone()) {
+                continue;
+            }
+            let text = analysis.text(&location.file);
+            let mut action = json!({
+                "title": title,
+                "kind": kind,
+                "diagnostics": [diagnostic(text, finding)],
+            });
+            match patch {
+                Ok(patch) => action["edit"] = workspace_edit(&patch),
+                Err(reason) => action["disabled"] = json!({ "reason": reason }),
+            }
+            actions.push(action);
+        }
+        actions
+    }
+}
+
+impl Analysis {
+    fn text(&self, path: &Path) -> &str {
+        self.krate
+            .files
+            .iter()
+            .find(|file| file.path() == path)
+            .map_or("", |file| file.text())
+    }
+}
+
+fn response(id: Value, reply: Reply) -> Value {
+    match reply {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err((code, message)) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message },
+        }),
+    }
+}
+
+fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+fn publish(path: &Path, diagnostics: Vec<Value>) -> Value {
+    notification(
+        "textDocument/publishDiagnostics",
+        json!({ "uri": uri(path), "diagnostics": diagnostics }),
+    )
+}
+
+fn diagnostic(text: &str, finding: &Finding) -> Value {
+    json!({
+        "range": range(text, &finding.location),
+        "severity": match finding.severity {
+            Severity::Error => 1,
+            Severity::Warning => 2,
+            Severity::Note => 3,
+        },
+        "code": finding.rule,
+        "source": "coupling",
+        "message": finding.message,
+        "data": { "item": finding.item },
+    })
+}
+
+/// The patch as edits against the files as they are now.
+fn workspace_edit(patch: &Patch) -> Value {
+    let mut changes = serde_json::Map::new();
+    for (path, original, edits) in patch.edits() {
+        let edits: Vec<Value> = edits
+            .into_iter()
+            .map(|(range, text)| {
+                json!({
+                    "range": {
+                        "start": byte_position(original, range.start),
+                        "end": byte_position(original, range.end),
+                    },
+                    "newText": text,
+                })
+            })
+            .collect();
+        changes.insert(uri(path), Value::Array(edits));
+    }
+    json!({ "changes": changes })
+}
+
+fn document(params: &Value) -> Result<PathBuf, (i64, String)> {
+    path_of(&params["textDocument"]["uri"])
+        .ok_or_else(|| (INVALID_PARAMS, "expected a file URI".to_string()))
+}
+
+fn document_position(params: &Value) -> Result<(PathBuf, usize, usize), (i64, String)> {
+    let path = document(params)?;
+    let at = &params["position"];
+    match (at["line"].as_u64(), at["character"].as_u64()) {
+        (Some(line), Some(character)) => Ok((path, line as usize, character as usize)),
+        _ => Err((INVALID_PARAMS, "expected a position".to_string())),
+    }
+}
+
+/// Whether a module's braces hold a 1-based line and character column.
+fn contains(module: &syn::ItemMod, line: usize, column: usize) -> bool {
+    let Some((braces, _)) = &module.content else {
+        return false;
+    };
+    let (open, close) = (braces.span.open().start(), braces.span.close().end());
+    (open.line, open.column) <= (line, column) && (line, column) < (close.line, close.column)
+}
+
+/// A location, whose columns count from 1, as a protocol range.
+fn range(text: &str, location: &Location) -> Value {
+    json!({
+        "start": position(text, location.line, location.column - 1),
+        "end": position(text, location.end_line, location.end_column - 1),
+    })
+}
+
+/// A 1-based line and 0-based character column, as spans have them, as a
+/// protocol position.
+fn position(text: &str, line: usize, column: usize) -> Value {
+    let line_text = text.lines().nth(line.saturating_sub(1)).unwrap_or("");
+    let character: usize = line_text.chars().take(column).map(char::len_utf16).sum();
+    json!({ "line": line.saturating_sub(1), "character": character })
+}
+
+fn byte_position(text: &str, offset: usize) -> Value {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |at| at + 1);
+    json!({
+        "line": before.matches('\n').count(),
+        "character": before[line_start..].encode_utf16().count(),
+    })
+}
+
+/// The character column of a protocol position on a 0-based line.
+fn char_column(text: &str, line: usize, character: usize) -> usize {
+    let line_text = text.lines().nth(line).unwrap_or("");
+    let mut units = 0;
+    line_text
+        .chars()
+        .take_while(|c| {
+            units += c.len_utf16();
+            units <= character
+        })
+        .count()
+}
+
+fn path_of(uri: &Value) -> Option<PathBuf> {
+    let path = uri.as_str()?.strip_prefix("file://")?;
+    let mut bytes = Vec::new();
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        match (byte, tail) {
+            (b'%', [high, low, tail @ ..]) => {
+                let hex = std::str::from_utf8(&[*high, *low]).ok()?.to_string();
+                bytes.push(u8::from_str_radix(&hex, 16).ok()?);
+                rest = tail;
+            }
+            _ => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+fn uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                uri.push(byte as char)
+            }
+            byte => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufReader, PipeReader, PipeWriter, pipe};
+    use std::thread::{self, JoinHandle};
+
+    use super::*;
+
+    /// The editor's end of two in-memory pipes to a server thread.
+    struct Client {
+        to_server: PipeWriter,
+        from_server: BufReader<PipeReader>,
+        server: JoinHandle<bool>,
+        next_id: u64,
+        /// Notifications received while waiting for responses.
+        notifications: Vec<Value>,
+    }
+
+    impl Client {
+        fn start() -> Self {
+            let (server_reads, to_server) = pipe().unwrap();
+            let (from_server, server_writes) = pipe().unwrap();
+            let server = thread::spawn(move || {
+                let mut server = Server::new();
+                server
+                    .run(BufReader::new(server_reads), server_writes)
+                    .unwrap();
+                server.shut_down()
+            });
+            Client {
+                to_server,
+                from_server: BufReader::new(from_server),
+                server,
+                next_id: 0,
+                notifications: Vec::new(),
+            }
+        }
+
+        fn request(&mut self, method: &str, params: Value) -> Value {
+            self.next_id += 1;
+            let id = self.next_id;
+            let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+            transport::write(&mut self.to_server, &message).unwrap();
+            loop {
+                let message = self.receive();
+                if message["id"] == id {
+                    return message;
+                }
+                self.notifications.push(message);
+            }
+        }
+
+        fn notify(&mut self, method: &str, params: Value) {
+            transport::write(&mut self.to_server, &notification(method, params)).unwrap();
+        }
+
+        fn receive(&mut self) -> Value {
+            let body = transport::read(&mut self.from_server).unwrap().unwrap();
+            serde_json::from_str(&body).unwrap()
+        }
+
+        /// The latest diagnostics published for `file`. A hover on it
+        /// serves as a sync point: the server answers in order, so once
+        /// the reply is in, a publish that never came fails the test
+        /// rather than leaving it waiting.
+        fn diagnostics(&mut self, file: &Path) -> Vec<Value> {
+            let uri = uri(file);
+            self.request(
+                "textDocument/hover",
+                json!({ "textDocument": { "uri": uri }, "position": { "line": 0, "character": 0 } }),
+            );
+            let published = |message: &Value| {
+                message["method"] == "textDocument/publishDiagnostics"
+                    && message["params"]["uri"] == uri.as_str()
+            };
+            let latest = self
+                .notifications
+                .iter()
+                .rfind(|message| published(message))
+                .unwrap_or_else(|| panic!("no diagnostics published for {}", uri))["params"]
+                ["diagnostics"]
+                .as_array()
+                .unwrap()
+                .clone();
+            self.notifications.retain(|message| !published(message));
+            latest
+        }
+
+        fn stop(mut self) -> bool {
+            let reply = self.request("shutdown", Value::Null);
+            assert_eq!(reply["result"], Value::Null);
+            self.notify("exit", Value::Null);
+            self.server.join().unwrap()
+        }
+    }
+
+    fn root() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+    }
+
+    fn started() -> Client {
+        let mut client = Client::start();
+        let reply = client.request("initialize", json!({ "rootUri": uri(&root()) }));
+        assert_eq!(reply["result"]["capabilities"]["hoverProvider"], true);
+        client.notify("initialized", json!({}));
+        client
+    }
+
+    #[test]
+    fn smells_are_published_hovered_and_refactored() {
+        let mut client = started();
+        let control = root().join("src/coupling/control_coupling.rs");
+        let stamp = root().join("src/coupling/stamp_coupling.rs");
+        let control_diagnostics = client.diagnostics(&control);
+        let format_data = control_diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic["code"] == "coupling/control")
+            .unwrap();
+        assert_eq!(
+            format_data["range"]["start"],
+            json!({"line": 17, "character": 8})
+        );
+        assert_eq!(format_data["severity"], 2);
+        assert!(
+            client
+                .diagnostics(&stamp)
+                .iter()
+                .any(|diagnostic| diagnostic["code"] == "coupling/stamp"
+                    && diagnostic["range"]["start"]["line"] == 22)
+        );
+
+        // Inside `mod formatter`, which the report generator depends on.
+        let hover = client.request(
+            "textDocument/hover",
+            json!({ "textDocument": { "uri": uri(&control) }, "position": { "line": 10, "character": 8 } }),
+        );
+        let text = hover["result"]["contents"]["value"].as_str().unwrap();
+        assert!(
+            text.starts_with("**crate::coupling::control_coupling::formatter**\n\nInstability 0.00 (afferent 1, efferent 0)"),
+            "{}",
+            text
+        );
+        let lcom = root().join("src/cohesion/communicational_cohesion.rs");
+        let hover = client.request(
+            "textDocument/hover",
+            json!({ "textDocument": { "uri": uri(&lcom) }, "position": { "line": 9, "character": 4 } }),
+        );
+        let text = hover["result"]["contents"]["value"].as_str().unwrap();
+        assert!(
+            text.contains("\n\n`CustomerProfile`: LCOM4 1, "),
+            "{}",
+            text
+        );
+
+        // The hand-written solution takes the names the refactoring needs,
+        // so it runs on the problem half alone.
+        let saved = std::fs::read_to_string(&control).unwrap();
+        let (problem, _) = saved.split_once("/// Solution").unwrap();
+        let document = json!({ "uri": uri(&control), "version": 1, "text": problem });
+        client.notify("textDocument/didOpen", json!({ "textDocument": document }));
+        let actions = client.request(
+            "textDocument/codeAction",
+            json!({
+                "textDocument": { "uri": uri(&control) },
+                "range": { "start": { "line": 17, "character": 0 }, "end": { "line": 17, "character": 0 } },
+                "context": { "diagnostics": [] },
+            }),
+        );
+        let action = &actions["result"][0];
+        assert_eq!(
+            action["title"],
+            "Replace the flag of `format_data` with a trait"
+        );
+        assert_eq!(action["kind"], "refactor.rewrite");
+        let edits = action["edit"]["changes"][uri(&control)].as_array().unwrap();
+        assert!(edits.iter().any(|edit| {
+            edit["newText"]
+                .as_str()
+                .unwrap()
+                .contains("pub mod xml_formatter {")
+        }));
+        let only_extract = client.request(
+            "textDocument/codeAction",
+            json!({
+                "textDocument": { "uri": uri(&control) },
+                "range": { "start": { "line": 17, "character": 0 }, "end": { "line": 17, "character": 0 } },
+                "context": { "diagnostics": [], "only": ["refactor.extract"] },
+            }),
+        );
+        assert_eq!(only_extract["result"], json!([]));
+
+        assert!(client.stop());
+    }
+
+    #[test]
+    fn unsaved_text_is_analyzed_instead_of_the_file() {
+        let mut client = Client::start();
+        let early = client.request("textDocument/hover", json!({}));
+        assert_eq!(early["error"]["code"], SERVER_NOT_INITIALIZED);
+        client.request("initialize", json!({ "rootUri": uri(&root()) }));
+
+        let stamp = root().join("src/coupling/stamp_coupling.rs");
+        let saved = std::fs::read_to_string(&stamp).unwrap();
+        let document = json!({ "uri": uri(&stamp), "version": 1, "text": saved.replace("pub fn send_telemetry(record: &Record) {", "pub fn send_telemetry(record: &Record {") });
+        client.notify("textDocument/didOpen", json!({ "textDocument": document }));
+        let broken = client.diagnostics(&stamp);
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0]["range"]["start"]["line"], 26);
+        assert!(
+            broken[0]["message"]
+                .as_str()
+                .unwrap()
+                .contains("doesn't parse")
+        );
+
+        client.notify(
+            "textDocument/didChange",
+            json!({ "textDocument": { "uri": uri(&stamp), "version": 2 }, "contentChanges": [{ "text": saved }] }),
+        );
+        assert!(
+            client
+                .diagnostics(&stamp)
+                .iter()
+                .any(|diagnostic| diagnostic["code"] == "coupling/stamp")
+        );
+
+        let unknown = client.request("workspace/symbol", json!({ "query": "" }));
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+        assert!(client.stop());
+    }
+
+    #[test]
+    fn positions_count_utf16_units() {
+        let text = "let a = 1;\nlet é𝔸 = 2;\n";
+        assert_eq!(position(text, 2, 7), json!({"line": 1, "character": 8}));
+        assert_eq!(char_column(text, 1, 8), 7);
+        let offset = text.rfind('=').unwrap();
+        assert_eq!(
+            byte_position(text, offset),
+            json!({"line": 1, "character": 8})
+        );
+        let path = Path::new("/work/my crate/src/lib.rs");
+        assert_eq!(uri(path), "file:///work/my%20crate/src/lib.rs");
+        assert_eq!(path_of(&json!(uri(path))).unwrap(), path);
+    }
+}
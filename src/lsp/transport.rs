@@ -0,0 +1,79 @@
+//! ./lsp/transport.rs
+//!
+//! The base protocol: JSON-RPC messages behind a `Content-Length` header,
+//! over any pair of byte streams.
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+
+/// The body of the next message, `None` once the stream ends between
+/// messages.
+pub fn read(input: &mut impl BufRead) -> io::Result<Option<String>> {
+    let mut length = None;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return match length {
+                None => Ok(None),
+                Some(_) => Err(io::ErrorKind::UnexpectedEof.into()),
+            };
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            length = Some(value.trim().parse::<usize>().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("bad header `{}`", header),
+                )
+            })?);
+        }
+    }
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "message without Content-Length")
+    })?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error.utf8_error()))
+}
+
+pub fn write(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn messages_round_trip_behind_their_headers() {
+        let mut stream = Vec::new();
+        write(&mut stream, &json!({"jsonrpc": "2.0", "method": "exit"})).unwrap();
+        stream.extend_from_slice(
+            "Content-Type: application/vscode-jsonrpc; charset=utf-8\r\nContent-Length: 7\r\n\r\n\"héé\""
+                .as_bytes(),
+        );
+
+        let mut input = stream.as_slice();
+        assert_eq!(
+            read(&mut input).unwrap().unwrap(),
+            r#"{"jsonrpc":"2.0","method":"exit"}"#
+        );
+        assert_eq!(read(&mut input).unwrap().unwrap(), "\"héé\"");
+        assert_eq!(read(&mut input).unwrap(), None);
+
+        let mut cut: &[u8] = b"Content-Length: 10\r\n\r\n{}";
+        assert!(read(&mut cut).is_err());
+    }
+}
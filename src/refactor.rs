@@ -0,0 +1,13 @@
+//! ./refactor.rs
+//!
+//! Refactorings for what the analyses find. Each one either produces a
+//! [`Patch`] to review and apply, or refuses with the reason it can't be
+//! done safely. Source files are never written.
+
+pub mod extract;
+pub mod narrow;
+mod patch;
+pub mod polymorphism;
+mod sites;
+
+pub use patch::Patch;
@@ -0,0 +1,596 @@
+//! ./refactor/extract.rs
+//!
+//! Extracting the steps of a temporally or procedurally cohesive function:
+//! each group of statements [`clusters`](crate::analysis::clusters) finds
+//! becomes a private function next to it, taking the parameters it reads
+//! and returning the locals the rest still needs, and the original body
+//! is left calling them in order. `system::startup` comes out like
+//! `system_v2::startup`.
+//!
+//! The patched file is parsed again and every function it touched is
+//! checked for locals read where nothing binds them any more.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::Path;
+
+use proc_macro2::Span;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+use super::Patch;
+use super::patch::span_range;
+use super::sites::{find_function, indentation, item_lines, reindent};
+use crate::analysis::clusters::{self, Cluster};
+use crate::analysis::macro_args;
+use crate::analysis::modules::{Crate, Function, Target};
+use crate::analysis::{Location, SourceFile};
+
+/// Why a function was left as it is.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Refusal {
+    /// No function outside test modules has that path.
+    NotFound(String),
+    /// The body doesn't fall apart into steps sharing no data.
+    NoSteps { function: String },
+    /// A function or step the extraction can't express.
+    Shape { reason: String, location: Location },
+    /// An extracted function would clash with an item of the module.
+    NameTaken { name: String, location: Location },
+    /// The rewritten code reads a local that nothing binds.
+    Dangling {
+        function: String,
+        local: String,
+        location: Location,
+    },
+}
+
+impl fmt::Display for Refusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Refusal::NotFound(function) => write!(f, "`{}` names no function", function),
+            Refusal::NoSteps { function } => {
+                write!(f, "`{}` doesn't fall apart into separate steps", function)
+            }
+            Refusal::Shape { reason, location } => write!(f, "{}: {}", location, reason),
+            Refusal::NameTaken { name, location } => {
+                write!(f, "{}: the module already has a `{}`", location, name)
+            }
+            Refusal::Dangling {
+                function,
+                local,
+                location,
+            } => write!(
+                f,
+                "{}: `{}` would read `{}` where nothing binds it",
+                location, function, local
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Refusal {}
+
+/// The patch with the functions whose bodies were checked.
+#[derive(Debug, Clone)]
+pub struct Extraction {
+    pub patch: Patch,
+    /// The extracted functions, then the coordinator.
+    pub checked: Vec<String>,
+}
+
+impl Extraction {
+    /// The diff with paths relative to `root`.
+    pub fn diff(&self, root: impl AsRef<Path>) -> String {
+        self.patch.diff(root)
+    }
+}
+
+/// Splits `function`, named by its path or a suffix of it, into one private
+/// function per step and a coordinator calling them.
+pub fn extract(krate: &Crate, function: &str) -> Result<Extraction, Refusal> {
+    let found =
+        find_function(krate, function).ok_or_else(|| Refusal::NotFound(function.to_string()))?;
+    let path = krate.function_path(&found);
+    let module = &krate.modules[found.module];
+    let file = krate.file_of(found.module);
+    let text = file.text();
+    let shape = |reason: &str, span: Span| Refusal::Shape {
+        reason: reason.to_string(),
+        location: file.location(span),
+    };
+    let item = module
+        .items
+        .iter()
+        .find_map(|item| match item {
+            syn::Item::Fn(item) if std::ptr::eq(&item.sig, found.sig) => Some(item),
+            _ => None,
+        })
+        .ok_or_else(|| shape("only free functions are split", found.sig.span()))?;
+    if !found.sig.generics.params.is_empty() {
+        return Err(shape(
+            "the steps would each need the function's generics",
+            found.sig.generics.span(),
+        ));
+    }
+    let finding = clusters::detect(krate)
+        .into_iter()
+        .find(|finding| finding.function == path)
+        .ok_or_else(|| Refusal::NoSteps {
+            function: path.clone(),
+        })?;
+
+    let stmts = &found.block.stmts;
+    let clusters = &finding.clusters;
+    for cluster in clusters {
+        let (first, last) = (cluster.statements[0], *cluster.statements.last().unwrap());
+        if cluster.statements.len() != last - first + 1 {
+            let step = |cluster: &Cluster| {
+                format!(
+                    "`{}` (lines {}-{})",
+                    cluster.name, cluster.first_line, cluster.last_line
+                )
+            };
+            return Err(shape(
+                &format!(
+                    "{} runs between statements of {}, moving it would reorder them",
+                    clusters
+                        .iter()
+                        .find(|other| other.statements.iter().any(|&at| first < at && at < last))
+                        .map_or("another step".to_string(), step),
+                    step(cluster)
+                ),
+                stmts[first].span(),
+            ));
+        }
+        if let Some(taken) = module.item(&cluster.name) {
+            return Err(Refusal::NameTaken {
+                name: cluster.name.clone(),
+                location: file.location(taken.span()),
+            });
+        }
+    }
+    let rest: Vec<&syn::Stmt> = finding.coordinator.iter().map(|&at| &stmts[at]).collect();
+
+    let indent = indentation(text, item.span());
+    let body_indent = format!("{}    ", indent);
+    let mut helpers = String::new();
+    let mut calls = Vec::new();
+    for cluster in clusters {
+        let first = &stmts[cluster.statements[0]];
+        let last = &stmts[*cluster.statements.last().unwrap()];
+        let mut inputs = Vec::new();
+        for param in &cluster.params {
+            let (input, ty) = found
+                .sig
+                .inputs
+                .iter()
+                .find_map(|input| match input {
+                    syn::FnArg::Typed(typed) if binds(&typed.pat, param) => {
+                        Some((typed, &*typed.ty))
+                    }
+                    _ => None,
+                })
+                .ok_or_else(|| shape("steps of methods would need `self`", found.sig.span()))?;
+            let shared = rest.iter().any(|stmt| reads(stmt, param));
+            if shared && !matches!(ty, syn::Type::Reference(_)) {
+                return Err(shape(
+                    &format!(
+                        "`{}` would move `{}`, which the function still needs",
+                        cluster.name, param
+                    ),
+                    input.span(),
+                ));
+            }
+            inputs.push(krate.snippet(found.module, input.span()));
+        }
+        let mut types = Vec::new();
+        for local in &cluster.returns {
+            types.push(local_type(krate, &found, cluster, local).ok_or_else(|| {
+                shape(
+                    &format!("the type of `{}` is not written down", local),
+                    first.span(),
+                )
+            })?);
+        }
+        let (returns, value, binding) = match (cluster.returns.as_slice(), types.as_slice()) {
+            ([], _) => (String::new(), String::new(), String::new()),
+            ([local], [ty]) => (
+                format!(" -> {}", ty),
+                format!("{}{}\n", body_indent, local),
+                format!("let {} = ", local),
+            ),
+            (locals, types) => (
+                format!(" -> ({})", types.join(", ")),
+                format!("{}({})\n", body_indent, locals.join(", ")),
+                format!("let ({}) = ", locals.join(", ")),
+            ),
+        };
+
+        // The comment naming the step stays with it.
+        let lines = item_lines(text, first.span());
+        let start = span_range(text, first.span()).start;
+        let comment_start = text[..start].rfind('\n').map_or(0, |at| at + 1);
+        let comment = reindent(&text[lines.start..comment_start], &indent);
+        let end = span_range(text, last.span()).end;
+        let body = reindent(&text[comment_start..end], &body_indent);
+        helpers.push_str(&format!(
+            "{}{}fn {}({}){} {{\n{}{}{}}}\n\n",
+            comment,
+            indent,
+            cluster.name,
+            inputs.join(", "),
+            returns,
+            body,
+            value,
+            indent
+        ));
+
+        let mut end = text[end..].find('\n').map_or(text.len(), |at| end + at + 1);
+        // Consecutive calls are not kept apart by the blank lines that
+        // separated the steps.
+        let next = *cluster.statements.last().unwrap() + 1;
+        if clusters.iter().any(|other| other.statements[0] == next) {
+            let next_start = item_lines(text, stmts[next].span()).start;
+            if text[end..next_start].trim().is_empty() {
+                end = next_start;
+            }
+        }
+        calls.push((
+            lines.start..end,
+            format!(
+                "{}{}{}({});\n",
+                body_indent,
+                binding,
+                cluster.name,
+                cluster.params.join(", ")
+            ),
+        ));
+    }
+
+    let mut patch = Patch::new();
+    let insert = item_lines(text, item.span()).start;
+    patch.replace_range(file, insert..insert, helpers.as_str());
+    for (range, call) in calls {
+        patch.replace_range(file, range, call);
+    }
+
+    // Parse the result again and look at what was written, not at what
+    // was meant to be.
+    let mut checked: Vec<String> = clusters
+        .iter()
+        .map(|cluster| cluster.name.clone())
+        .collect();
+    checked.push(found.sig.ident.to_string());
+    let patched = &patch.apply()[file.path()];
+    let first_line = text[..insert].matches('\n').count() + 1;
+    let last_line = found.sig.ident.span().start().line + helpers.matches('\n').count();
+    let reparsed = SourceFile::parse(file.path(), patched.as_str()).map_err(|error| {
+        shape(
+            &format!("the result doesn't parse: {}", error),
+            found.sig.span(),
+        )
+    })?;
+    let mut locals = Bound::default();
+    locals.visit_block(found.block);
+    for input in &found.sig.inputs {
+        locals.visit_fn_arg(input);
+    }
+    let mut functions = Vec::new();
+    collect_functions(&reparsed.syntax().items, &mut functions);
+    for function in functions {
+        let line = function.sig.ident.span().start().line;
+        let name = function.sig.ident.to_string();
+        if !(first_line..=last_line).contains(&line) || !checked.contains(&name) {
+            continue;
+        }
+        if let Some((local, span)) = dangling(function, &locals.0).into_iter().next() {
+            return Err(Refusal::Dangling {
+                function: name,
+                local,
+                location: Location::new(file.path(), span),
+            });
+        }
+    }
+    Ok(Extraction { patch, checked })
+}
+
+/// The written type of a local a step returns: its annotation, the return
+/// type of the function in the same module that initializes it, or
+/// `String` for `format!` and `.to_string()`.
+fn local_type(
+    krate: &Crate,
+    function: &Function,
+    cluster: &Cluster,
+    local: &str,
+) -> Option<String> {
+    let binding = cluster
+        .statements
+        .iter()
+        .find_map(|&at| match &function.block.stmts[at] {
+            syn::Stmt::Local(binding) if binds(&binding.pat, local) => Some(binding),
+            _ => None,
+        })?;
+    if let syn::Pat::Type(typed) = &binding.pat {
+        return Some(krate.snippet(function.module, typed.ty.span()));
+    }
+    match &*binding.init.as_ref()?.expr {
+        syn::Expr::Call(call) => {
+            let syn::Expr::Path(callee) = &*call.func else {
+                return None;
+            };
+            let target @ Target::Item { module, .. } =
+                krate.resolve(function.module, &callee.path)?
+            else {
+                return None;
+            };
+            match krate.item(&target)? {
+                syn::Item::Fn(callee) if module == function.module => match &callee.sig.output {
+                    syn::ReturnType::Type(_, ty) => Some(krate.snippet(module, ty.span())),
+                    syn::ReturnType::Default => Some("()".to_string()),
+                },
+                _ => None,
+            }
+        }
+        syn::Expr::Macro(expr) if expr.mac.path.is_ident("format") => Some("String".to_string()),
+        syn::Expr::MethodCall(call) if call.method == "to_string" => Some("String".to_string()),
+        _ => None,
+    }
+}
+
+fn binds(pat: &syn::Pat, name: &str) -> bool {
+    let mut bound = Bound::default();
+    bound.visit_pat(pat);
+    bound.0.contains(name)
+}
+
+fn reads(stmt: &syn::Stmt, name: &str) -> bool {
+    let mut reads = Reads::default();
+    reads.visit_stmt(stmt);
+    reads.0.iter().any(|(read, _)| read == name)
+}
+
+/// Free functions at any depth of inline modules.
+fn collect_functions<'f>(items: &'f [syn::Item], functions: &mut Vec<&'f syn::ItemFn>) {
+    for item in items {
+        match item {
+            syn::Item::Fn(function) => functions.push(function),
+            syn::Item::Mod(module) => {
+                if let Some((_, items)) = &module.content {
+                    collect_functions(items, functions);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Reads of any of `locals` in `function` that no parameter or earlier
+/// `let` binds. A name bound anywhere inside the same statement, by a
+/// closure or a `match` arm, counts as bound there.
+fn dangling(function: &syn::ItemFn, locals: &BTreeSet<String>) -> Vec<(String, Span)> {
+    let mut bound = Bound::default();
+    for input in &function.sig.inputs {
+        bound.visit_fn_arg(input);
+    }
+    let mut found = Vec::new();
+    for stmt in &function.block.stmts {
+        let mut inner = Bound::default();
+        inner.visit_stmt(stmt);
+        let mut reads = Reads::default();
+        reads.visit_stmt(stmt);
+        found.extend(reads.0.into_iter().filter(|(name, _)| {
+            locals.contains(name) && !bound.0.contains(name) && !inner.0.contains(name)
+        }));
+        if let syn::Stmt::Local(local) = stmt {
+            bound.visit_pat(&local.pat);
+        }
+    }
+    found
+}
+
+/// Names bound by patterns.
+#[derive(Default)]
+struct Bound(BTreeSet<String>);
+
+impl<'ast> Visit<'ast> for Bound {
+    fn visit_pat_ident(&mut self, pat: &'ast syn::PatIdent) {
+        self.0.insert(pat.ident.to_string());
+        visit::visit_pat_ident(self, pat);
+    }
+
+    fn visit_item(&mut self, _: &'ast syn::Item) {}
+}
+
+/// Single-segment paths read, with where.
+#[derive(Default)]
+struct Reads(Vec<(String, Span)>);
+
+impl<'ast> Visit<'ast> for Reads {
+    fn visit_expr_path(&mut self, expr: &'ast syn::ExprPath) {
+        if let Some(ident) = expr.path.get_ident() {
+            self.0.push((ident.to_string(), ident.span()));
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        for arg in macro_args(mac) {
+            self.visit_expr(&arg);
+        }
+    }
+
+    fn visit_item(&mut self, _: &'ast syn::Item) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn startup_splits_like_system_v2() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let krate = Crate::load(dir).unwrap();
+        let extraction = extract(&krate, "temporal_cohesion::system::startup").unwrap();
+        assert_eq!(
+            extraction.checked,
+            vec![
+                "initialize_in_memory_cache",
+                "create_log_file",
+                "set_global_configuration_variable",
+                "startup"
+            ]
+        );
+        let patched = extraction.patch.apply();
+        let patched = patched.values().next().unwrap();
+        assert!(patched.contains(
+            "    // 3. Set a global configuration variable.
+    // This relies on some external, global state pattern.
+    // For demonstration, let's just print.
+    fn set_global_configuration_variable() {
+        println!(\"Configuration loaded from environment.\");
+    }
+
+    // This function exhibits temporal cohesion.
+    // It groups several unrelated initialization tasks together.
+    pub fn startup() {
+        println!(\"Starting up the application...\");
+
+        initialize_in_memory_cache();
+        create_log_file();
+        set_global_configuration_variable();
+
+        println!(\"Startup complete.\");
+    }
+"
+        ));
+        assert!(extraction.diff(dir).contains(
+            "+    // 1. Initialize an in-memory cache.
++    fn initialize_in_memory_cache() {
+"
+        ));
+    }
+
+    fn split(source: &str, function: &str) -> Result<String, Refusal> {
+        let file = SourceFile::parse("lib.rs", source).unwrap();
+        let krate = Crate::from_file("demo", file).unwrap();
+        extract(&krate, function)
+            .map(|extraction| extraction.patch.apply()[Path::new("lib.rs")].clone())
+    }
+
+    #[test]
+    fn steps_take_parameters_and_return_what_the_rest_needs() {
+        let patched = split(
+            "fn load() -> Vec<u8> {
+    vec![1, 2]
+}
+
+pub fn build(name: &str, limit: usize) -> String {
+    let data = load();
+    println!(\"loaded {}\", data.len());
+
+    let title: String = name.to_uppercase();
+
+    let cap = limit * 2;
+    println!(\"cap {}\", cap);
+
+    format!(\"{} {:?}\", title, data)
+}
+",
+            "build",
+        )
+        .unwrap();
+        assert_eq!(
+            patched,
+            "fn load() -> Vec<u8> {
+    vec![1, 2]
+}
+
+fn build_data() -> Vec<u8> {
+    let data = load();
+    println!(\"loaded {}\", data.len());
+    data
+}
+
+fn build_title(name: &str) -> String {
+    let title: String = name.to_uppercase();
+    title
+}
+
+fn build_cap(limit: usize) {
+    let cap = limit * 2;
+    println!(\"cap {}\", cap);
+}
+
+pub fn build(name: &str, limit: usize) -> String {
+    let data = build_data();
+    let title = build_title(name);
+    build_cap(limit);
+
+    format!(\"{} {:?}\", title, data)
+}
+"
+        );
+    }
+
+    #[test]
+    fn refuses_what_it_cannot_move() {
+        let interleaved = "fn a() -> u8 { 1 }
+fn b() -> u8 { 2 }
+pub fn load() -> u8 {
+    // Load
+    let x = a();
+    // Load
+    let y = b();
+    let z = x + 1;
+    y + z
+}";
+        let Refusal::Shape { reason, .. } = split(interleaved, "load").unwrap_err() else {
+            panic!()
+        };
+        assert_eq!(
+            reason,
+            "`build_y` (lines 7-7) runs between statements of `build_z` (lines 5-8), moving it would reorder them"
+        );
+
+        let moved = "pub fn run(items: Vec<u8>) -> usize {
+    let first = items.len();
+    println!(\"{}\", first);
+
+    let other = 2;
+    println!(\"{}\", other);
+
+    items.len()
+}";
+        let Refusal::Shape { reason, .. } = split(moved, "run").unwrap_err() else {
+            panic!()
+        };
+        assert!(reason.contains("would move `items`"), "{}", reason);
+
+        let untyped = "pub fn run() -> u8 {
+    let x = 1 + 1;
+    println!(\"{}\", x);
+
+    let y = 2;
+    println!(\"{}\", y);
+
+    x
+}";
+        let Refusal::Shape { reason, .. } = split(untyped, "run").unwrap_err() else {
+            panic!()
+        };
+        assert!(reason.contains("type of `x`"), "{}", reason);
+    }
+
+    #[test]
+    fn locals_read_where_nothing_binds_them_are_dangling() {
+        let function: syn::ItemFn =
+            syn::parse_str("fn run(a: u8) -> u8 { let b = a; let f = |c: u8| c + b; f(d) + e }")
+                .unwrap();
+        let locals = ["a", "b", "c", "d", "f"].map(String::from).into();
+        let names: Vec<String> = dangling(&function, &locals)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, vec!["d"]);
+    }
+}
@@ -0,0 +1,619 @@
+//! ./refactor/narrow.rs
+//!
+//! Narrowing a stamp-coupled parameter: the struct is replaced by the
+//! fields the function reads, and every call passes those fields instead.
+//! `send_telemetry(record: &Record)` becomes `send_telemetry(id: u64)`, and
+//! `send_telemetry(&record)` becomes `send_telemetry(record.id)`.
+//!
+//! It refuses rather than guess: when the struct is used through a trait,
+//! when a method call hides which fields are read, or when a caller's
+//! argument can't be taken apart without evaluating it more than once.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use proc_macro2::Span;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+use super::Patch;
+use super::patch::span_range;
+use super::sites::{Calls, find_function, line_range, replace_in_list};
+use crate::analysis::dependencies::{peel, struct_param, typed_params};
+use crate::analysis::modules::{Crate, Function, Target};
+use crate::analysis::ripple;
+use crate::analysis::stamp::{declared_fields, narrowed};
+use crate::analysis::{Location, macro_args};
+
+/// Why a parameter was left as it is.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Refusal {
+    /// No function outside test modules has that path and struct parameter.
+    NotFound(String),
+    /// The function implements a trait method, whose signature it must keep.
+    TraitSignature { function: String, trait_: String },
+    /// Writes through a mutable parameter would no longer reach the caller.
+    Mutable { parameter: String },
+    /// The struct is used through a trait it implements.
+    ThroughTrait { trait_: String, location: Location },
+    /// A method called on the parameter reads fields the body doesn't show.
+    BehindMethod { method: String, location: Location },
+    /// The parameter is used as a whole: passed on, matched or returned.
+    WholeUse { location: Location },
+    /// Every field is read, so there is nothing to leave behind.
+    NothingUnused { parameter: String },
+    /// A use of the function that can't be rewritten.
+    CallSite { reason: String, location: Location },
+}
+
+impl fmt::Display for Refusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Refusal::NotFound(function) => {
+                write!(
+                    f,
+                    "`{}` names no function with a struct parameter",
+                    function
+                )
+            }
+            Refusal::TraitSignature { function, trait_ } => write!(
+                f,
+                "`{}` implements `{}`, which fixes its signature",
+                function, trait_
+            ),
+            Refusal::Mutable { parameter } => write!(
+                f,
+                "`{}` is mutable, and writes to its fields must reach the caller",
+                parameter
+            ),
+            Refusal::ThroughTrait { trait_, location } => {
+                write!(f, "{}: the struct is used through `{}`", location, trait_)
+            }
+            Refusal::BehindMethod { method, location } => write!(
+                f,
+                "{}: `{}` may read any field, and its body is out of reach",
+                location, method
+            ),
+            Refusal::WholeUse { location } => {
+                write!(f, "{}: the parameter is used as a whole", location)
+            }
+            Refusal::NothingUnused { parameter } => {
+                write!(f, "every field of `{}` is read", parameter)
+            }
+            Refusal::CallSite { reason, location } => write!(f, "{}: {}", location, reason),
+        }
+    }
+}
+
+impl std::error::Error for Refusal {}
+
+/// Replaces the struct `parameter` of `function`, or its first struct
+/// parameter, with the fields the body reads, and rewrites every call.
+/// The function is named by its path or a suffix of it, like
+/// `notification::send_telemetry`.
+pub fn narrow(krate: &Crate, function: &str, parameter: Option<&str>) -> Result<Patch, Refusal> {
+    let not_found = || {
+        Refusal::NotFound(match parameter {
+            Some(parameter) => format!("{}({})", function, parameter),
+            None => function.to_string(),
+        })
+    };
+    let found = find_function(krate, function).ok_or_else(not_found)?;
+    let path = krate.function_path(&found);
+    if let Some(trait_) = implemented_trait(krate, &found) {
+        return Err(Refusal::TraitSignature {
+            function: path,
+            trait_,
+        });
+    }
+    let param = found
+        .sig
+        .inputs
+        .iter()
+        .enumerate()
+        .find_map(|(index, input)| {
+            let syn::FnArg::Typed(typed) = input else {
+                return None;
+            };
+            let syn::Pat::Ident(pat) = &*typed.pat else {
+                return None;
+            };
+            let name = pat.ident.to_string();
+            if parameter.is_some_and(|parameter| parameter != name) {
+                return None;
+            }
+            let target = struct_param(krate, found.module, &typed.ty)?;
+            Some(Param {
+                index,
+                name,
+                ty: &typed.ty,
+                mutable: pat.mutability.is_some(),
+                target,
+            })
+        })
+        .ok_or_else(not_found)?;
+    let mutable_ref = matches!(param.ty, syn::Type::Reference(ty) if ty.mutability.is_some());
+    if mutable_ref || param.mutable {
+        return Err(Refusal::Mutable {
+            parameter: param.name,
+        });
+    }
+    let by_ref = matches!(param.ty, syn::Type::Reference(_));
+
+    let file = krate.file_of(found.module);
+    let mut uses = Uses {
+        param: &param.name,
+        fields: Vec::new(),
+        methods: Vec::new(),
+        whole: Vec::new(),
+        names: BTreeSet::new(),
+    };
+    uses.visit_block(found.block);
+    if let Some((method, span)) = uses.methods.first() {
+        let location = file.location(*span);
+        return Err(match trait_providing(krate, &param.target, method) {
+            Some(trait_) => Refusal::ThroughTrait { trait_, location },
+            None => Refusal::BehindMethod {
+                method: method.clone(),
+                location,
+            },
+        });
+    }
+    if let Some((span, callee)) = uses.whole.first() {
+        let location = file.location(*span);
+        let bound = callee
+            .as_ref()
+            .and_then(|(callee, arg)| trait_bound(krate, found.module, callee, *arg));
+        return Err(match bound {
+            Some(trait_) => Refusal::ThroughTrait { trait_, location },
+            None => Refusal::WholeUse { location },
+        });
+    }
+
+    let read: BTreeSet<&str> = uses
+        .fields
+        .iter()
+        .map(|(field, _)| field.as_str())
+        .collect();
+    let declared = declared_fields(krate, &param.target);
+    let used: Vec<(String, String)> = declared
+        .iter()
+        .filter(|(field, _)| read.contains(field.as_str()))
+        .map(|(field, ty)| (field.clone(), narrowed(ty, by_ref)))
+        .collect();
+    if used.len() == declared.len() {
+        return Err(Refusal::NothingUnused {
+            parameter: param.name,
+        });
+    }
+    // A field named like another parameter or local keeps its struct's name
+    // as a prefix, so nothing in the body is shadowed.
+    let others: BTreeSet<String> = typed_params(found.sig)
+        .map(|(name, _)| name)
+        .filter(|name| *name != param.name)
+        .collect();
+    let renamed: BTreeMap<&str, String> = used
+        .iter()
+        .map(|(field, _)| {
+            let name = if uses.names.contains(field) || others.contains(field) {
+                format!("{}_{}", param.name, field)
+            } else {
+                field.clone()
+            };
+            (field.as_str(), name)
+        })
+        .collect();
+
+    let mut patch = Patch::new();
+    let inputs: Vec<Span> = found.sig.inputs.iter().map(Spanned::span).collect();
+    let params: Vec<String> = used
+        .iter()
+        .map(|(field, ty)| format!("{}: {}", renamed[field.as_str()], ty))
+        .collect();
+    replace_in_list(&mut patch, file, &inputs, param.index, &params.join(", "));
+    for (field, span) in &uses.fields {
+        if let Some(name) = renamed.get(field.as_str()) {
+            patch.replace(file, *span, name.clone());
+        }
+    }
+
+    let calls = Calls::collect(krate);
+    let ripple = ripple::ripple(krate, &path).ok_or_else(not_found)?;
+    for site in &ripple.sites {
+        if calls.uses.contains_key(&site.location) {
+            continue;
+        }
+        let refuse = |reason: String| Refusal::CallSite {
+            reason,
+            location: site.location.clone(),
+        };
+        if site.possible {
+            return Err(refuse(format!(
+                "`{}` is called on a receiver of unknown type",
+                site.snippet
+            )));
+        }
+        let Some(call) = calls.found.get(&site.location) else {
+            return Err(refuse(format!(
+                "`{}` is used as a value, not called",
+                site.snippet
+            )));
+        };
+        let index = if call.method {
+            param.index - 1
+        } else {
+            param.index
+        };
+        let Some(arg) = call.args.get(index) else {
+            return Err(refuse(format!("no argument for `{}`", param.name)));
+        };
+        let call_file = &krate.files[call.file];
+        let base = place(arg).map_err(|reason| {
+            refuse(format!(
+                "`{}` {}",
+                call_file.text()[span_range(call_file.text(), arg.span())].trim(),
+                reason
+            ))
+        })?;
+        let base = &call_file.text()[span_range(call_file.text(), base.span())];
+        let args: Vec<String> = used
+            .iter()
+            .map(|(field, ty)| {
+                let borrow = if ty.starts_with('&') { "&" } else { "" };
+                format!("{}{}.{}", borrow, base, field)
+            })
+            .collect();
+        let spans: Vec<Span> = call.args.iter().map(Spanned::span).collect();
+        replace_in_list(&mut patch, call_file, &spans, index, &args.join(", "));
+    }
+
+    remove_unused_import(krate, &found, &param, &calls, &mut patch);
+    Ok(patch)
+}
+
+struct Param<'c> {
+    /// Position in the signature, the receiver included.
+    index: usize,
+    name: String,
+    ty: &'c syn::Type,
+    mutable: bool,
+    target: Target,
+}
+
+/// The trait whose impl block holds `function`.
+fn implemented_trait(krate: &Crate, function: &Function) -> Option<String> {
+    krate.modules[function.module]
+        .items
+        .iter()
+        .find_map(|item| match item {
+            syn::Item::Impl(item) => {
+                let (_, path, _) = item.trait_.as_ref()?;
+                item.items
+                    .iter()
+                    .any(|item| {
+                        matches!(item, syn::ImplItem::Fn(method) if std::ptr::eq(&method.sig, function.sig))
+                    })
+                    .then(|| krate.snippet(function.module, path.span()))
+            }
+            _ => None,
+        })
+}
+
+/// The trait `target` implements that provides `method`, by its impl or
+/// a default body.
+fn trait_providing(krate: &Crate, target: &Target, method: &str) -> Option<String> {
+    krate.modules.iter().enumerate().find_map(|(index, module)| {
+        module.items.iter().find_map(|item| {
+            let syn::Item::Impl(item) = item else {
+                return None;
+            };
+            let (_, path, _) = item.trait_.as_ref()?;
+            let syn::Type::Path(self_ty) = peel(&item.self_ty) else {
+                return None;
+            };
+            if krate.resolve(index, &self_ty.path).as_ref() != Some(target) {
+                return None;
+            }
+            let implemented = item.items.iter().any(
+                |item| matches!(item, syn::ImplItem::Fn(found) if found.sig.ident == method),
+            );
+            let trait_ = krate.resolve(index, path);
+            let defaulted = trait_.as_ref().and_then(|trait_| krate.item(trait_)).is_some_and(
+                |item| {
+                    matches!(item, syn::Item::Trait(item) if item.items.iter().any(
+                        |item| matches!(item, syn::TraitItem::Fn(found) if found.sig.ident == method),
+                    ))
+                },
+            );
+            (implemented || defaulted).then(|| match &trait_ {
+                Some(trait_) => krate.target_path(trait_),
+                None => krate.snippet(index, path.span()),
+            })
+        })
+    })
+}
+
+/// The trait bounds of argument `arg` of the function `callee` names,
+/// when it takes `impl Trait`, `dyn Trait` or a generic parameter.
+fn trait_bound(krate: &Crate, module: usize, callee: &syn::Path, arg: usize) -> Option<String> {
+    let target = krate.resolve(module, callee)?;
+    let (Target::Item { module, .. }, Some(syn::Item::Fn(item))) = (&target, krate.item(&target))
+    else {
+        return None;
+    };
+    let (_, ty) = typed_params(&item.sig).nth(arg)?;
+    match peel(ty) {
+        syn::Type::ImplTrait(ty) => Some(krate.snippet(*module, ty.bounds.span())),
+        syn::Type::TraitObject(ty) => Some(krate.snippet(*module, ty.bounds.span())),
+        syn::Type::Path(ty) => {
+            let ident = ty.path.get_ident()?;
+            let param = item
+                .sig
+                .generics
+                .type_params()
+                .find(|param| param.ident == *ident)?;
+            Some(krate.snippet(*module, param.bounds.span()))
+        }
+        _ => None,
+    }
+}
+
+/// Drops `use` of the struct from the function's module when the narrowed
+/// parameter was its only use there.
+fn remove_unused_import(
+    krate: &Crate,
+    function: &Function,
+    param: &Param,
+    calls: &Calls,
+    patch: &mut Patch,
+) {
+    let syn::Type::Path(ty) = peel(param.ty) else {
+        return;
+    };
+    let Some(ripple) = ripple::ripple(krate, &krate.target_path(&param.target)) else {
+        return;
+    };
+    let module = &krate.modules[function.module];
+    let file = krate.file_of(function.module);
+    let parameter = file.location(ty.path.span());
+    let here: Vec<&Location> = ripple
+        .sites
+        .iter()
+        .filter(|site| site.module == module.path)
+        .map(|site| &site.location)
+        .collect();
+    if here
+        .iter()
+        .any(|location| **location != parameter && !calls.uses.contains_key(location))
+    {
+        return;
+    }
+    for item in &module.items {
+        let syn::Item::Use(item) = item else {
+            continue;
+        };
+        if here.contains(&&file.location(item.tree.span())) && is_single_name(&item.tree) {
+            patch.replace_range(file, line_range(file.text(), item.span()), "");
+        }
+    }
+}
+
+fn is_single_name(tree: &syn::UseTree) -> bool {
+    match tree {
+        syn::UseTree::Path(tree) => is_single_name(&tree.tree),
+        syn::UseTree::Name(_) => true,
+        _ => false,
+    }
+}
+
+/// The struct an argument passes, when it is a variable or a field that
+/// can be read more than once; the reason otherwise.
+fn place(arg: &syn::Expr) -> Result<&syn::Expr, &'static str> {
+    fn is_place(expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Path(expr) => expr.qself.is_none(),
+            syn::Expr::Field(expr) => is_place(&expr.base),
+            _ => false,
+        }
+    }
+    fn behind_call(expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Call(_) | syn::Expr::MethodCall(_) => true,
+            syn::Expr::Field(expr) => behind_call(&expr.base),
+            syn::Expr::Paren(expr) => behind_call(&expr.expr),
+            syn::Expr::Unary(expr) => behind_call(&expr.expr),
+            _ => false,
+        }
+    }
+
+    let mut base = arg;
+    while let syn::Expr::Reference(syn::ExprReference { expr, .. })
+    | syn::Expr::Paren(syn::ExprParen { expr, .. }) = base
+    {
+        base = expr;
+    }
+    if is_place(base) {
+        Ok(base)
+    } else if behind_call(base) {
+        Err("reaches the struct through a call whose result can't be taken apart")
+    } else {
+        Err("is neither a variable nor a field")
+    }
+}
+
+/// How a function body uses one parameter.
+struct Uses<'p> {
+    param: &'p str,
+    /// `param.field` reads, with the span of the whole expression.
+    fields: Vec<(String, Span)>,
+    /// `param.method()` calls, with the span of the method name.
+    methods: Vec<(String, Span)>,
+    /// Other uses, with the callee path and argument index when the
+    /// parameter is passed straight to a function.
+    whole: Vec<(Span, Option<(syn::Path, usize)>)>,
+    /// Locals and paths named by a single identifier.
+    names: BTreeSet<String>,
+}
+
+impl Uses<'_> {
+    fn is_param(&self, expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Path(expr) => expr.qself.is_none() && expr.path.is_ident(self.param),
+            syn::Expr::Paren(expr) => self.is_param(&expr.expr),
+            syn::Expr::Reference(expr) => self.is_param(&expr.expr),
+            _ => false,
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Uses<'_> {
+    fn visit_expr_field(&mut self, expr: &'ast syn::ExprField) {
+        match &expr.member {
+            syn::Member::Named(field) if self.is_param(&expr.base) => {
+                self.fields.push((field.to_string(), expr.span()));
+            }
+            _ => visit::visit_expr_field(self, expr),
+        }
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        if self.is_param(&call.receiver) {
+            self.methods
+                .push((call.method.to_string(), call.method.span()));
+            for arg in &call.args {
+                self.visit_expr(arg);
+            }
+        } else {
+            visit::visit_expr_method_call(self, call);
+        }
+    }
+
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        let syn::Expr::Path(func) = &*call.func else {
+            return visit::visit_expr_call(self, call);
+        };
+        self.visit_expr(&call.func);
+        for (index, arg) in call.args.iter().enumerate() {
+            if self.is_param(arg) {
+                self.whole
+                    .push((arg.span(), Some((func.path.clone(), index))));
+            } else {
+                self.visit_expr(arg);
+            }
+        }
+    }
+
+    fn visit_expr_path(&mut self, expr: &'ast syn::ExprPath) {
+        if expr.qself.is_none() && expr.path.is_ident(self.param) {
+            self.whole.push((expr.span(), None));
+        } else if let Some(ident) = expr.path.get_ident() {
+            self.names.insert(ident.to_string());
+        }
+    }
+
+    fn visit_pat_ident(&mut self, pat: &'ast syn::PatIdent) {
+        self.names.insert(pat.ident.to_string());
+        visit::visit_pat_ident(self, pat);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        for arg in macro_args(mac) {
+            self.visit_expr(&arg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::analysis::SourceFile;
+
+    fn narrow_source(source: &str, function: &str) -> Result<String, Refusal> {
+        let file = SourceFile::parse("lib.rs", source).unwrap();
+        let krate = Crate::from_file("demo", file).unwrap();
+        narrow(&krate, function, None).map(|patch| patch.apply()[Path::new("lib.rs")].clone())
+    }
+
+    #[test]
+    fn send_telemetry_takes_the_id_and_callers_pass_it() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let krate = Crate::load(dir).unwrap();
+        let patch = narrow(&krate, "notification::send_telemetry", None).unwrap();
+        let diff = patch.diff(dir);
+
+        assert!(diff.starts_with(
+            "--- a/src/coupling/stamp_coupling.rs\n+++ b/src/coupling/stamp_coupling.rs\n"
+        ));
+        for line in [
+            "-    use super::telemetry::Record;\n-\n",
+            "-    pub fn send_telemetry(record: &Record) {\n",
+            "-        println!(\"Sending record with {} id\", record.id);\n",
+            "+    pub fn send_telemetry(id: u64) {\n",
+            "+        println!(\"Sending record with {} id\", id);\n",
+            "-        send_telemetry(&record);\n+        send_telemetry(record.id);\n",
+            "-    notification::send_telemetry(&record);\n+    notification::send_telemetry(record.id);\n",
+        ] {
+            assert!(diff.contains(line), "{}\nnot in\n{}", line, diff);
+        }
+        let files = patch.apply();
+        assert_eq!(files.len(), 1);
+        assert!(files.values().all(|text| syn::parse_file(text).is_ok()));
+    }
+
+    #[test]
+    fn fields_keep_their_borrow_and_clashing_names_their_prefix() {
+        let patched = narrow_source(
+            "mod model { pub struct User { pub name: String, pub id: u32, pub email: String } }
+use model::User;
+fn greet(user: &User, id: u32) -> String { format!(\"{} {} {}\", user.name, user.id, id) }
+fn main() { let session = Session { user: make() }; greet(&session.user, 7); }",
+            "greet",
+        )
+        .unwrap();
+        assert!(patched.contains(
+            "fn greet(name: &str, user_id: u32, id: u32) -> String { format!(\"{} {} {}\", name, user_id, id) }"
+        ));
+        assert!(patched.contains("greet(&session.user.name, session.user.id, 7);"));
+        // The parameter was the only use of `User` at the crate root.
+        assert!(!patched.contains("use model::User;"));
+    }
+
+    #[test]
+    fn refuses_what_a_trait_or_a_method_call_hides() {
+        let source = "pub struct User { pub name: String, pub id: u32 }
+trait Describe { fn describe(&self) -> String; }
+impl Describe for User { fn describe(&self) -> String { self.name.clone() } }
+impl User { fn initials(&self) -> String { self.name[..1].to_string() } }
+fn log(item: &impl Describe) {}
+fn shown(user: &User) -> String { format!(\"{} {}\", user.id, user.describe()) }
+fn logged(user: &User) -> u32 { log(user); user.id }
+fn short(user: &User) -> String { format!(\"{} {}\", user.id, user.initials()) }
+fn id(user: &User) -> u32 { user.id }
+fn session() -> User { todo!() }
+fn caller() { id(&session()); }";
+
+        let refusal = |function| narrow_source(source, function).unwrap_err();
+        assert!(matches!(
+            refusal("shown"),
+            Refusal::ThroughTrait { trait_, .. } if trait_ == "crate::Describe"
+        ));
+        assert!(matches!(
+            refusal("logged"),
+            Refusal::ThroughTrait { trait_, .. } if trait_ == "Describe"
+        ));
+        assert!(
+            matches!(refusal("short"), Refusal::BehindMethod { method, .. } if method == "initials")
+        );
+        assert!(matches!(
+            refusal("describe"),
+            Refusal::TraitSignature { trait_, .. } if trait_ == "Describe"
+        ));
+        let Refusal::CallSite { reason, location } = refusal("id") else {
+            panic!()
+        };
+        assert!(reason.starts_with("`&session()` reaches the struct through a call"));
+        assert_eq!(location.line, 11);
+    }
+}
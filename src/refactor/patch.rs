@@ -0,0 +1,364 @@
+//! ./refactor/patch.rs
+//!
+//! Text edits against the source files of a crate, rendered as a unified
+//! diff. Nothing is written back; `git apply` or `patch -p1` does that.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::{LineColumn, Span};
+
+use crate::analysis::SourceFile;
+
+/// Lines of unchanged context around each hunk.
+const CONTEXT: usize = 3;
+
+/// Replacements in one or more files, by byte range of the original text.
+#[derive(Debug, Clone, Default)]
+pub struct Patch {
+    files: BTreeMap<PathBuf, FileEdits>,
+}
+
+#[derive(Debug, Clone)]
+struct FileEdits {
+    original: String,
+    edits: Vec<Edit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Edit {
+    range: Range<usize>,
+    text: String,
+}
+
+impl Patch {
+    pub fn new() -> Self {
+        Patch::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Replaces the source text `span` covers in `file`.
+    pub fn replace(&mut self, file: &SourceFile, span: Span, text: impl Into<String>) {
+        self.replace_range(file, span_range(file.text(), span), text);
+    }
+
+    /// Replaces a byte range of `file`'s text; an empty range inserts.
+    /// The same edit made twice counts once.
+    pub fn replace_range(
+        &mut self,
+        file: &SourceFile,
+        range: Range<usize>,
+        text: impl Into<String>,
+    ) {
+        let edits = self
+            .files
+            .entry(file.path().to_path_buf())
+            .or_insert_with(|| FileEdits {
+                original: file.text().to_string(),
+                edits: Vec::new(),
+            });
+        let edit = Edit {
+            range,
+            text: text.into(),
+        };
+        if !edits.edits.contains(&edit) {
+            edits.edits.push(edit);
+        }
+    }
+
+    /// Every touched file with its edits applied.
+    pub fn apply(&self) -> BTreeMap<PathBuf, String> {
+        self.files
+            .iter()
+            .map(|(path, file)| (path.clone(), file.apply()))
+            .collect()
+    }
+
+    /// The edits of every touched file against its original text, in the
+    /// order they apply.
+    pub fn edits(&self) -> impl Iterator<Item = (&Path, &str, Vec<(Range<usize>, &str)>)> {
+        self.files.iter().map(|(path, file)| {
+            let edits = file
+                .sorted()
+                .into_iter()
+                .map(|edit| (edit.range.clone(), edit.text.as_str()))
+                .collect();
+            (path.as_path(), file.original.as_str(), edits)
+        })
+    }
+
+    /// A unified diff of every touched file, with paths relative to `root`
+    /// behind git's `a/` and `b/` prefixes.
+    pub fn diff(&self, root: impl AsRef<Path>) -> String {
+        let mut out = String::new();
+        for (path, file) in &self.files {
+            let patched = file.apply();
+            if patched == file.original {
+                continue;
+            }
+            let relative = path.strip_prefix(root.as_ref()).unwrap_or(path);
+            let name = relative.display().to_string().replace('\\', "/");
+            out.push_str(&format!("--- a/{}\n+++ b/{}\n", name, name));
+            out.push_str(&unified(&file.original, &patched));
+        }
+        out
+    }
+}
+
+impl FileEdits {
+    fn sorted(&self) -> Vec<&Edit> {
+        let mut edits: Vec<&Edit> = self.edits.iter().collect();
+        // Stable, so insertions at one offset keep the order they were made in.
+        edits.sort_by_key(|edit| (edit.range.start, edit.range.end));
+        edits
+    }
+
+    fn apply(&self) -> String {
+        let edits = self.sorted();
+        let mut out = String::with_capacity(self.original.len());
+        let mut at = 0;
+        for edit in edits {
+            debug_assert!(edit.range.start >= at, "overlapping edits");
+            out.push_str(&self.original[at..edit.range.start.max(at)]);
+            out.push_str(&edit.text);
+            at = at.max(edit.range.end);
+        }
+        out.push_str(&self.original[at..]);
+        out
+    }
+}
+
+/// Byte offset of a line and character column as proc-macro2 reports them.
+pub(crate) fn offset(text: &str, at: LineColumn) -> usize {
+    let start: usize = text
+        .split_inclusive('\n')
+        .take(at.line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    let line = &text[start..];
+    start
+        + line
+            .char_indices()
+            .nth(at.column)
+            .map_or(line.len(), |(index, _)| index)
+}
+
+/// Byte range of `text` a span covers.
+pub(crate) fn span_range(text: &str, span: Span) -> Range<usize> {
+    offset(text, span.start())..offset(text, span.end())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// The hunks turning `old` into `new`, without file headers.
+fn unified(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.split_inclusive('\n').collect();
+    let new: Vec<&str> = new.split_inclusive('\n').collect();
+    let ops = diff_lines(&old, &new);
+
+    // Positions in `old` and `new` before each op.
+    let mut positions = Vec::with_capacity(ops.len() + 1);
+    let (mut i, mut j) = (0, 0);
+    for op in &ops {
+        positions.push((i, j));
+        match op {
+            Op::Equal => (i, j) = (i + 1, j + 1),
+            Op::Delete => i += 1,
+            Op::Insert => j += 1,
+        }
+    }
+    positions.push((i, j));
+
+    let changes: Vec<usize> = (0..ops.len()).filter(|&k| ops[k] != Op::Equal).collect();
+    let mut out = String::new();
+    let mut k = 0;
+    while k < changes.len() {
+        // Changes closer than twice the context share a hunk.
+        let first = changes[k];
+        let mut last = first;
+        while k + 1 < changes.len() && changes[k + 1] - last <= 2 * CONTEXT + 1 {
+            k += 1;
+            last = changes[k];
+        }
+        k += 1;
+        let from = first.saturating_sub(CONTEXT);
+        let to = (last + 1 + CONTEXT).min(ops.len());
+
+        let (old_start, new_start) = positions[from];
+        let (old_end, new_end) = positions[to];
+        let header = |start: usize, len: usize| {
+            if len == 0 {
+                format!("{},0", start)
+            } else {
+                format!("{},{}", start + 1, len)
+            }
+        };
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            header(old_start, old_end - old_start),
+            header(new_start, new_end - new_start)
+        ));
+        for (op, &(i, j)) in ops[from..to].iter().zip(&positions[from..to]) {
+            let (prefix, line) = match op {
+                Op::Equal => (' ', old[i]),
+                Op::Delete => ('-', old[i]),
+                Op::Insert => ('+', new[j]),
+            };
+            out.push(prefix);
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+/// The shortest edit script between two sequences of lines (Myers).
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<Op> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut ops = vec![Op::Equal; prefix];
+    ops.extend(myers(a, b));
+    ops.extend(vec![Op::Equal; suffix]);
+    ops
+}
+
+fn myers(a: &[&str], b: &[&str]) -> Vec<Op> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m) as usize;
+    if max == 0 {
+        return Vec::new();
+    }
+    let index = |k: isize| (k + max as isize) as usize;
+
+    // Furthest x reached on each diagonal k = x - y, kept for every d.
+    let mut v = vec![0isize; 2 * max + 2];
+    let mut trace = Vec::new();
+    'search: for d in 0..=max as isize {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+                v[index(k + 1)]
+            } else {
+                v[index(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[index(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let previous = if k == -d || (k != d && v[index(k - 1)] < v[index(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let previous_x = v[index(previous)];
+        let previous_y = previous_x - previous;
+        while x > previous_x && y > previous_y {
+            ops.push(Op::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            ops.push(if x == previous_x {
+                Op::Insert
+            } else {
+                Op::Delete
+            });
+        }
+        (x, y) = (previous_x, previous_y);
+    }
+    ops.reverse();
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diff(old: &str, new: &str) -> String {
+        unified(old, new)
+    }
+
+    #[test]
+    fn hunks_carry_three_lines_of_context() {
+        let old: String = (1..=20).map(|n| format!("{}\n", n)).collect();
+        let new = old.replace("10\n", "ten\n").replace("12\n", "");
+        assert_eq!(
+            diff(&old, &new),
+            "@@ -7,9 +7,8 @@\n 7\n 8\n 9\n-10\n+ten\n 11\n-12\n 13\n 14\n 15\n"
+        );
+
+        let new: String = (1..=20)
+            .map(|n| match n {
+                2 => "two\n".to_string(),
+                19 => "nineteen\n".to_string(),
+                n => format!("{}\n", n),
+            })
+            .collect();
+        let hunks = diff(&old, &new);
+        assert!(hunks.starts_with("@@ -1,5 +1,5 @@\n 1\n-2\n+two\n"));
+        assert!(hunks.contains("@@ -16,5 +16,5 @@\n"));
+    }
+
+    #[test]
+    fn edits_apply_by_span_and_render_with_paths() {
+        let file =
+            SourceFile::parse("/work/src/lib.rs", "fn main() {\n    let x = 1;\n}\n").unwrap();
+        let syn::Item::Fn(main) = &file.syntax().items[0] else {
+            panic!()
+        };
+        let mut patch = Patch::new();
+        patch.replace(&file, main.sig.ident.span(), "start");
+        patch.replace(&file, main.sig.ident.span(), "start");
+        patch.replace_range(&file, 0..0, "// entry\n");
+
+        assert_eq!(
+            patch.apply()[Path::new("/work/src/lib.rs")],
+            "// entry\nfn start() {\n    let x = 1;\n}\n"
+        );
+        assert_eq!(
+            patch.diff("/work"),
+            "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,3 +1,4 @@\n-fn main() {\n+// entry\n+fn start() {\n     let x = 1;\n }\n"
+        );
+    }
+
+    #[test]
+    fn offsets_count_characters_not_bytes() {
+        let text = "é = 1;\nlet ü = 2;\n";
+        let at = LineColumn { line: 2, column: 6 };
+        assert_eq!(&text[offset(text, at)..], "= 2;\n");
+    }
+}
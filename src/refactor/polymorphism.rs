@@ -0,0 +1,973 @@
+//! ./refactor/polymorphism.rs
+//!
+//! Replacing a control flag with polymorphism: the enum a function
+//! `match`es on becomes a trait, each variant a unit struct implementing
+//! it with its arm as the method body, and callers pass the implementation.
+//! The new modules go next to the function's module, as in the hand-written
+//! solution: `formatter::format_data(data, Format::Html)` becomes
+//! `html_formatter::HtmlFormatter.format(data)`.
+//!
+//! A caller that only passes on a flag it was given takes `&impl Trait`
+//! instead, and its own callers are rewritten in turn, which is how
+//! `report_generator_v2` came about by hand.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use proc_macro2::Span;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+use super::Patch;
+use super::patch::span_range;
+use super::sites::{
+    Call, Calls, find_function, function_at, indentation, item_lines, line_range, reindent,
+    relative_path, use_without,
+};
+use crate::analysis::control::{implementation_name, trait_name};
+use crate::analysis::dependencies::{control_params, peel};
+use crate::analysis::modules::{Crate, Target, flatten_use, item_name};
+use crate::analysis::ripple::{self, Site};
+use crate::analysis::{Location, SourceFile, macro_args};
+
+/// Why a flag was left as it is.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Refusal {
+    /// No function outside test modules has that path.
+    NotFound(String),
+    /// The function doesn't branch on a flag parameter.
+    NoFlag { function: String },
+    /// A function or `match` that doesn't map onto one method per variant.
+    Shape { reason: String, location: Location },
+    /// The enum is needed for more than picking the behaviour.
+    StillUsed { snippet: String, location: Location },
+    /// A generated module would clash with an item next to it.
+    NameTaken { name: String, location: Location },
+    /// A use of the function, or of a caller passing the flag on, that
+    /// can't be rewritten.
+    CallSite { reason: String, location: Location },
+}
+
+impl fmt::Display for Refusal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Refusal::NotFound(function) => write!(f, "`{}` names no function", function),
+            Refusal::NoFlag { function } => {
+                write!(f, "`{}` doesn't branch on a flag parameter", function)
+            }
+            Refusal::Shape { reason, location } => write!(f, "{}: {}", location, reason),
+            Refusal::StillUsed { snippet, location } => write!(
+                f,
+                "{}: `{}` still needs the enum after the rewrite",
+                location, snippet
+            ),
+            Refusal::NameTaken { name, location } => {
+                write!(f, "{}: the module already has a `{}`", location, name)
+            }
+            Refusal::CallSite { reason, location } => write!(f, "{}: {}", location, reason),
+        }
+    }
+}
+
+impl std::error::Error for Refusal {}
+
+/// Replaces the enum `function` matches on with a trait and one unit
+/// struct per variant, in modules next to the function's own, and
+/// rewrites every call. The function's module stays, empty if the function
+/// and the enum were all it held. A function at the top of a file has no
+/// module beside it there, so the new modules take its place instead. The function is named by its path or a suffix of
+/// it, like `formatter::format_data`.
+pub fn replace_conditional(krate: &Crate, function: &str) -> Result<Patch, Refusal> {
+    let found =
+        find_function(krate, function).ok_or_else(|| Refusal::NotFound(function.to_string()))?;
+    let path = krate.function_path(&found);
+    let module = &krate.modules[found.module];
+    let file = krate.file_of(found.module);
+    let text = file.text();
+    let shape = |reason: String, span: Span| Refusal::Shape {
+        reason,
+        location: file.location(span),
+    };
+    let item = module
+        .items
+        .iter()
+        .find_map(|item| match item {
+            syn::Item::Fn(item) if std::ptr::eq(&item.sig, found.sig) => Some(item),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            shape(
+                "only free functions become trait methods".into(),
+                found.sig.span(),
+            )
+        })?;
+
+    let (flag, branch) = control_params(krate, found.module, found.sig, found.block)
+        .into_iter()
+        .next()
+        .ok_or_else(|| Refusal::NoFlag {
+            function: path.clone(),
+        })?;
+    let (flag_index, flag_ty) = found
+        .sig
+        .inputs
+        .iter()
+        .enumerate()
+        .find_map(|(index, input)| match input {
+            syn::FnArg::Typed(typed)
+                if matches!(&*typed.pat, syn::Pat::Ident(pat) if pat.ident == flag) =>
+            {
+                Some((index, &*typed.ty))
+            }
+            _ => None,
+        })
+        .expect("control parameters are typed");
+    let target = match peel(flag_ty) {
+        syn::Type::Path(ty) => krate.resolve(found.module, &ty.path),
+        _ => None,
+    };
+    let (
+        Some(
+            target @ Target::Item {
+                module: enum_module,
+                ..
+            },
+        ),
+        syn::Expr::Match(matched),
+    ) = (target, branch)
+    else {
+        return Err(shape(
+            format!("`{}` is a `bool`, there is no enum to replace", flag),
+            branch.span(),
+        ));
+    };
+    let Some(syn::Item::Enum(flag_enum)) = krate.item(&target) else {
+        return Err(shape(format!("`{}` is not an enum", flag), flag_ty.span()));
+    };
+    if let Some(variant) = flag_enum
+        .variants
+        .iter()
+        .find(|variant| !matches!(variant.fields, syn::Fields::Unit))
+    {
+        return Err(Refusal::Shape {
+            reason: format!("`{}` carries data", variant.ident),
+            location: krate.location(enum_module, variant.span()),
+        });
+    }
+    let is_tail = matches!(found.block.stmts.last(), Some(syn::Stmt::Expr(expr, None)) if std::ptr::eq(expr, branch));
+    if !is_tail {
+        return Err(shape(
+            "the `match` is not what the function returns".into(),
+            matched.match_token.span,
+        ));
+    }
+
+    let variants: Vec<String> = flag_enum
+        .variants
+        .iter()
+        .map(|variant| variant.ident.to_string())
+        .collect();
+    let mut arms: BTreeMap<&str, &syn::Expr> = BTreeMap::new();
+    for arm in &matched.arms {
+        if let Some((_, guard)) = &arm.guard {
+            return Err(shape("a guarded arm".into(), guard.span()));
+        }
+        let covered = arm_variants(&arm.pat, &variants).ok_or_else(|| {
+            shape(
+                format!(
+                    "`{}` is not a variant",
+                    krate.snippet(found.module, arm.pat.span())
+                ),
+                arm.pat.span(),
+            )
+        })?;
+        if mentions(&arm.body, &flag) {
+            return Err(shape(
+                format!("the arm uses `{}` itself", flag),
+                arm.body.span(),
+            ));
+        }
+        for variant in covered {
+            arms.entry(variant).or_insert(&arm.body);
+        }
+    }
+
+    // Names follow the hand-written solutions: `format_data` gives
+    // `traits::Formatter::format` and `html_formatter::HtmlFormatter`.
+    let name = found.sig.ident.to_string();
+    let method = name.split('_').next().unwrap_or(&name).to_string();
+    let trait_ = trait_name(&name);
+    let implementations: Vec<(String, String)> = variants
+        .iter()
+        .map(|variant| {
+            let implementation = implementation_name(variant, &trait_);
+            (snake_case(&implementation), implementation)
+        })
+        .collect();
+    let implementation_of = |variant: &str| {
+        let index = variants.iter().position(|v| v == variant).unwrap();
+        format!("{}::{}", implementations[index].0, implementations[index].1)
+    };
+
+    // The module the new ones go in, and the `mod` item they follow.
+    let (home, after) = match module.path.rsplit_once("::") {
+        Some((parent, name))
+            if krate.file_root(module.file).map(|root| &root.path) != Some(&module.path) =>
+        {
+            let parent = krate.index_of(parent).expect("a module's parent is loaded");
+            (parent, item_named(&krate.modules[parent].items, name))
+        }
+        _ => (found.module, None),
+    };
+    let home_path = &krate.modules[home].path;
+    for name in
+        std::iter::once("traits").chain(implementations.iter().map(|(name, _)| name.as_str()))
+    {
+        if let Some(taken) = item_named(&krate.modules[home].items, name) {
+            return Err(Refusal::NameTaken {
+                name: name.to_string(),
+                location: krate.location(home, taken.span()),
+            });
+        }
+    }
+
+    let vis = match &item.vis {
+        syn::Visibility::Inherited => String::new(),
+        vis => format!("{} ", krate.snippet(found.module, vis.span())),
+    };
+    let mut inputs = vec!["&self".to_string()];
+    inputs.extend(
+        found
+            .sig
+            .inputs
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != flag_index)
+            .map(|(_, input)| krate.snippet(found.module, input.span())),
+    );
+    let generics = &found.sig.generics;
+    let signature = format!(
+        "fn {}{}({}){}{}",
+        method,
+        if generics.params.is_empty() {
+            String::new()
+        } else {
+            krate.snippet(found.module, generics.span())
+        },
+        inputs.join(", "),
+        match &found.sig.output {
+            syn::ReturnType::Default => String::new(),
+            output => format!(" {}", krate.snippet(found.module, output.span())),
+        },
+        generics
+            .where_clause
+            .as_ref()
+            .map(|clause| format!(" {}", krate.snippet(found.module, clause.span())))
+            .unwrap_or_default(),
+    );
+
+    // Statements before the `match` run in every implementation.
+    let open = span_range(text, found.block.brace_token.span.open()).end;
+    let before = &text[open..span_range(text, matched.span()).start];
+    let indent = indentation(text, after.map_or(item.span(), |after| after.span()));
+    let body_indent = format!("{}            ", indent);
+    let mut generated = format!(
+        "{i}{vis}mod traits {{\n{i}    {vis}trait {t} {{\n{i}        {s};\n{i}    }}\n{i}}}\n",
+        i = indent,
+        vis = vis,
+        t = trait_,
+        s = signature
+    );
+    for (variant, (module_name, implementation)) in variants.iter().zip(&implementations) {
+        let mut body = reindent(before, &body_indent);
+        body.push_str(&reindent(
+            arm_body(text, arms[variant.as_str()]),
+            &body_indent,
+        ));
+        generated.push_str(&format!(
+            "\n{i}{vis}mod {m} {{\n{i}    use super::traits::{t};\n\n{i}    {vis}struct {n};\n\n{i}    impl {t} for {n} {{\n{i}        {s} {{\n{b}{i}        }}\n{i}    }}\n{i}}}\n",
+            i = indent,
+            vis = vis,
+            m = module_name,
+            t = trait_,
+            n = implementation,
+            s = signature,
+            b = body
+        ));
+    }
+
+    let mut patch = Patch::new();
+    match after {
+        Some(after) => {
+            let mut range = item_lines(text, item.span());
+            if text[range.end..].starts_with('\n') && text[..range.start].ends_with("{\n") {
+                range.end += 1;
+            }
+            patch.replace_range(file, range, "");
+            let end = line_range(text, after.span()).end;
+            patch.replace_range(file, end..end, format!("\n{}", generated));
+        }
+        None => patch.replace_range(file, item_lines(text, item.span()), generated),
+    }
+    let enum_file = krate.file_of(enum_module);
+    let mut range = item_lines(enum_file.text(), flag_enum.span());
+    if enum_file.text()[range.end..].starts_with('\n') {
+        range.end += 1;
+    }
+    patch.replace_range(enum_file, range, "");
+
+    // Every mention of the enum or a variant, to account for below.
+    let enum_path = krate.target_path(&target);
+    let mut variant_at: BTreeMap<Location, String> = BTreeMap::new();
+    let mut enum_sites: Vec<Site> = Vec::new();
+    for variant in &variants {
+        let sites = ripple::ripple(krate, &format!("{}::{}", enum_path, variant))
+            .map(|ripple| ripple.sites)
+            .unwrap_or_default();
+        for site in sites {
+            variant_at.insert(site.location.clone(), variant.clone());
+            enum_sites.push(site);
+        }
+    }
+    enum_sites.extend(
+        ripple::ripple(krate, &enum_path)
+            .map(|ripple| ripple.sites)
+            .unwrap_or_default(),
+    );
+
+    let calls = Calls::collect(krate);
+    let mut rewrite = Rewrite {
+        krate,
+        calls: &calls,
+        variant_at: &variant_at,
+        flag_enum: &target,
+        handled: BTreeSet::new(),
+        drops: BTreeMap::new(),
+        needs_trait: BTreeSet::new(),
+        passes: BTreeMap::new(),
+        queue: Vec::new(),
+    };
+    for site in ripple::ripple(krate, &path)
+        .map(|ripple| ripple.sites)
+        .unwrap_or_default()
+    {
+        if calls.uses.contains_key(&site.location) {
+            rewrite.drop_import(&site.location, &name);
+            continue;
+        }
+        let call = rewrite.direct_call(&site)?;
+        let caller_file = &krate.files[call.file];
+        let arg = call.args.get(flag_index).ok_or_else(|| Refusal::CallSite {
+            reason: format!("no argument for `{}`", flag),
+            location: site.location.clone(),
+        })?;
+        let base = module_prefix(krate, &site.module, home_path);
+        let receiver = match rewrite.flag_arg(caller_file, arg) {
+            FlagArg::Variant(variant) => {
+                rewrite.needs_trait.extend(krate.index_of(&site.module));
+                format!("{}{}", base, implementation_of(&variant))
+            }
+            FlagArg::Name(name) => {
+                rewrite.forward(&site, &name, &base)?;
+                name
+            }
+            FlagArg::Other => return Err(rewrite.not_a_flag(caller_file, arg, &site)),
+        };
+        let others: Vec<&str> = call
+            .args
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != flag_index)
+            .map(|(_, arg)| source(caller_file, arg.span()))
+            .collect();
+        patch.replace(
+            caller_file,
+            call.span,
+            format!("{}.{}({})", receiver, method, others.join(", ")),
+        );
+    }
+
+    // Callers passing the flag on take the trait, and their callers pass
+    // an implementation or their own flag parameter.
+    let mut done = BTreeSet::new();
+    while let Some((caller, parameter, base)) = rewrite.queue.pop() {
+        if !done.insert(caller.clone()) {
+            continue;
+        }
+        let function = function_at(krate, &caller).expect("forwarded from a known function");
+        let function_file = krate.file_of(function.module);
+        let (index, ty) = function
+            .sig
+            .inputs
+            .iter()
+            .enumerate()
+            .find_map(|(index, input)| match input {
+                syn::FnArg::Typed(typed)
+                    if matches!(&*typed.pat, syn::Pat::Ident(pat) if pat.ident == parameter) =>
+                {
+                    Some((index, &*typed.ty))
+                }
+                _ => None,
+            })
+            .expect("forwarded parameters are typed");
+        if count_uses(function.block, &parameter) != rewrite.passes[&caller] {
+            return Err(Refusal::StillUsed {
+                snippet: parameter,
+                location: function_file.location(ty.span()),
+            });
+        }
+        if let syn::Type::Path(path) = peel(ty) {
+            rewrite
+                .handled
+                .insert(function_file.location(path.path.span()));
+        }
+        patch.replace(
+            function_file,
+            ty.span(),
+            format!("&impl {}traits::{}", base, trait_),
+        );
+
+        for site in ripple::ripple(krate, &caller)
+            .map(|ripple| ripple.sites)
+            .unwrap_or_default()
+        {
+            if calls.uses.contains_key(&site.location) {
+                continue;
+            }
+            let call = rewrite.direct_call(&site)?;
+            let caller_file = &krate.files[call.file];
+            let index = if call.method { index - 1 } else { index };
+            let Some(arg) = call.args.get(index) else {
+                return Err(Refusal::CallSite {
+                    reason: format!("no argument for `{}`", parameter),
+                    location: site.location.clone(),
+                });
+            };
+            let base = module_prefix(krate, &site.module, home_path);
+            match rewrite.flag_arg(caller_file, arg) {
+                FlagArg::Variant(variant) => patch.replace(
+                    caller_file,
+                    arg.span(),
+                    format!("&{}{}", base, implementation_of(&variant)),
+                ),
+                FlagArg::Name(name) => rewrite.forward(&site, &name, &base)?,
+                FlagArg::Other => return Err(rewrite.not_a_flag(caller_file, arg, &site)),
+            }
+        }
+    }
+
+    let function_lines = {
+        let span = item.span();
+        (span.start().line, span.end().line)
+    };
+    let enum_name = flag_enum.ident.to_string();
+    for site in &enum_sites {
+        let inside = site.location.file == file.path()
+            && (function_lines.0..=function_lines.1).contains(&site.location.line);
+        if inside || rewrite.handled.contains(&site.location) {
+            continue;
+        }
+        if calls.uses.contains_key(&site.location) && !variant_at.contains_key(&site.location) {
+            rewrite.drop_import(&site.location, &enum_name);
+            continue;
+        }
+        return Err(Refusal::StillUsed {
+            snippet: site.snippet.clone(),
+            location: site.location.clone(),
+        });
+    }
+
+    let trait_path = format!("{}::traits::{}", home_path, trait_);
+    for index in &rewrite.needs_trait {
+        let import = relative_path(&krate.modules[*index].path, &trait_path);
+        add_import(
+            krate,
+            *index,
+            &import,
+            &mut rewrite.drops,
+            &calls,
+            &mut patch,
+        );
+    }
+    for (location, names) in &rewrite.drops {
+        let (file_index, item) = &calls.uses[location];
+        let use_file = &krate.files[*file_index];
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        match use_without(&item.tree, &names) {
+            Some(tree) => patch.replace(use_file, item.tree.span(), tree),
+            None => patch.replace_range(use_file, line_range(use_file.text(), item.span()), ""),
+        }
+    }
+    Ok(patch)
+}
+
+/// What a call passes for the flag.
+enum FlagArg {
+    Variant(String),
+    /// A plain name, a parameter if the caller is to pass it on.
+    Name(String),
+    Other,
+}
+
+/// State shared while walking from the function out to its callers.
+struct Rewrite<'a> {
+    krate: &'a Crate,
+    calls: &'a Calls,
+    variant_at: &'a BTreeMap<Location, String>,
+    flag_enum: &'a Target,
+    /// Mentions of the enum that the rewrite replaces.
+    handled: BTreeSet<Location>,
+    /// Names to drop from `use` declarations, by the tree's location.
+    drops: BTreeMap<Location, Vec<String>>,
+    /// Modules calling a method on an implementation, by index.
+    needs_trait: BTreeSet<usize>,
+    /// How often each forwarding function passes its flag on.
+    passes: BTreeMap<String, usize>,
+    /// Forwarding functions with their flag parameter and the prefix
+    /// through which they reach the trait's module.
+    queue: Vec<(String, String, String)>,
+}
+
+impl<'a> Rewrite<'a> {
+    fn direct_call(&self, site: &Site) -> Result<&'a Call, Refusal> {
+        let refuse = |reason: String| Refusal::CallSite {
+            reason,
+            location: site.location.clone(),
+        };
+        if site.possible {
+            return Err(refuse(format!(
+                "`{}` is called on a receiver of unknown type",
+                site.snippet
+            )));
+        }
+        self.calls
+            .found
+            .get(&site.location)
+            .ok_or_else(|| refuse(format!("`{}` is used as a value, not called", site.snippet)))
+    }
+
+    fn flag_arg(&mut self, file: &SourceFile, arg: &syn::Expr) -> FlagArg {
+        let mut arg = arg;
+        while let syn::Expr::Reference(syn::ExprReference { expr, .. })
+        | syn::Expr::Paren(syn::ExprParen { expr, .. }) = arg
+        {
+            arg = expr;
+        }
+        let syn::Expr::Path(expr) = arg else {
+            return FlagArg::Other;
+        };
+        let location = file.location(expr.path.span());
+        if let Some(variant) = self.variant_at.get(&location) {
+            self.handled.insert(location);
+            return FlagArg::Variant(variant.clone());
+        }
+        match expr.path.get_ident() {
+            Some(ident) => FlagArg::Name(ident.to_string()),
+            None => FlagArg::Other,
+        }
+    }
+
+    /// Marks the function around `site` as passing its flag parameter
+    /// `name` on, refusing when `name` is anything else.
+    fn forward(&mut self, site: &Site, name: &str, base: &str) -> Result<(), Refusal> {
+        let refuse = || Refusal::CallSite {
+            reason: format!("`{}` is not a flag parameter of the caller", name),
+            location: site.location.clone(),
+        };
+        let caller = site.function.as_ref().ok_or_else(refuse)?;
+        let function = function_at(self.krate, caller).ok_or_else(refuse)?;
+        let is_flag = function.sig.inputs.iter().any(|input| match input {
+            syn::FnArg::Typed(typed) => {
+                matches!(&*typed.pat, syn::Pat::Ident(pat) if pat.ident == name)
+                    && matches!(peel(&typed.ty), syn::Type::Path(ty)
+                        if self.krate.resolve(function.module, &ty.path).as_ref() == Some(self.flag_enum))
+            }
+            syn::FnArg::Receiver(_) => false,
+        });
+        if !is_flag {
+            return Err(refuse());
+        }
+        *self.passes.entry(caller.clone()).or_default() += 1;
+        self.queue
+            .push((caller.clone(), name.to_string(), base.to_string()));
+        Ok(())
+    }
+
+    fn not_a_flag(&self, file: &SourceFile, arg: &syn::Expr, site: &Site) -> Refusal {
+        Refusal::CallSite {
+            reason: format!(
+                "`{}` is neither a variant nor a parameter passed on",
+                source(file, arg.span())
+            ),
+            location: site.location.clone(),
+        }
+    }
+
+    fn drop_import(&mut self, location: &Location, name: &str) {
+        self.drops
+            .entry(location.clone())
+            .or_default()
+            .push(name.to_string());
+    }
+}
+
+/// Imports `path` into a module, next to its last `use` or above its first
+/// item. A `use` that loses every name is reused for it.
+fn add_import(
+    krate: &Crate,
+    module: usize,
+    path: &str,
+    drops: &mut BTreeMap<Location, Vec<String>>,
+    calls: &Calls,
+    patch: &mut Patch,
+) {
+    let file = krate.file_of(module);
+    let text = file.text();
+    let items = &krate.modules[module].items;
+    let last_use = items.iter().rev().find_map(|item| match item {
+        syn::Item::Use(item) => Some(item),
+        _ => None,
+    });
+    match last_use {
+        Some(item) => {
+            let location = file.location(item.tree.span());
+            let emptied = drops.get(&location).is_some_and(|names| {
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                calls.uses.contains_key(&location) && use_without(&item.tree, &names).is_none()
+            });
+            if emptied {
+                drops.remove(&location);
+                patch.replace(file, item.tree.span(), path);
+            } else {
+                let end = span_range(text, item.span()).end;
+                let indent = indentation(text, item.span());
+                patch.replace_range(file, end..end, format!("\n{}use {};", indent, path));
+            }
+        }
+        None => {
+            let Some(first) = items.first() else {
+                return;
+            };
+            let start = item_lines(text, first.span()).start;
+            let indent = indentation(text, first.span());
+            patch.replace_range(file, start..start, format!("{}use {};\n\n", indent, path));
+        }
+    }
+}
+
+/// `path::` to the module `to` as written from `from`, empty inside it
+/// or when `from` glob-imports it.
+fn module_prefix(krate: &Crate, from: &str, to: &str) -> String {
+    let Some(index) = krate.index_of(from) else {
+        return format!("{}::", to);
+    };
+    let mut globs = Vec::new();
+    for item in &krate.modules[index].items {
+        if let syn::Item::Use(item) = item {
+            flatten_use(
+                &item.tree,
+                &mut Vec::new(),
+                &mut BTreeMap::new(),
+                &mut globs,
+            );
+        }
+    }
+    let glob_imported = globs.iter().any(|glob| {
+        krate.resolve_segments_from(index, glob) == krate.index_of(to).map(Target::Module)
+    });
+    match relative_path(from, to) {
+        _ if glob_imported => String::new(),
+        path if path.is_empty() => path,
+        path => format!("{}::", path),
+    }
+}
+
+/// The item, `mod` included, that `name` refers to among `items`.
+fn item_named<'i>(items: &'i [syn::Item], name: &str) -> Option<&'i syn::Item> {
+    items.iter().find(|item| match item {
+        syn::Item::Mod(item) => item.ident == name,
+        item => item_name(item).is_some_and(|(ident, _)| ident == name),
+    })
+}
+
+/// Variants an arm covers, `None` for patterns that bind or destructure.
+fn arm_variants<'v>(pat: &syn::Pat, variants: &'v [String]) -> Option<Vec<&'v str>> {
+    let named = |ident: &syn::Ident| {
+        variants
+            .iter()
+            .find(|variant| ident == variant.as_str())
+            .map(|variant| vec![variant.as_str()])
+    };
+    match pat {
+        syn::Pat::Path(pat) => named(&pat.path.segments.last()?.ident),
+        // A variant brought in by `use Enum::*`.
+        syn::Pat::Ident(pat) if pat.subpat.is_none() => named(&pat.ident),
+        syn::Pat::Or(pat) => {
+            let mut covered = Vec::new();
+            for case in &pat.cases {
+                covered.extend(arm_variants(case, variants)?);
+            }
+            Some(covered)
+        }
+        syn::Pat::Wild(_) => Some(variants.iter().map(String::as_str).collect()),
+        _ => None,
+    }
+}
+
+/// The source of an arm's body, without the braces of a block.
+fn arm_body<'t>(text: &'t str, body: &syn::Expr) -> &'t str {
+    match body {
+        syn::Expr::Block(block) if block.attrs.is_empty() && block.label.is_none() => {
+            let braces = &block.block.brace_token.span;
+            &text[span_range(text, braces.open()).end..span_range(text, braces.close()).start]
+        }
+        body => source_text(text, body.span()),
+    }
+}
+
+fn source(file: &SourceFile, span: Span) -> &str {
+    source_text(file.text(), span)
+}
+
+fn source_text(text: &str, span: Span) -> &str {
+    &text[span_range(text, span)]
+}
+
+/// Whether `name` is read as a plain path anywhere in `expr`.
+fn mentions(expr: &syn::Expr, name: &str) -> bool {
+    let mut count = Count { name, count: 0 };
+    count.visit_expr(expr);
+    count.count > 0
+}
+
+/// How often `name` is read as a plain path in `block`.
+fn count_uses(block: &syn::Block, name: &str) -> usize {
+    let mut count = Count { name, count: 0 };
+    count.visit_block(block);
+    count.count
+}
+
+struct Count<'n> {
+    name: &'n str,
+    count: usize,
+}
+
+impl<'ast> Visit<'ast> for Count<'_> {
+    fn visit_expr_path(&mut self, expr: &'ast syn::ExprPath) {
+        if expr.qself.is_none() && expr.path.is_ident(self.name) {
+            self.count += 1;
+        }
+        visit::visit_expr_path(self, expr);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        for arg in macro_args(mac) {
+            self.visit_expr(&arg);
+        }
+    }
+}
+
+/// `PlainTextFormatter` as a module name, `plain_text_formatter`.
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (index, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if index > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use quote::ToTokens;
+
+    use super::*;
+
+    /// Item kinds and names of a module, to compare generated modules
+    /// with the hand-written ones regardless of their bodies.
+    fn outline(items: &[syn::Item]) -> Vec<String> {
+        items
+            .iter()
+            .map(|item| match item {
+                syn::Item::Use(item) => item.to_token_stream().to_string(),
+                syn::Item::Struct(item) => format!("struct {}", item.ident),
+                syn::Item::Trait(item) => format!("trait {}", item.ident),
+                syn::Item::Impl(item) => format!(
+                    "impl {} for {}",
+                    item.trait_.as_ref().unwrap().1.to_token_stream(),
+                    item.self_ty.to_token_stream()
+                ),
+                item => item.to_token_stream().to_string(),
+            })
+            .collect()
+    }
+
+    fn module<'f>(items: &'f [syn::Item], name: &str) -> &'f [syn::Item] {
+        items
+            .iter()
+            .find_map(|item| match item {
+                syn::Item::Mod(item) if item.ident == name => Some(&item.content.as_ref()?.1[..]),
+                _ => None,
+            })
+            .unwrap_or_else(|| panic!("no module {}", name))
+    }
+
+    #[test]
+    fn format_data_becomes_the_formatter_solution() {
+        let krate = Crate::load(env!("CARGO_MANIFEST_DIR")).unwrap();
+        let index = krate.index_of("crate::coupling::control_coupling").unwrap();
+        let text = krate.file_of(index).text();
+
+        // In the crate the hand-written solution is already in the way.
+        let Err(Refusal::NameTaken { name, location }) =
+            replace_conditional(&krate, "formatter::format_data")
+        else {
+            panic!("not refused");
+        };
+        assert_eq!(name, "traits");
+        assert_eq!(text.lines().nth(location.line - 1), Some("mod traits {"));
+
+        // Without it, the generated modules take its place.
+        let (problem, _) = text.split_once("/// Solution").unwrap();
+        let patched = rewrite(problem, "formatter::format_data").unwrap();
+        let solution = syn::parse_file(text).unwrap();
+        let generated = syn::parse_file(&patched).unwrap();
+        for name in ["traits", "plain_text_formatter", "html_formatter"] {
+            assert_eq!(
+                outline(module(&generated.items, name)),
+                outline(module(&solution.items, name)),
+                "{}",
+                name
+            );
+        }
+        assert_eq!(
+            outline(module(&generated.items, "xml_formatter")),
+            vec![
+                "use super :: traits :: Formatter ;",
+                "struct XmlFormatter",
+                "impl Formatter for XmlFormatter"
+            ]
+        );
+        assert!(module(&generated.items, "formatter").is_empty());
+
+        for line in [
+            "}\n\npub mod traits {\n    pub trait Formatter {\n        fn format(&self, data: String) -> String;\n",
+            "    impl Formatter for XmlFormatter {\n        fn format(&self, data: String) -> String {\n            // Formatting for plain text\n",
+            "    pub fn generate_report(format_type: &impl crate::traits::Formatter) -> String {\n",
+            "        format_type.format(data)\n",
+            "            let plain_report = generate_report(&crate::plain_text_formatter::PlainTextFormatter);\n",
+        ] {
+            assert!(patched.contains(line), "{}\nnot in\n{}", line, patched);
+        }
+        assert!(!patched.contains("Format::"));
+    }
+
+    fn rewrite(source: &str, function: &str) -> Result<String, Refusal> {
+        let file = SourceFile::parse("lib.rs", source).unwrap();
+        let krate = Crate::from_file("demo", file).unwrap();
+        replace_conditional(&krate, function)
+            .map(|patch| patch.apply()[Path::new("lib.rs")].clone())
+    }
+
+    #[test]
+    fn direct_calls_get_an_implementation_and_the_trait() {
+        let patched = rewrite(
+            "mod shapes {
+    pub enum Shape {
+        Circle,
+        Square,
+    }
+
+    pub fn draw_shape(size: u32, shape: Shape) -> String {
+        let size = size * 2;
+        match shape {
+            Shape::Circle => format!(\"o{}\", size),
+            Shape::Square | _ => format!(\"#{}\", size),
+        }
+    }
+}
+
+mod canvas {
+    use super::shapes::{draw_shape, Shape};
+
+    pub fn paint() -> String {
+        draw_shape(2, Shape::Circle)
+    }
+}
+",
+            "draw_shape",
+        )
+        .unwrap();
+
+        assert!(patched.starts_with("mod shapes {\n}\n\npub mod traits {\n"));
+        assert!(patched.contains(
+            "pub mod square_drawer {
+    use super::traits::Drawer;
+
+    pub struct SquareDrawer;
+
+    impl Drawer for SquareDrawer {
+        fn draw(&self, size: u32) -> String {
+            let size = size * 2;
+            format!(\"#{}\", size)
+        }
+    }
+}
+
+mod canvas {
+    use crate::traits::Drawer;
+
+    pub fn paint() -> String {
+        crate::circle_drawer::CircleDrawer.draw(2)
+    }
+}"
+        ));
+        assert!(syn::parse_file(&patched).is_ok());
+    }
+
+    #[test]
+    fn refuses_flags_that_are_not_only_passed_on() {
+        let source = "pub enum Mode { Fast, Safe }
+pub fn run(mode: Mode) -> u8 { match mode { Mode::Fast => 1, Mode::Safe => 2 } }
+pub fn pick(flag: bool) -> u8 { if flag { 1 } else { 2 } }
+pub fn relay(mode: Mode) -> u8 { let again = matches!(mode, Mode::Fast); run(mode) }
+pub fn stored() -> u8 { let mode = Mode::Safe; run(mode) }";
+
+        let refusal = |function| rewrite(source, function).unwrap_err();
+        assert!(
+            matches!(refusal("pick"), Refusal::Shape { reason, .. } if reason.contains("`bool`"))
+        );
+
+        let single = "pub enum Mode { Fast, Safe }
+pub fn run(mode: Mode) -> u8 { match mode { Mode::Fast => 1, Mode::Safe => 2 } }
+pub fn relay(mode: Mode) -> u8 { let again = matches!(mode, Mode::Fast); run(mode) }";
+        assert!(matches!(
+            rewrite(single, "run").unwrap_err(),
+            Refusal::StillUsed { snippet, .. } if snippet == "mode"
+        ));
+        let Refusal::CallSite { reason, .. } = refusal("run") else {
+            panic!()
+        };
+        assert!(
+            reason.contains("`mode` is not a flag parameter"),
+            "{}",
+            reason
+        );
+
+        let kept = "pub enum Mode { Fast, Safe }
+pub fn run(mode: Mode) -> u8 { match mode { Mode::Fast => 1, Mode::Safe => 2 } }
+pub fn default_mode() -> Mode { Mode::Safe }";
+        assert!(matches!(
+            rewrite(kept, "run").unwrap_err(),
+            Refusal::StillUsed { snippet, location } if snippet == "Mode::Safe" && location.line == 3
+        ));
+    }
+}
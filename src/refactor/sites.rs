@@ -0,0 +1,291 @@
+//! ./refactor/sites.rs
+//!
+//! Finding the calls and imports a refactoring has to rewrite, and editing
+//! source by whole lines or list entries so the result keeps its layout.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::PathBuf;
+
+use proc_macro2::Span;
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+
+use super::Patch;
+use super::patch::span_range;
+use crate::analysis::modules::{Crate, Function};
+use crate::analysis::{Location, SourceFile, macro_args};
+
+/// A function outside test modules named by its path or a suffix of it.
+pub(super) fn find_function<'c>(krate: &'c Crate, wanted: &str) -> Option<Function<'c>> {
+    let wanted = wanted.strip_prefix("crate::").unwrap_or(wanted);
+    krate
+        .functions()
+        .filter(|function| !krate.modules[function.module].test)
+        .find(|function| {
+            let path = krate.function_path(function);
+            let path = path.strip_prefix("crate::").unwrap_or(&path);
+            path == wanted || path.ends_with(&format!("::{}", wanted))
+        })
+}
+
+/// The function with exactly this full path, test modules included.
+pub(super) fn function_at<'c>(krate: &'c Crate, path: &str) -> Option<Function<'c>> {
+    krate
+        .functions()
+        .find(|function| krate.function_path(function) == path)
+}
+
+/// `path` as written from inside the module `from`, both full paths:
+/// a child needs no prefix, a sibling of an ancestor goes through `super`.
+pub(super) fn relative_path(from: &str, path: &str) -> String {
+    let from: Vec<&str> = from.split("::").collect();
+    let to: Vec<&str> = path.split("::").collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+    if common == from.len() {
+        to[common..].join("::")
+    } else if common <= 1 {
+        path.to_string()
+    } else {
+        let mut segments = vec!["super"; from.len() - common];
+        segments.extend(&to[common..]);
+        segments.join("::")
+    }
+}
+
+/// The whole lines `span` covers, with the blank line after it when it
+/// opens a block. Just the span when it shares a line with other code.
+pub(super) fn line_range(text: &str, span: Span) -> Range<usize> {
+    let range = span_range(text, span);
+    let start = text[..range.start].rfind('\n').map_or(0, |at| at + 1);
+    let mut end = text[range.end..]
+        .find('\n')
+        .map_or(text.len(), |at| range.end + at + 1);
+    if !text[start..range.start].trim().is_empty() || !text[range.end..end].trim().is_empty() {
+        return range;
+    }
+    let opens_block = text[..start].trim_end().ends_with('{');
+    if opens_block && text[end..].starts_with('\n') {
+        end += 1;
+    }
+    start..end
+}
+
+/// The whole lines of an item together with the `//` comments right above
+/// it, which describe what is being replaced.
+pub(super) fn item_lines(text: &str, span: Span) -> Range<usize> {
+    let range = line_range(text, span);
+    let mut start = range.start;
+    if start > 0 && !text[..start].ends_with('\n') {
+        return range;
+    }
+    while let Some(previous) = text[..start].strip_suffix('\n') {
+        let line_start = previous.rfind('\n').map_or(0, |at| at + 1);
+        if !previous[line_start..].trim_start().starts_with("//") {
+            break;
+        }
+        start = line_start;
+    }
+    start..range.end
+}
+
+/// Indentation of the line `span` starts on.
+pub(super) fn indentation(text: &str, span: Span) -> String {
+    let start = span_range(text, span).start;
+    let line_start = text[..start].rfind('\n').map_or(0, |at| at + 1);
+    text[line_start..]
+        .chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .collect()
+}
+
+/// Source lines moved to another depth: the common indentation is
+/// replaced by `indent`, blank lines inside stay empty and those around
+/// are dropped.
+pub(super) fn reindent(text: &str, indent: &str) -> String {
+    let blank = |line: &&str| line.trim().is_empty();
+    let mut lines: Vec<&str> = text.lines().skip_while(blank).collect();
+    while lines.last().is_some_and(blank) {
+        lines.pop();
+    }
+    let common = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| {
+            if line.trim().is_empty() {
+                "\n".to_string()
+            } else {
+                format!("{}{}\n", indent, line[common..].trim_end())
+            }
+        })
+        .collect()
+}
+
+/// Replaces entry `index` of a comma separated list by `text`, or removes
+/// it together with its comma when `text` is empty.
+pub(super) fn replace_in_list(
+    patch: &mut Patch,
+    file: &SourceFile,
+    spans: &[Span],
+    index: usize,
+    text: &str,
+) {
+    let range = |span: Span| span_range(file.text(), span);
+    if !text.is_empty() {
+        patch.replace(file, spans[index], text);
+    } else if index + 1 < spans.len() {
+        let range = range(spans[index]).start..range(spans[index + 1]).start;
+        patch.replace_range(file, range, "");
+    } else if index > 0 {
+        let range = range(spans[index - 1]).end..range(spans[index]).end;
+        patch.replace_range(file, range, "");
+    } else {
+        patch.replace(file, spans[index], "");
+    }
+}
+
+/// A `use` tree without the `names` it imports, `None` when nothing is
+/// left. `a::{self}` comes out as `a`.
+pub(super) fn use_without(tree: &syn::UseTree, names: &[&str]) -> Option<String> {
+    let kept = |ident: &syn::Ident| !names.iter().any(|name| ident == name);
+    match tree {
+        syn::UseTree::Path(tree) => match use_without(&tree.tree, names)?.as_str() {
+            "self" => Some(tree.ident.to_string()),
+            rest => Some(format!("{}::{}", tree.ident, rest)),
+        },
+        syn::UseTree::Name(tree) => kept(&tree.ident).then(|| tree.ident.to_string()),
+        syn::UseTree::Rename(tree) => {
+            kept(&tree.ident).then(|| format!("{} as {}", tree.ident, tree.rename))
+        }
+        syn::UseTree::Glob(_) => Some("*".to_string()),
+        syn::UseTree::Group(group) => {
+            let items: Vec<String> = group
+                .items
+                .iter()
+                .filter_map(|item| use_without(item, names))
+                .collect();
+            match items.as_slice() {
+                [] => None,
+                [item] => Some(item.clone()),
+                items => Some(format!("{{{}}}", items.join(", "))),
+            }
+        }
+    }
+}
+
+/// A call, keyed by where the called path or method name is.
+pub(super) struct Call {
+    /// Index into [`Crate::files`].
+    pub file: usize,
+    /// The whole call expression.
+    pub span: Span,
+    pub args: Vec<syn::Expr>,
+    pub method: bool,
+}
+
+/// Every call and `use` in a crate, so ripple sites can be matched to them.
+pub(super) struct Calls {
+    file: usize,
+    path: PathBuf,
+    pub found: BTreeMap<Location, Call>,
+    /// `use` declarations by the location of their tree, with their file.
+    pub uses: BTreeMap<Location, (usize, syn::ItemUse)>,
+}
+
+impl Calls {
+    pub fn collect(krate: &Crate) -> Self {
+        let mut calls = Calls {
+            file: 0,
+            path: PathBuf::new(),
+            found: BTreeMap::new(),
+            uses: BTreeMap::new(),
+        };
+        for (index, file) in krate.files.iter().enumerate() {
+            calls.file = index;
+            calls.path = file.path().to_path_buf();
+            calls.visit_file(file.syntax());
+        }
+        calls
+    }
+
+    fn add(&mut self, at: Span, span: Span, args: Vec<syn::Expr>, method: bool) {
+        let call = Call {
+            file: self.file,
+            span,
+            args,
+            method,
+        };
+        self.found.insert(Location::new(&self.path, at), call);
+    }
+}
+
+impl<'ast> Visit<'ast> for Calls {
+    fn visit_expr_call(&mut self, call: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(func) = &*call.func {
+            let args = call.args.iter().cloned().collect();
+            self.add(func.path.span(), call.span(), args, false);
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast syn::ExprMethodCall) {
+        let args = call.args.iter().cloned().collect();
+        self.add(call.method.span(), call.span(), args, true);
+        visit::visit_expr_method_call(self, call);
+    }
+
+    fn visit_item_use(&mut self, item: &'ast syn::ItemUse) {
+        let location = Location::new(&self.path, item.tree.span());
+        self.uses.insert(location, (self.file, item.clone()));
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        for arg in macro_args(mac) {
+            self.visit_expr(&arg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_are_written_from_the_module_that_uses_them() {
+        let from = "crate::coupling::report_generator";
+        assert_eq!(
+            relative_path(from, "crate::coupling::formatter::Formatter"),
+            "super::formatter::Formatter"
+        );
+        assert_eq!(
+            relative_path(from, "crate::coupling::report_generator::inner::Item"),
+            "inner::Item"
+        );
+        assert_eq!(
+            relative_path(from, "crate::other::Item"),
+            "crate::other::Item"
+        );
+    }
+
+    #[test]
+    fn imports_lose_one_name() {
+        let without = |source: &str| {
+            let item: syn::ItemUse = syn::parse_str(source).unwrap();
+            use_without(&item.tree, &["Format", "format_data"])
+        };
+        assert_eq!(
+            without("use super::formatter::{self, Format};").as_deref(),
+            Some("super::formatter")
+        );
+        assert_eq!(
+            without("use a::{b::Format, c, d::*};").as_deref(),
+            Some("a::{c, d::*}")
+        );
+        assert_eq!(without("use formatter::{format_data, Format};"), None);
+    }
+}
@@ -0,0 +1,769 @@
+//! ./slides.rs
+//!
+//! Turns the catalog into the talk's slide deck, as Markdown for Marp or
+//! as one HTML file laid out like a reveal.js deck.
+//!
+//! Each example gets a title slide from its `//!` docs and a slide per
+//! snippet, the snippets named by item path and cut from the source the
+//! catalog compiles in, so the slides show the code as it is. The deck
+//! closes with both ladders in the order `cohesion.rs` and `coupling.rs`
+//! list them. Speaker notes from [`notes`] go under each title slide.
+
+pub mod notes;
+
+use std::fmt::{self, Write};
+
+use syn::spanned::Spanned;
+use syn::{ImplItem, Item};
+
+use crate::analysis::dashboard::escape;
+use crate::analysis::{self, SourceFile};
+use crate::catalog::{Catalog, Example, Kind};
+use notes::{Notes, Plan};
+
+/// The code each example shows, by item path inside its module. A type
+/// brings its inherent impls along.
+struct Snippets {
+    example: &'static str,
+    problem: &'static [&'static str],
+    solution: &'static [&'static str],
+}
+
+static SNIPPETS: [Snippets; 13] = [
+    Snippets {
+        example: "function_cohesion",
+        problem: &[],
+        solution: &["circle_geometry"],
+    },
+    Snippets {
+        example: "sequence_cohesion",
+        problem: &[],
+        solution: &["text_processor"],
+    },
+    Snippets {
+        example: "communicational_cohesion",
+        problem: &["customer::CustomerProfile"],
+        solution: &["reporting_module"],
+    },
+    Snippets {
+        example: "procedural_cohesion",
+        problem: &["report_generation"],
+        solution: &["report_parts"],
+    },
+    Snippets {
+        example: "temporal_cohesion",
+        problem: &["system"],
+        solution: &["system_v2"],
+    },
+    Snippets {
+        example: "logical_cohesion",
+        problem: &["logging"],
+        solution: &["loggers"],
+    },
+    Snippets {
+        example: "coincidental_cohesion",
+        problem: &["utils"],
+        solution: &["math_utils"],
+    },
+    Snippets {
+        example: "data_coupling",
+        problem: &[],
+        solution: &["basket::Product", "display::format_price"],
+    },
+    Snippets {
+        example: "stamp_coupling",
+        problem: &["notification::send_telemetry"],
+        solution: &["notification_v2::send_telemetry"],
+    },
+    Snippets {
+        example: "control_coupling",
+        problem: &[
+            "formatter::format_data",
+            "report_generator::generate_report",
+        ],
+        solution: &[
+            "traits::Formatter",
+            "html_formatter",
+            "report_generator_v2::generate_report",
+        ],
+    },
+    Snippets {
+        example: "external_coupling",
+        problem: &["data_loader", "data_processor::process_records"],
+        solution: &["parser", "data_processor_v2::process_records"],
+    },
+    Snippets {
+        example: "common_coupling",
+        problem: &["problem"],
+        solution: &["State"],
+    },
+    Snippets {
+        example: "content_coupling",
+        problem: &["Storage"],
+        solution: &["Reportable", "solution"],
+    },
+];
+
+#[derive(Debug)]
+pub enum Error {
+    Source(analysis::Error),
+    /// A snippet path that names nothing in its example.
+    Missing {
+        example: String,
+        path: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Source(error) => write!(f, "{}", error),
+            Error::Missing { example, path } => {
+                write!(f, "`{}` names no item in {}", path, example)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Source(error) => Some(error),
+            Error::Missing { .. } => None,
+        }
+    }
+}
+
+impl From<analysis::Error> for Error {
+    fn from(error: analysis::Error) -> Self {
+        Error::Source(error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    Problem,
+    Solution,
+}
+
+impl Variant {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Variant::Problem => "problem",
+            Variant::Solution => "solution",
+        }
+    }
+}
+
+/// A place on a ladder as `cohesion.rs` or `coupling.rs` lists it, with
+/// the comment next to it if there is one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rung {
+    pub name: String,
+    pub note: Option<String>,
+}
+
+pub enum Slide {
+    /// Opens a ladder with the `//!` docs of `cohesion.rs` or `coupling.rs`.
+    Ladder {
+        kind: Kind,
+        text: Vec<String>,
+    },
+    /// Opens an example with the paragraphs of its `//!` docs, and the
+    /// presenter's notes on it if there are any.
+    Title {
+        example: &'static Example,
+        text: Vec<String>,
+        notes: Option<Notes>,
+    },
+    Snippet {
+        example: &'static Example,
+        variant: Variant,
+        path: String,
+        code: String,
+    },
+    Ranking {
+        ladders: Vec<(Kind, Vec<Rung>)>,
+    },
+}
+
+pub struct Deck {
+    pub slides: Vec<Slide>,
+}
+
+impl Deck {
+    pub fn build(plan: &Plan) -> Result<Deck, Error> {
+        let mut slides = Vec::new();
+        let mut ladders = Vec::new();
+        for kind in [Kind::Cohesion, Kind::Coupling] {
+            slides.push(Slide::Ladder {
+                kind,
+                text: paragraphs(ladder_source(kind)),
+            });
+            for example in Catalog::of_kind(kind) {
+                slides.push(Slide::Title {
+                    example,
+                    text: paragraphs(example.source()),
+                    notes: plan.get(example.name()).cloned(),
+                });
+                let snippets = SNIPPETS
+                    .iter()
+                    .find(|snippets| snippets.example == example.name());
+                let Some(snippets) = snippets else {
+                    continue;
+                };
+                for (variant, paths) in [
+                    (Variant::Problem, snippets.problem),
+                    (Variant::Solution, snippets.solution),
+                ] {
+                    for path in paths {
+                        slides.push(Slide::Snippet {
+                            example,
+                            variant,
+                            path: path.to_string(),
+                            code: snippet(example, path)?,
+                        });
+                    }
+                }
+            }
+            ladders.push((kind, ranking(kind)));
+        }
+        slides.push(Slide::Ranking { ladders });
+        Ok(Deck { slides })
+    }
+
+    /// Markdown for Marp, slides separated by `---`.
+    pub fn to_marp(&self) -> String {
+        let mut out = String::from("---\nmarp: true\npaginate: true\n---\n");
+        for (index, slide) in self.slides.iter().enumerate() {
+            if index > 0 {
+                out.push_str("\n---\n");
+            }
+            out.push('\n');
+            match slide {
+                Slide::Ladder { kind, text } => {
+                    let _ = writeln!(out, "<!-- _class: lead -->\n");
+                    let _ = writeln!(out, "# {}", capitalize(kind.as_str()));
+                    for paragraph in text {
+                        let _ = writeln!(out, "\n{}", paragraph);
+                    }
+                }
+                Slide::Title {
+                    example,
+                    text,
+                    notes,
+                } => {
+                    let _ = writeln!(out, "<!-- _class: lead -->\n");
+                    let _ = writeln!(out, "# {}\n", title(example));
+                    let _ = writeln!(out, "{}", place(example));
+                    for (index, paragraph) in text.iter().enumerate() {
+                        match index {
+                            0 => {
+                                let _ = writeln!(out, "\n{}", paragraph);
+                            }
+                            _ => {
+                                let _ = writeln!(out, "\n*{}*", paragraph);
+                            }
+                        }
+                    }
+                    // Marp shows comments other than directives as the
+                    // presenter notes of their slide.
+                    if let Some(notes) = notes {
+                        let text = speaker_notes(notes).replace("-->", "- ->");
+                        let _ = writeln!(out, "\n<!--\n{}\n-->", text);
+                    }
+                }
+                Slide::Snippet {
+                    example,
+                    variant,
+                    path,
+                    code,
+                } => {
+                    let _ = writeln!(out, "## {}: {}\n", title(example), variant.as_str());
+                    let _ = writeln!(out, "`{}`\n", item_path(example, path));
+                    let _ = writeln!(out, "```rust\n{}\n```", code);
+                }
+                Slide::Ranking { ladders } => {
+                    let _ = writeln!(out, "## Ranking");
+                    for (kind, rungs) in ladders {
+                        let _ = writeln!(out, "\n### {}\n", capitalize(kind.as_str()));
+                        for (rank, rung) in rungs.iter().enumerate() {
+                            let _ = write!(out, "{}. {}", rank + 1, rung.name);
+                            if let Some(note) = &rung.note {
+                                let _ = write!(out, " ({})", note);
+                            }
+                            out.push('\n');
+                        }
+                    }
+                }
+            }
+        }
+        out
+    }
+
+    /// One HTML file holding the deck as reveal.js lays it out, a
+    /// `section` per slide with its notes in an `aside`, and the styles and
+    /// the keyboard navigation inline.
+    pub fn to_reveal(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "<!DOCTYPE html>");
+        let _ = writeln!(out, "<html lang=\"en\">");
+        let _ = writeln!(out, "<head>");
+        let _ = writeln!(out, "<meta charset=\"utf-8\">");
+        let _ = writeln!(out, "<title>Coupling and cohesion</title>");
+        let _ = writeln!(out, "<style>{}</style>", STYLE);
+        let _ = writeln!(out, "</head>");
+        let _ = writeln!(out, "<body>");
+        let _ = writeln!(out, "<div class=\"reveal\">");
+        let _ = writeln!(out, "<div class=\"slides\">");
+        for slide in &self.slides {
+            match slide {
+                Slide::Ladder { kind, text } => {
+                    let _ = writeln!(out, "<section class=\"lead\">");
+                    let _ = writeln!(out, "<h1>{}</h1>", capitalize(kind.as_str()));
+                    for paragraph in text {
+                        let _ = writeln!(out, "<p>{}</p>", escape(paragraph));
+                    }
+                }
+                Slide::Title {
+                    example,
+                    text,
+                    notes,
+                } => {
+                    let _ = writeln!(out, "<section class=\"lead\">");
+                    let _ = writeln!(out, "<h1>{}</h1>", title(example));
+                    let _ = writeln!(out, "<p class=\"place\">{}</p>", place(example));
+                    for (index, paragraph) in text.iter().enumerate() {
+                        match index {
+                            0 => {
+                                let _ = writeln!(out, "<p>{}</p>", escape(paragraph));
+                            }
+                            _ => {
+                                let _ = writeln!(out, "<p><em>{}</em></p>", escape(paragraph));
+                            }
+                        }
+                    }
+                    if let Some(notes) = notes {
+                        let _ = writeln!(out, "<aside class=\"notes\">");
+                        for paragraph in notes.paragraphs() {
+                            let _ = writeln!(out, "<p>{}</p>", escape(&paragraph));
+                        }
+                        if !notes.questions.is_empty() {
+                            let _ = writeln!(out, "<p>Ask:</p>");
+                            let _ = writeln!(out, "<ul>");
+                            for question in &notes.questions {
+                                let _ = writeln!(out, "<li>{}</li>", escape(question));
+                            }
+                            let _ = writeln!(out, "</ul>");
+                        }
+                        if let Some(minutes) = notes.minutes {
+                            let _ = writeln!(out, "<p>{} min</p>", minutes);
+                        }
+                        let _ = writeln!(out, "</aside>");
+                    }
+                }
+                Slide::Snippet {
+                    example,
+                    variant,
+                    path,
+                    code,
+                } => {
+                    let _ = writeln!(out, "<section>");
+                    let _ = writeln!(out, "<h2>{}: {}</h2>", title(example), variant.as_str());
+                    let _ = writeln!(out, "<p><code>{}</code></p>", item_path(example, path));
+                    let _ = writeln!(
+                        out,
+                        "<pre><code class=\"language-rust\">{}</code></pre>",
+                        escape(code)
+                    );
+                }
+                Slide::Ranking { ladders } => {
+                    let _ = writeln!(out, "<section>");
+                    let _ = writeln!(out, "<h2>Ranking</h2>");
+                    let _ = writeln!(out, "<div class=\"columns\">");
+                    for (kind, rungs) in ladders {
+                        let _ = writeln!(out, "<div>");
+                        let _ = writeln!(out, "<h3>{}</h3>", capitalize(kind.as_str()));
+                        let _ = writeln!(out, "<ol>");
+                        for rung in rungs {
+                            let _ = write!(out, "<li>{}", rung.name);
+                            if let Some(note) = &rung.note {
+                                let _ = write!(out, " <em>({})</em>", escape(note));
+                            }
+                            let _ = writeln!(out, "</li>");
+                        }
+                        let _ = writeln!(out, "</ol>");
+                        let _ = writeln!(out, "</div>");
+                    }
+                    let _ = writeln!(out, "</div>");
+                }
+            }
+            let _ = writeln!(out, "</section>");
+        }
+        let _ = writeln!(out, "</div>");
+        let _ = writeln!(out, "</div>");
+        let _ = writeln!(out, "<script>{}</script>", SCRIPT);
+        let _ = writeln!(out, "</body>");
+        let _ = writeln!(out, "</html>");
+        out
+    }
+}
+
+const STYLE: &str = "
+html, body { margin: 0; height: 100%; background: #fff; color: #222; font: 28px/1.4 system-ui, sans-serif; }
+.reveal, .slides { height: 100%; }
+.slides > section { display: none; box-sizing: border-box; height: 100%; padding: 1.5em 2em; overflow: auto; }
+.slides > section.present { display: block; }
+.slides > section.lead { display: none; text-align: center; padding-top: 20vh; }
+.slides > section.lead.present { display: block; }
+h1 { font-size: 2em; margin: 0 0 0.5em; }
+h2 { font-size: 1.4em; margin: 0 0 0.5em; }
+.place { color: #777; }
+pre { background: #f6f6f6; padding: 0.6em; font-size: 0.6em; overflow: auto; }
+.columns { display: flex; gap: 3em; }
+.progress { position: fixed; bottom: 0; left: 0; height: 4px; background: #1565c0; }
+aside.notes { display: none; }
+body.notes aside.notes { display: block; position: fixed; right: 0; bottom: 0; width: 40%; max-height: 50%; overflow: auto; padding: 0.5em 1em; background: #fffde7; border: 1px solid #ccc; font-size: 0.6em; text-align: left; }
+";
+
+/// Arrow keys, space and clicks move through the sections; the slide
+/// number stays in the URL fragment so a reload keeps the place. `N`
+/// shows and hides the speaker notes.
+const SCRIPT: &str = "
+const sections = Array.from(document.querySelectorAll('.slides > section'));
+const progress = document.body.appendChild(document.createElement('div'));
+progress.className = 'progress';
+let current = 0;
+function show(index) {
+  current = Math.max(0, Math.min(sections.length - 1, index));
+  sections.forEach((section, at) => section.classList.toggle('present', at === current));
+  progress.style.width = (100 * (current + 1) / sections.length) + '%';
+  history.replaceState(null, '', '#/' + current);
+}
+document.addEventListener('keydown', event => {
+  if (['ArrowRight', 'ArrowDown', 'PageDown', ' '].includes(event.key)) show(current + 1);
+  if (['ArrowLeft', 'ArrowUp', 'PageUp'].includes(event.key)) show(current - 1);
+  if (event.key === 'Home') show(0);
+  if (event.key === 'End') show(sections.length - 1);
+  if (event.key === 'n' || event.key === 'N') document.body.classList.toggle('notes');
+});
+document.addEventListener('click', event => show(current + (event.clientX < innerWidth / 3 ? -1 : 1)));
+show(parseInt(location.hash.slice(2)) || 0);
+";
+
+/// The source of the item at `path` inside an example's module, whole
+/// lines from its first line to its last, dedented. A type is followed by
+/// its inherent impls.
+///
+/// Doc comments on items at the top of the module are left out: there
+/// they mark the sections of the example (`/// Solution`, `/// Demo`)
+/// rather than document the item.
+pub fn snippet(example: &Example, path: &str) -> Result<String, Error> {
+    let file = SourceFile::parse(example.file(), example.source())?;
+    let segments: Vec<&str> = path.split("::").collect();
+    let spans = find(&file.syntax().items, &segments);
+    if spans.is_empty() {
+        return Err(Error::Missing {
+            example: example.name().to_string(),
+            path: path.to_string(),
+        });
+    }
+    let top = segments.len() == 1;
+    let lines: Vec<&str> = example.source().lines().collect();
+    let blocks: Vec<String> = spans
+        .into_iter()
+        .map(|span| {
+            let block: Vec<&str> = lines[span.start().line - 1..span.end().line]
+                .iter()
+                .copied()
+                .skip_while(|line| top && line.trim_start().starts_with("///"))
+                .collect();
+            dedent(&block)
+        })
+        .collect();
+    Ok(blocks.join("\n\n"))
+}
+
+/// Spans of the items `path` names in `items`.
+fn find(items: &[Item], path: &[&str]) -> Vec<proc_macro2::Span> {
+    match path {
+        [] => Vec::new(),
+        [name] => {
+            let Some(item) = items
+                .iter()
+                .find(|item| item_name(item).as_deref() == Some(*name))
+            else {
+                return Vec::new();
+            };
+            let mut spans = vec![item.span()];
+            if matches!(item, Item::Struct(_) | Item::Enum(_) | Item::Union(_)) {
+                spans.extend(inherent_impls(items, name).map(|item| item.span()));
+            }
+            spans
+        }
+        [first, rest @ ..] => {
+            let module = items.iter().find_map(|item| match item {
+                Item::Mod(module) if module.ident == first => module.content.as_ref(),
+                _ => None,
+            });
+            match (module, rest) {
+                (Some((_, items)), _) => find(items, rest),
+                (None, [method]) => inherent_impls(items, first)
+                    .flat_map(|item| &item.items)
+                    .find_map(|impl_item| match impl_item {
+                        ImplItem::Fn(function) if function.sig.ident == method => {
+                            Some(function.span())
+                        }
+                        _ => None,
+                    })
+                    .into_iter()
+                    .collect(),
+                (None, _) => Vec::new(),
+            }
+        }
+    }
+}
+
+fn item_name(item: &Item) -> Option<String> {
+    let ident = match item {
+        Item::Const(item) => &item.ident,
+        Item::Enum(item) => &item.ident,
+        Item::Fn(item) => &item.sig.ident,
+        Item::Mod(item) => &item.ident,
+        Item::Static(item) => &item.ident,
+        Item::Struct(item) => &item.ident,
+        Item::Trait(item) => &item.ident,
+        Item::Type(item) => &item.ident,
+        Item::Union(item) => &item.ident,
+        _ => return None,
+    };
+    Some(ident.to_string())
+}
+
+fn inherent_impls<'a>(items: &'a [Item], name: &'a str) -> impl Iterator<Item = &'a syn::ItemImpl> {
+    items.iter().filter_map(move |item| match item {
+        Item::Impl(item) if item.trait_.is_none() => match &*item.self_ty {
+            syn::Type::Path(ty) if ty.path.is_ident(name) => Some(item),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+fn dedent(lines: &[&str]) -> String {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The paragraphs of a module's `//!` docs after the `./file.rs` header,
+/// leaving out the bare `Problem` marker that introduces the code.
+fn paragraphs(source: &str) -> Vec<String> {
+    let mut paragraphs: Vec<Vec<&str>> = vec![Vec::new()];
+    for line in source
+        .lines()
+        .map_while(|line| line.strip_prefix("//!"))
+        .map(str::trim)
+        .skip_while(|line| line.starts_with("./"))
+    {
+        match (line.is_empty(), paragraphs.last_mut()) {
+            (true, _) => paragraphs.push(Vec::new()),
+            (false, Some(paragraph)) => paragraph.push(line),
+            (false, None) => {}
+        }
+    }
+    paragraphs
+        .into_iter()
+        .map(|lines| lines.join(" "))
+        .filter(|paragraph| !paragraph.is_empty() && paragraph != "Problem")
+        .collect()
+}
+
+fn ladder_source(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Cohesion => include_str!("cohesion.rs"),
+        Kind::Coupling => include_str!("coupling.rs"),
+    }
+}
+
+/// The ladder as the commented-out `// mod name;` lines of its file list
+/// it, best first.
+fn ranking(kind: Kind) -> Vec<Rung> {
+    ladder_source(kind)
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("// mod "))
+        .filter_map(|line| {
+            let (name, rest) = line.split_once(';')?;
+            let note = rest
+                .trim()
+                .strip_prefix("//")
+                .map(|note| note.trim().to_string());
+            Some(Rung {
+                name: name.trim().to_string(),
+                note,
+            })
+        })
+        .collect()
+}
+
+/// `temporal_cohesion` as `Temporal cohesion`.
+fn title(example: &Example) -> String {
+    capitalize(&example.name().replace('_', " "))
+}
+
+/// `Cohesion, 5 of 7`.
+fn place(example: &Example) -> String {
+    format!(
+        "{}, {} of {}",
+        capitalize(example.kind().as_str()),
+        example.rank(),
+        Catalog::of_kind(example.kind()).count()
+    )
+}
+
+/// The notes as plain text: the talking points, the questions to ask and
+/// the time to spend.
+fn speaker_notes(notes: &Notes) -> String {
+    let mut blocks: Vec<String> = notes.paragraphs().collect();
+    if !notes.questions.is_empty() {
+        let questions: Vec<String> = notes
+            .questions
+            .iter()
+            .map(|question| format!("- {}", question))
+            .collect();
+        blocks.push(format!("Ask:\n{}", questions.join("\n")));
+    }
+    if let Some(minutes) = notes.minutes {
+        blocks.push(format!("{} min", minutes));
+    }
+    blocks.join("\n\n")
+}
+
+fn item_path(example: &Example, path: &str) -> String {
+    format!("{}::{}::{}", example.kind().as_str(), example.name(), path)
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippets_are_cut_from_the_source_by_path() {
+        let stamp = Catalog::get("stamp_coupling").unwrap();
+        assert_eq!(
+            snippet(stamp, "notification_v2::send_telemetry").unwrap(),
+            "pub fn send_telemetry(id: u64) {\n    println!(\"Sending record with {} id\", id);\n}"
+        );
+
+        // A type comes with its impl; the `/// Solution` marker stays out.
+        let common = snippet(Catalog::get("common_coupling").unwrap(), "State").unwrap();
+        assert!(common.starts_with("#[derive(Clone, Default)]\npub struct State {"));
+        assert!(common.contains("}\n\nimpl State {\n    pub fn increment(&self) {"));
+
+        let content = Catalog::get("content_coupling").unwrap();
+        assert!(
+            snippet(content, "Storage::show_view_count")
+                .unwrap()
+                .starts_with("/// Exposed API and being consumed")
+        );
+        assert!(matches!(
+            snippet(content, "Storage::hide_view_count"),
+            Err(Error::Missing { .. })
+        ));
+    }
+
+    #[test]
+    fn deck_covers_every_example_from_the_real_source() {
+        let deck = Deck::build(&Plan::default()).unwrap();
+        for example in Catalog::examples() {
+            let slides = |variant| {
+                deck.slides
+                    .iter()
+                    .filter(|slide| {
+                        matches!(slide, Slide::Snippet { example: shown, variant: shown_variant, .. }
+                            if shown.name() == example.name() && *shown_variant == variant)
+                    })
+                    .count()
+            };
+            assert_eq!(
+                slides(Variant::Problem) > 0,
+                example.problem().is_some(),
+                "{}",
+                example.name()
+            );
+            assert!(slides(Variant::Solution) > 0, "{}", example.name());
+        }
+        let Some(Slide::Title { text, .. }) = deck.slides.get(1) else {
+            panic!("expected the title of function_cohesion");
+        };
+        assert_eq!(
+            text,
+            &[
+                "The highest and most desirable form of cohesion, where all elements within a module work together to achieve a single, well-defined task",
+                "Higest Form of Cohesion",
+            ]
+        );
+
+        let Some(Slide::Ranking { ladders }) = deck.slides.last() else {
+            panic!("expected the ranking last");
+        };
+        for (kind, rungs) in ladders {
+            let ranked: Vec<&str> = Catalog::of_kind(*kind).map(Example::name).collect();
+            let listed: Vec<&str> = rungs.iter().map(|rung| rung.name.as_str()).collect();
+            assert_eq!(listed, ranked);
+        }
+        assert_eq!(ladders[1].1.last().unwrap().note.as_deref(), Some("Worst"));
+    }
+
+    #[test]
+    fn renders_marp_and_reveal() {
+        let plan = Plan::load(env!("CARGO_MANIFEST_DIR")).unwrap();
+        let deck = Deck::build(&plan).unwrap();
+        let marp = deck.to_marp();
+        assert!(marp.starts_with("---\nmarp: true\n"));
+        assert_eq!(marp.matches("\n---\n").count(), deck.slides.len());
+        assert!(marp.contains(
+            "## Stamp coupling: solution\n\n`coupling::stamp_coupling::notification_v2::send_telemetry`\n\n```rust\npub fn send_telemetry(id: u64) {"
+        ));
+        assert!(marp.contains("### Coupling\n\n1. data_coupling (Ok)\n2. stamp_coupling\n"));
+        assert!(marp.contains(
+            "\n<!--\nStart from the startup function everyone has written: cache, logging and config, together only because they happen at boot.\n\n"
+        ));
+        assert!(marp.contains(
+            "Ask:\n- Where does your application's startup code live today?\n- What breaks first when one initialization step needs to be lazy?\n\n4 min\n-->\n"
+        ));
+
+        let reveal = deck.to_reveal();
+        assert_eq!(reveal.matches("<section").count(), deck.slides.len());
+        assert!(
+            reveal.contains("<h1>Temporal cohesion</h1>\n<p class=\"place\">Cohesion, 5 of 7</p>")
+        );
+        assert_eq!(
+            reveal.matches("<aside class=\"notes\">").count(),
+            Catalog::examples().len()
+        );
+        assert!(reveal.contains(
+            "<li>Is &quot;uses the same struct&quot; a good enough reason to live together?</li>"
+        ));
+        assert!(reveal.contains("pub fn format_data(data: String, format: Format) -&gt; String {"));
+        for external in ["http", "src=", "href=", "<link"] {
+            assert!(!reveal.contains(external), "{}", external);
+        }
+    }
+}
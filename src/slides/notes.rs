@@ -0,0 +1,233 @@
+//! ./slides/notes.rs
+//!
+//! What the presenter says about each example and how long it takes, kept
+//! next to the example as a TOML file with the same name:
+//!
+//! ```toml
+//! minutes = 4
+//! notes = "Start from the startup function everyone has written."
+//! questions = ["Where does your app's startup code live?"]
+//! ```
+//!
+//! The notes go into the deck under each example's title slide; the
+//! minutes add up into a run sheet for the talk.
+
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::analysis::Error;
+use crate::catalog::{Catalog, Example};
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Notes {
+    /// Estimated time on the example, its snippets included.
+    pub minutes: Option<u32>,
+    /// Talking points, one paragraph per blank-line separated block.
+    #[serde(default)]
+    pub notes: String,
+    /// Questions to put to the audience.
+    #[serde(default)]
+    pub questions: Vec<String>,
+}
+
+impl Notes {
+    pub fn parse(path: impl AsRef<Path>, text: &str) -> Result<Self, Error> {
+        toml::from_str(text).map_err(|source| Error::Config {
+            path: path.as_ref().to_path_buf(),
+            source,
+        })
+    }
+
+    /// The talking points split into paragraphs.
+    pub fn paragraphs(&self) -> impl Iterator<Item = String> + '_ {
+        self.notes
+            .split("\n\n")
+            .map(|paragraph| paragraph.split_whitespace().collect::<Vec<_>>().join(" "))
+            .filter(|paragraph| !paragraph.is_empty())
+    }
+}
+
+/// A gap in the notes; the deck and run sheet still build without them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+/// The notes of every example in the catalog.
+#[derive(Debug, Clone, Default)]
+pub struct Plan {
+    notes: BTreeMap<&'static str, Notes>,
+    pub warnings: Vec<Warning>,
+}
+
+impl Plan {
+    /// Reads the sidecar of every example under `dir`, the crate root.
+    /// A missing file, or one without notes or minutes, is a warning; a
+    /// malformed one an error.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        let mut plan = Plan::default();
+        for example in Catalog::examples() {
+            let path = dir.join(sidecar(example));
+            let mut warn = |message: &str| {
+                plan.warnings.push(Warning {
+                    path: path.clone(),
+                    message: format!("{} for {}", message, example.name()),
+                })
+            };
+            if !path.exists() {
+                warn("no speaker notes");
+                continue;
+            }
+            let text = fs::read_to_string(&path).map_err(|source| Error::Io {
+                path: path.clone(),
+                source,
+            })?;
+            let notes = Notes::parse(&path, &text)?;
+            if notes.notes.trim().is_empty() {
+                warn("no `notes`");
+            }
+            if notes.minutes.is_none() {
+                warn("no `minutes`, counted as 0");
+            }
+            plan.notes.insert(example.name(), notes);
+        }
+        Ok(plan)
+    }
+
+    pub fn get(&self, example: &str) -> Option<&Notes> {
+        self.notes.get(example)
+    }
+
+    pub fn minutes(&self, example: &str) -> u32 {
+        self.get(example)
+            .and_then(|notes| notes.minutes)
+            .unwrap_or(0)
+    }
+
+    /// The talk in catalog order: when each example starts, how long it
+    /// runs and what to ask, ending with the total.
+    pub fn run_sheet(&self) -> String {
+        let width = Catalog::examples()
+            .iter()
+            .map(|example| example.name().len())
+            .max()
+            .unwrap_or(0);
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{:>5}  {:>3}  {:<width$}  ladder",
+            "start", "min", "example"
+        );
+        let mut elapsed = 0;
+        for example in Catalog::examples() {
+            let minutes = self.minutes(example.name());
+            let _ = writeln!(
+                out,
+                "{:>5}  {:>3}  {:<width$}  {} {}",
+                clock(elapsed),
+                minutes,
+                example.name(),
+                example.kind().as_str(),
+                example.rank()
+            );
+            for question in self
+                .get(example.name())
+                .into_iter()
+                .flat_map(|notes| &notes.questions)
+            {
+                let _ = writeln!(out, "{:>5}  {:>3}  ? {}", "", "", question);
+            }
+            elapsed += minutes;
+        }
+        let _ = writeln!(out, "{:>5}  {:>3}  end", clock(elapsed), elapsed);
+        out
+    }
+}
+
+/// Where the notes of an example live, relative to the crate root: its
+/// source file with a `.toml` extension.
+pub fn sidecar(example: &Example) -> PathBuf {
+    Path::new(example.file()).with_extension("toml")
+}
+
+/// Minutes into the talk as `h:mm`.
+fn clock(minutes: u32) -> String {
+    format!("{}:{:02}", minutes / 60, minutes % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_example_has_notes() {
+        let plan = Plan::load(env!("CARGO_MANIFEST_DIR")).unwrap();
+        assert_eq!(plan.warnings, Vec::new());
+        for example in Catalog::examples() {
+            let notes = plan.get(example.name()).unwrap();
+            assert!(!notes.questions.is_empty(), "{}", example.name());
+        }
+    }
+
+    #[test]
+    fn missing_notes_are_warnings_and_count_as_no_time() {
+        let dir = std::env::temp_dir().join(format!("slides-notes-{}", std::process::id()));
+        let write = |example: &str, text: &str| {
+            let path = dir.join(sidecar(Catalog::get(example).unwrap()));
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, text).unwrap();
+        };
+        write(
+            "function_cohesion",
+            "minutes = 3\nnotes = \"\"\"\nOne job.\n\nAsk around.\n\"\"\"\nquestions = [\"Why?\"]",
+        );
+        write("sequence_cohesion", "notes = \"A pipeline.\"");
+        write("data_coupling", "minutes = 70\nnotes = \"Just values.\"");
+
+        let plan = Plan::load(&dir).unwrap();
+        let messages: Vec<String> = plan
+            .warnings
+            .iter()
+            .map(|warning| warning.message.clone())
+            .collect();
+        assert_eq!(messages.len(), 11);
+        assert_eq!(
+            messages[0],
+            "no `minutes`, counted as 0 for sequence_cohesion"
+        );
+        assert_eq!(messages[1], "no speaker notes for communicational_cohesion");
+        assert_eq!(
+            plan.warnings[1].path,
+            dir.join("src/cohesion/communicational_cohesion.toml")
+        );
+        assert_eq!(
+            plan.get("function_cohesion")
+                .unwrap()
+                .paragraphs()
+                .collect::<Vec<_>>(),
+            ["One job.", "Ask around."]
+        );
+
+        let sheet = plan.run_sheet();
+        assert!(sheet.contains(" 0:00    3  function_cohesion         cohesion 1\n            ? Why?\n 0:03    0  sequence_cohesion"));
+        assert!(sheet.contains(" 0:03   70  data_coupling"));
+        assert!(sheet.ends_with(" 1:13   73  end\n"));
+
+        write("stamp_coupling", "minute = 2");
+        assert!(matches!(Plan::load(&dir), Err(Error::Config { .. })));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}